@@ -28,6 +28,7 @@ import type {
     PreviewDeck,
     PreviewNotes,
     ReferencesDiff,
+    SavedSearch,
     SlimDeck,
     State,
     StateChangeAddPreview,
@@ -39,12 +40,14 @@ import type {
     StateChangeGraph,
     StateChangeInputGiven,
     StateChangeKeyDown,
+    StateChangeLiveChange,
     StateChangeMode,
     StateChangeNoteForm,
     StateChangeNoteRefsModified,
     StateChangePageState,
     StateChangeRecentImages,
     StateChangeRecentlyUsedDecks,
+    StateChangeSavedSearches,
     StateChangeSetFocus,
     StateChangeSetSearch,
     StateChangeShowShortcuts,
@@ -162,6 +165,9 @@ broadcastChannel.onmessage = (event) => {
         case "setRecentlyUsedDecks":
             AppStateChange.setRecentlyUsedDecks(args);
             break;
+        case "setSavedSearches":
+            AppStateChange.setSavedSearches(args);
+            break;
         case "setReviewCount":
             AppStateChange.setReviewCount(args);
             break;
@@ -257,9 +263,12 @@ const state: State = {
 
     bookmarks: signal([]),
     bookmarksMinimised: signal(false),
+    savedSearches: signal([]),
 
     memoriseReviewCount: signal(0),
     memoriseEarliestReviewDate: signal(undefined),
+
+    liveChange: signal(undefined),
 };
 
 export const initialState = state;
@@ -582,6 +591,12 @@ export const AppStateChange = {
         state.memoriseEarliestReviewDate.value =
             uber.memoriseEarliestReviewDate;
         state.bookmarks.value = uber.bookmarks;
+        state.savedSearches.value = uber.savedSearches;
+    }),
+
+    liveChange: build(Scope.Local, "liveChange", (asca?: AppStateChangeArgs) => {
+        let args = asca! as StateChangeLiveChange;
+        state.liveChange.value = args.change;
     }),
 
     userLogin: build(Scope.Local, "userLogin", (asca?: AppStateChangeArgs) => {
@@ -743,6 +758,16 @@ export const AppStateChange = {
         },
     ),
 
+    setSavedSearches: build(
+        Scope.Broadcast,
+        "setSavedSearches",
+        (asca?: AppStateChangeArgs) => {
+            let args = asca! as StateChangeSavedSearches;
+            const savedSearches: Array<SavedSearch> = args.savedSearches;
+            state.savedSearches.value = savedSearches;
+        },
+    ),
+
     setRecentlyUsedDecks: build(
         Scope.Broadcast,
         "setRecentlyUsedDecks",
@@ -4,6 +4,7 @@ import type { State, UserWithUiConfig, UberSetup, UiConfig } from "./types";
 
 import { AppStateChange, AppStateProvider, getAppState } from "./app-state";
 
+import { startLiveUpdates } from "./shared/live";
 import Net from "./shared/net";
 
 import Previewer from "./components/previewer";
@@ -71,6 +72,7 @@ const AppUI = () => {
 
         Net.get<UberSetup>("/api/ubersetup").then((uber) => {
             AppStateChange.uberSetup({ uber });
+            startLiveUpdates();
             route("/", true);
         });
     }
@@ -1,11 +1,10 @@
 import { NoteKind, WaitingFor } from "../enums";
 import type { FatDeck, Note } from "../types";
 
-import { AppStateChange, getAppState } from "../app-state";
+import { AppStateChange } from "../app-state";
 
 import Net from "../shared/net";
 
-import buildSimplifiedText from "./build-simplified-text";
 import CivilButton from "./civil-button";
 
 type AutoSummarizeProps = {
@@ -14,16 +13,8 @@ type AutoSummarizeProps = {
 };
 
 export default function AutoSummarize({ deck, onFinish }: AutoSummarizeProps) {
-    const appState = getAppState();
-    const wasmInterface = appState.wasmInterface!;
-
     function onClick() {
         if (deck.passage[NoteKind.Note]) {
-            let textPassage = deck.passage[NoteKind.Note].map((n) =>
-                buildSimplifiedText(n.content, wasmInterface),
-            );
-            let textPassageContent = textPassage.join("\n");
-
             let summaryPassage = deck.passage[NoteKind.NoteSummary];
             let prevId: number | undefined = undefined;
             if (summaryPassage.length > 0) {
@@ -33,14 +24,14 @@ export default function AutoSummarize({ deck, onFinish }: AutoSummarizeProps) {
 
             type SummarizeStruct = {
                 prevId?: number;
-                content: string;
             };
 
             let summarizeStruct: SummarizeStruct = {
                 prevId,
-                content: textPassageContent,
             };
 
+            // the server builds the text from the deck's notes, leaving out any private ones
+            //
             // returns the newly created NoteSummary
             AppStateChange.setWaitingFor({ waitingFor: WaitingFor.Server });
             Net.post<SummarizeStruct, Note>(
@@ -1,8 +1,12 @@
-import type { Bookmark } from "../types";
+import { route } from "preact-router";
+
+import type { Bookmark, SavedSearch } from "../types";
 
 import { AppStateChange, getAppState } from "../app-state";
 
-import { svgChevronDown, svgChevronUp } from "./svg-icons";
+import { deleteSavedSearch } from "../shared/saved-searches";
+
+import { svgChevronDown, svgChevronUp, svgX } from "./svg-icons";
 import ViewBookmark from "./view-bookmark";
 
 export default function Bookmarks() {
@@ -12,16 +16,27 @@ export default function Bookmarks() {
         return <ViewBookmark bookmark={bookmark} />;
     }
 
+    function buildSmartDeck(savedSearch: SavedSearch) {
+        return <ViewSmartDeck savedSearch={savedSearch} />;
+    }
+
     function clickedToggle() {
         AppStateChange.bookmarkToggle();
     }
 
-    if (!!appState.bookmarks.value.length) {
+    if (
+        !!appState.bookmarks.value.length ||
+        !!appState.savedSearches.value.length
+    ) {
+        const smartDecks = appState.savedSearches.value.map(buildSmartDeck);
         const bookmarks = appState.bookmarks.value.map(buildBookmark);
         return (
             <div id="bookmark-component">
                 {!appState.bookmarksMinimised.value && (
-                    <ul id="bookmarks">{bookmarks}</ul>
+                    <ul id="bookmarks">
+                        {smartDecks}
+                        {bookmarks}
+                    </ul>
                 )}
                 {appState.bookmarksMinimised.value ? (
                     <div class="bookmark-menu">
@@ -48,3 +63,34 @@ export default function Bookmarks() {
         return <div></div>;
     }
 }
+
+// a pinned saved search, clicking on it re-runs the search
+//
+function ViewSmartDeck({ savedSearch }: { savedSearch: SavedSearch }) {
+    function clickedDelete(e: Event) {
+        e.stopPropagation();
+        deleteSavedSearch(savedSearch.id);
+    }
+
+    function clicked() {
+        route(`/search?q=${encodeURIComponent(savedSearch.query)}`, true);
+    }
+
+    return (
+        <li
+            key={`saved-search-${savedSearch.id}`}
+            class="c-view-bookmark"
+            onClick={clicked}
+        >
+            <div class="c-view-bookmark-remove" onClick={clickedDelete}>
+                {svgX()}
+            </div>
+            <span
+                class="bookmark-text smart-deck-text"
+                title={savedSearch.query}
+            >
+                {savedSearch.name}
+            </span>
+        </li>
+    );
+}
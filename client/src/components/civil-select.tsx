@@ -42,6 +42,7 @@ enum ActionType {
     SelectAdd,
     SelectCreate,
     ShortcutCheck,
+    SuggestionsSet,
 }
 
 type ActionDataReferenceChangeKind = {
@@ -83,6 +84,7 @@ type LocalState = {
     text: string;
     showKeyboardShortcuts: boolean;
     candidates: Array<SlimDeck>;
+    suggested: boolean; // the candidates are similarly spelt decks rather than matches
     canSave: boolean;
     nakedCtrlPress: boolean;
     justAddedViaShortcut: boolean;
@@ -353,6 +355,13 @@ function reducer(state: LocalState, action: Action): LocalState {
             return {
                 ...state,
                 candidates: action.data as SlimDeck[],
+                suggested: false,
+            };
+        case ActionType.SuggestionsSet:
+            return {
+                ...state,
+                candidates: action.data as SlimDeck[],
+                suggested: true,
             };
         case ActionType.AbsorbInput:
             return {
@@ -406,6 +415,7 @@ export default function CivilSelect({
 
         showKeyboardShortcuts: false,
         candidates: [],
+        suggested: false,
         canSave: false,
         nakedCtrlPress: false,
         justAddedViaShortcut: false,
@@ -504,11 +514,19 @@ export default function CivilSelect({
                                 slimDeck.id !== parentDeckId &&
                                 !alreadySelected(slimDeck.title)
                             );
-                        })
-                        .sort((a, b) => {
+                        });
+                    if (searchResponse.didYouMean) {
+                        // already ordered by similarity
+                        localDispatch(
+                            ActionType.SuggestionsSet,
+                            newCandidates,
+                        );
+                    } else {
+                        newCandidates.sort((a, b) => {
                             return a.title.length - b.title.length;
                         });
-                    localDispatch(ActionType.CandidatesSet, newCandidates);
+                        localDispatch(ActionType.CandidatesSet, newCandidates);
+                    }
                 }
             } else {
                 localDispatch(ActionType.CandidatesSet, []);
@@ -574,6 +592,7 @@ export default function CivilSelect({
                 text={local.text}
                 onTextChanged={onTextChanged}
                 candidates={local.candidates}
+                suggested={local.suggested}
                 onAdd={(existingDeck) => {
                     localDispatch(ActionType.SelectAdd, existingDeck);
                 }}
@@ -736,6 +755,7 @@ type CivilSelectInputProps = {
     onAdd: (c: SlimDeck) => void;
     onCreate: (r: Reference) => void;
     candidates: Array<SlimDeck>;
+    suggested: boolean;
     showKeyboardShortcuts: boolean;
 };
 
@@ -745,11 +765,19 @@ function CivilSelectInput({
     onAdd,
     onCreate,
     candidates,
+    suggested,
     showKeyboardShortcuts,
 }: CivilSelectInputProps) {
     function onSubmit(e: Event) {
         e.preventDefault();
-        if (text.length > 0) {
+
+        // don't create a duplicate of a deck that already exists
+        const existing = candidates.find(
+            (c) => c.title.toLowerCase() === text.trim().toLowerCase(),
+        );
+        if (existing) {
+            onAdd(existing);
+        } else if (text.length > 0) {
             // treat this text as a new idea that needs to be created
             //
             // should really be a ProtoReference, but there's a concat
@@ -796,6 +824,9 @@ function CivilSelectInput({
                 autoComplete="off"
                 onContentChange={onContentChange}
             />
+            {suggested && cl.length > 0 && (
+                <div class="c-candidate-suggestion">did you mean:</div>
+            )}
             {cl}
         </form>
     );
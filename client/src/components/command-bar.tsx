@@ -14,7 +14,7 @@ import type { DeckQuote, Key, SlimDeck, State, SearchResults } from "../types";
 
 import { AppStateChange, getAppState } from "../app-state";
 
-import { sanitize } from "../shared/search";
+import { sanitize, sanitizeQuery } from "../shared/search";
 import { addBookmark, addMultipleBookmarks } from "../shared/bookmarks";
 import { isCivilModeAllowed } from "../shared/civil";
 import { indexToShortcut, isCommand } from "../shared/command";
@@ -318,9 +318,11 @@ export default function CommandBar() {
                         AppStateChange.commandBarResetAndHide();
                     }
                 } else if (commandBarState.mode === CommandBarMode.Search) {
-                    let sanitized: string = sanitize(commandBarState.text);
+                    let sanitized: string = sanitizeQuery(
+                        commandBarState.text,
+                    );
                     if (sanitized.length > 0) {
-                        route(`/search?q=${encodeURI(sanitized)}`);
+                        route(`/search?q=${encodeURIComponent(sanitized)}`);
                         AppStateChange.commandBarResetAndHide();
                     }
                 }
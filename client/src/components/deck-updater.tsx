@@ -1,7 +1,7 @@
 import { useEffect, useState } from "preact/hooks";
 
 import { Font } from "../enums";
-import type { FatDeck, ProtoSlimDeck, SlimDeck } from "../types";
+import type { FatDeck, ProtoSlimDeck } from "../types";
 
 import {
     deckKindToResourceString,
@@ -18,7 +18,7 @@ import FontSelector from "./font-selector";
 import InsigniaSelector from "./insignia-selector";
 
 type DeckUpdaterProps<T extends FatDeck> = {
-    deck: SlimDeck;
+    deck: FatDeck;
     onUpdate: (d: T) => void;
     onCancel: () => void;
 };
@@ -65,11 +65,13 @@ const DeckUpdater = <T extends FatDeck>({
 
         const resource = deckKindToResourceString(deck.deckKind);
 
-        Net.put<ProtoSlimDeck, T>(`/api/${resource}/${deck.id}`, data).then(
-            (newDeck) => {
-                onUpdate(newDeck);
-            },
-        );
+        Net.put<ProtoSlimDeck, T>(
+            `/api/${resource}/${deck.id}`,
+            data,
+            deck.version,
+        ).then((newDeck) => {
+            onUpdate(newDeck);
+        });
 
         event.preventDefault();
     }
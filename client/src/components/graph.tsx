@@ -35,8 +35,12 @@ type ConnectivityData = {
     sourceDeck: SlimDeck;
     edges: Array<Edge>;
     decks: Array<SlimDeck>;
+    truncated: boolean;
 };
 
+// the server won't expand a graph any further than this
+const maxGraphDepth = 4;
+
 type LocalState = {
     activeHyperlinks: boolean;
     mouseButtonDown: boolean;
@@ -127,6 +131,7 @@ export default function Graph({ id }: { id: Key }) {
         arcArray: [],
     };
     const [graphState, setGraphState] = useState(initialState);
+    const [depth, setDepth] = useState(1);
     const svgContainerRef = createRef();
     const [local, localDispatch] = useLocalReducer<LocalState, ActionType>(
         reducer,
@@ -244,15 +249,16 @@ export default function Graph({ id }: { id: Key }) {
         return false;
     }
 
-    function fetchGraphStateData(id: number) {
-        Net.get<ConnectivityData>(`/api/graph/${id}`).then((data) => {
+    function fetchGraphStateData(id: number, depth: number) {
+        const url = `/api/graph/${id}?depth=${depth}`;
+        Net.get<ConnectivityData>(url).then((data) => {
             applyConnectivityData(data);
         });
     }
 
     useEffect(() => {
-        fetchGraphStateData(id);
-    }, [id]);
+        fetchGraphStateData(id, depth);
+    }, [id, depth]);
 
     useEffect(() => {
         let svg = buildSvg(svgContainerRef.current, graphState);
@@ -417,6 +423,13 @@ export default function Graph({ id }: { id: Key }) {
         localDispatch(ActionType.ToggleHyperlinks);
     }
 
+    function onExpandClicked(e: Event) {
+        e.preventDefault();
+        if (depth < maxGraphDepth) {
+            setDepth(depth + 1);
+        }
+    }
+
     return (
         <CivContainer>
             <CivLeft>
@@ -431,6 +444,16 @@ export default function Graph({ id }: { id: Key }) {
                         ? svgTickedCheckBox()
                         : svgUntickedCheckBox()}
                 </div>
+                {depth < maxGraphDepth && (
+                    <div
+                        class="left-margin-entry fadeable clickable"
+                        onClick={onExpandClicked}
+                    >
+                        <span class="left-margin-icon-label">
+                            Expand to {depth + 1} Hops
+                        </span>
+                    </div>
+                )}
             </CivLeft>
 
             <div
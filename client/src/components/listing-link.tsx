@@ -1,15 +1,18 @@
 import type { SlimDeck } from "../types";
 
+import buildMarkup from "./build-markup";
 import DeckLink from "./deck-link";
 
 type ListingLinkProps = {
     slimDeck: SlimDeck;
     extraClasses?: string;
+    snippet?: string;
 };
 
 export default function ListingLink({
     slimDeck,
     extraClasses,
+    snippet,
 }: ListingLinkProps) {
     let klass = "listing-link";
     if (extraClasses) {
@@ -19,6 +22,7 @@ export default function ListingLink({
     let res = (
         <li class={klass}>
             <DeckLink slimDeck={slimDeck} />
+            {snippet && buildMarkup(snippet, slimDeck.font, 0)}
         </li>
     );
 
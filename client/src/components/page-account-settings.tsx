@@ -1,10 +1,12 @@
 import { type ComponentChildren } from "preact";
-import { useState } from "preact/hooks";
+import { useEffect, useState } from "preact/hooks";
 
 import { CivilSpan, ColourScheme } from "../enums";
+import type { Language, Ranking, SearchLanguage } from "../types";
 
 import { AppStateChange, getAppState } from "../app-state";
 
+import Net from "../shared/net";
 import { basicUiConfig, updateAndSaveUiConfig } from "../shared/ui-config";
 
 import { CivContainer, CivMainUi } from "./civil-layout";
@@ -20,6 +22,8 @@ export default function AccountSettings({ path }: { path?: string }) {
         <article>
             <ColourSchemeSelector />
             {appState.canNarrowWidth && <SpanSelector />}
+            <SearchRanking />
+            <SearchLanguageSelector />
             <ResetUiConfig />
         </article>
     );
@@ -39,6 +43,109 @@ function ResetUiConfig({}) {
     );
 }
 
+function SearchRanking({}) {
+    const [ranking, setRanking] = useState<Ranking | undefined>(undefined);
+
+    useEffect(() => {
+        Net.get<Ranking>("/api/search/ranking").then(setRanking);
+    }, []);
+
+    if (!ranking) {
+        return <div></div>;
+    }
+
+    const weights: Array<[keyof Ranking, string]> = [
+        ["title", "Title"],
+        ["aliases", "Aliases"],
+        ["body", "Body"],
+        ["impact", "Impact"],
+        ["insignia", "Insignia"],
+        ["visits", "Visits"],
+        ["recency", "Recency"],
+    ];
+
+    function handleChange(key: keyof Ranking, event: Event) {
+        if (ranking && event.target instanceof HTMLInputElement) {
+            setRanking({ ...ranking, [key]: Number(event.target.value) });
+        }
+    }
+
+    function handleSubmit(event: Event) {
+        event.preventDefault();
+        Net.put<Ranking, Ranking>("/api/search/ranking", ranking!).then(
+            setRanking,
+        );
+    }
+
+    return (
+        <Module heading="search ranking">
+            <form onSubmit={handleSubmit}>
+                {weights.map(([key, label]) => (
+                    <div>
+                        <label for={`ranking-${key}`}>{label}:</label>
+                        <input
+                            id={`ranking-${key}`}
+                            type="number"
+                            min="0"
+                            step="0.1"
+                            value={ranking[key]}
+                            onInput={(e) => handleChange(key, e)}
+                        />
+                    </div>
+                ))}
+                <input class="c-civil-button" type="submit" value="Save" />
+            </form>
+        </Module>
+    );
+}
+
+function SearchLanguageSelector({}) {
+    const [language, setLanguage] = useState<Language | undefined>(undefined);
+
+    useEffect(() => {
+        Net.get<SearchLanguage>("/api/search/language").then((s) =>
+            setLanguage(s.language),
+        );
+    }, []);
+
+    if (!language) {
+        return <div></div>;
+    }
+
+    const languages: Array<[Language, string]> = [
+        ["en", "English"],
+        ["de", "German"],
+        ["fr", "French"],
+        ["es", "Spanish"],
+        ["it", "Italian"],
+        ["ja", "Japanese"],
+        ["zh", "Chinese"],
+        ["ko", "Korean"],
+    ];
+
+    function handleChange(event: Event) {
+        if (event.target instanceof HTMLSelectElement) {
+            const body = { language: event.target.value as Language };
+            Net.put<SearchLanguage, SearchLanguage>(
+                "/api/search/language",
+                body,
+            ).then((s) => setLanguage(s.language));
+        }
+    }
+
+    return (
+        <Module heading="search language">
+            <select onChange={handleChange}>
+                {languages.map(([code, label]) => (
+                    <option value={code} selected={language === code}>
+                        {label}
+                    </option>
+                ))}
+            </select>
+        </Module>
+    );
+}
+
 function SpanSelector({}) {
     const appState = getAppState();
 
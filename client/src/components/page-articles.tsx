@@ -26,6 +26,7 @@ import SegmentGraph from "./segment-graph";
 import SegmentHits from "./segment-hits";
 import SegmentInsignias from "./segment-insignias";
 import SegmentNotes from "./segment-notes";
+import SegmentRelatedDecks from "./segment-related-decks";
 import SegmentSearchResults from "./segment-search-results";
 import TopMatter from "./top-matter";
 import useDeckManager from "./use-deck-manager";
@@ -262,6 +263,7 @@ function Article({ path, id }: { path?: string; id?: string }) {
                 />
                 <SegmentArrivals deck={deck} />
                 <SegmentSearchResults slimdeck={deck as SlimDeck} />
+                <SegmentRelatedDecks slimdeck={deck as SlimDeck} />
                 <SegmentGraph deck={deck} />
             </article>
         );
@@ -383,6 +385,7 @@ function ArticleUpdater({ article, onUpdate, onCancel }: ArticleUpdaterProps) {
         Net.put<ProtoArticle, DeckArticle>(
             buildUrl(deckKind, article.id, "/api"),
             data,
+            article.version,
         ).then((newDeck) => {
             onUpdate(newDeck);
         });
@@ -509,6 +509,7 @@ function DialogueUpdater({
         Net.put<ProtoDialogue, DeckDialogue>(
             `/api/${resource}/${dialogue.id}`,
             data,
+            dialogue.version,
         ).then((newDeck) => {
             onUpdate(newDeck);
         });
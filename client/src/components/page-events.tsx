@@ -34,6 +34,7 @@ import SegmentHits from "./segment-hits";
 import SegmentInsignias from "./segment-insignias";
 import SegmentNotes from "./segment-notes";
 import SegmentPoints from "./segment-points";
+import SegmentRelatedDecks from "./segment-related-decks";
 import SegmentSearchResults from "./segment-search-results";
 import TopMatter from "./top-matter";
 import useDeckManager from "./use-deck-manager";
@@ -183,6 +184,7 @@ function CivEvent({ path, id }: { path?: string; id?: string }) {
                 />
                 <SegmentArrivals deck={deck} />
                 <SegmentSearchResults slimdeck={deck as SlimDeck} />
+                <SegmentRelatedDecks slimdeck={deck as SlimDeck} />
 
                 {eventHasDuration(deck) && <SegmentPoints deck={deck} />}
 
@@ -359,11 +361,13 @@ function EventUpdater({ event, onUpdate, onCancel }: EventUpdaterProps) {
         };
 
         // edit an existing event
-        Net.put<ProtoEvent, DeckEvent>(`/api/events/${event.id}`, data).then(
-            (newDeck) => {
-                onUpdate(newDeck);
-            },
-        );
+        Net.put<ProtoEvent, DeckEvent>(
+            `/api/events/${event.id}`,
+            data,
+            event.version,
+        ).then((newDeck) => {
+            onUpdate(newDeck);
+        });
 
         e.preventDefault();
     };
@@ -19,6 +19,7 @@ import SegmentGraph from "./segment-graph";
 import SegmentHits from "./segment-hits";
 import SegmentInsignias from "./segment-insignias";
 import SegmentNotes from "./segment-notes";
+import SegmentRelatedDecks from "./segment-related-decks";
 import SegmentSearchResults from "./segment-search-results";
 import TopMatter from "./top-matter";
 import useDeckManager from "./use-deck-manager";
@@ -200,6 +201,7 @@ function Idea({ path, id }: { path?: string; id?: string }) {
                 />
                 <SegmentArrivals deck={deck} />
                 <SegmentSearchResults slimdeck={deck as SlimDeck} />
+                <SegmentRelatedDecks slimdeck={deck as SlimDeck} />
                 <SegmentGraph deck={deck} />
             </article>
         );
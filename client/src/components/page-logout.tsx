@@ -3,6 +3,7 @@ import { route } from "preact-router";
 
 import { AppStateChange } from "../app-state";
 
+import { stopLiveUpdates } from "../shared/live";
 import Net from "../shared/net";
 
 import { CivContainer, CivMainUi } from "./civil-layout";
@@ -19,6 +20,7 @@ function Logout({}) {
     const handleLogout = (event: Event) => {
         Net.delete("api/auth", {}).then(() => {
             //// this isn't logging out the user, refreshing the app logs the user back in
+            stopLiveUpdates();
             AppStateChange.userLogout({});
             route("/login", true);
         });
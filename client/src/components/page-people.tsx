@@ -47,6 +47,7 @@ import SegmentGraph from "./segment-graph";
 import SegmentHits from "./segment-hits";
 import SegmentInsignias from "./segment-insignias";
 import SegmentNotes from "./segment-notes";
+import SegmentRelatedDecks from "./segment-related-decks";
 import SegmentSearchResults from "./segment-search-results";
 import {
     svgBlank,
@@ -301,6 +302,7 @@ function Person({ path, id }: { path?: string; id?: string }) {
                 <SegmentArrivals deck={deck} />
 
                 <SegmentSearchResults slimdeck={deck as SlimDeck} />
+                <SegmentRelatedDecks slimdeck={deck as SlimDeck} />
                 {hasKnownLifespan && (
                     <SegmentPersonPoints
                         person={deck}
@@ -384,6 +384,7 @@ function Quote({ path, id }: { path?: string; id?: string }) {
             Net.put<ProtoQuote, DeckQuote>(
                 `/api/${resource}/${deck.id}`,
                 data,
+                deck.version,
             ).then((newDeck) => {
                 deckManager.update(newDeck);
             });
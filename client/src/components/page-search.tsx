@@ -1,3 +1,4 @@
+import { Link } from "preact-router";
 import { useEffect, useState } from "preact/hooks";
 
 import { CivilMode } from "../enums";
@@ -7,6 +8,7 @@ import { AppStateChange, getAppState } from "../app-state";
 
 import { addMultipleBookmarks } from "../shared/bookmarks";
 import Net from "../shared/net";
+import { addSavedSearch } from "../shared/saved-searches";
 import { emptySearchResults } from "../shared/search";
 
 import CivilModeButton from "./civil-mode-button";
@@ -49,13 +51,29 @@ function SearchModule({ encodedQuery }: { encodedQuery: string }) {
 
     function getSearchResults(query: string) {
         AppStateChange.urlTitle({ title: query });
-        const url = `/api/search/full?q=${query}`;
+        const url = `/api/search/full?q=${encodeURIComponent(query)}`;
         Net.getTimed<SearchResults>(url).then(([response, duration]) => {
             setResults(response);
             setTiming(duration);
         });
     }
 
+    function getMoreResults() {
+        if (!results.nextCursor) {
+            return;
+        }
+        const q = encodeURIComponent(encodedQuery);
+        const cursor = encodeURIComponent(results.nextCursor);
+        const url = `/api/search/full?q=${q}&cursor=${cursor}`;
+        Net.get<SearchResults>(url).then((response) => {
+            setResults({
+                ...results,
+                deckLevel: [...results.deckLevel, ...response.deckLevel],
+                nextCursor: response.nextCursor,
+            });
+        });
+    }
+
     function bookmarkAll() {
         let deckIds: Array<Key> = [];
         results.deckLevel.forEach((d) => deckIds.push(d.deck.id));
@@ -64,6 +82,14 @@ function SearchModule({ encodedQuery }: { encodedQuery: string }) {
         AppStateChange.mode({ mode: CivilMode.View });
     }
 
+    function saveAsSmartDeck() {
+        const name = window.prompt("Name of the smart deck", encodedQuery);
+        if (name && name.trim().length > 0) {
+            addSavedSearch(name.trim(), encodedQuery);
+        }
+        AppStateChange.mode({ mode: CivilMode.View });
+    }
+
     const bookmarkMode = appState.mode.value === CivilMode.BookmarkLinks;
 
     // can't use a module since search will end up rendering user content
@@ -76,6 +102,17 @@ function SearchModule({ encodedQuery }: { encodedQuery: string }) {
                 </CivLeft>
                 <CivMainUi>
                     <h3 class="ui">{encodedQuery}</h3>
+                    {results.didYouMean && (
+                        <p class="ui">
+                            Did you mean{" "}
+                            <Link
+                                href={`/search?q=${encodeURIComponent(results.didYouMean)}`}
+                            >
+                                {results.didYouMean}
+                            </Link>
+                            ?
+                        </p>
+                    )}
                     {bookmarkMode && (
                         <CivilModeButton
                             mode={CivilMode.BookmarkLinks}
@@ -84,9 +121,26 @@ function SearchModule({ encodedQuery }: { encodedQuery: string }) {
                             Bookmark All Results
                         </CivilModeButton>
                     )}
+                    {bookmarkMode && encodedQuery.length > 0 && (
+                        <CivilModeButton
+                            mode={CivilMode.BookmarkLinks}
+                            onClick={saveAsSmartDeck}
+                        >
+                            Save as Smart Deck
+                        </CivilModeButton>
+                    )}
                 </CivMainUi>
             </CivContainer>
             <ViewSearchResults searchResults={results} timing={timing} />
+            {results.nextCursor && (
+                <CivContainer>
+                    <CivMainUi>
+                        <button class="c-civil-button" onClick={getMoreResults}>
+                            More Results
+                        </button>
+                    </CivMainUi>
+                </CivContainer>
+            )}
         </article>
     );
 }
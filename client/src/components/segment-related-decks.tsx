@@ -0,0 +1,59 @@
+import { useEffect, useState } from "preact/hooks";
+
+import type { RelatedDeck, SlimDeck } from "../types";
+
+import { nonEmptyArray } from "../shared/civil";
+import { plural } from "../shared/english";
+import Net from "../shared/net";
+
+import { CivContainer, CivMain } from "./civil-layout";
+import ListingLink from "./listing-link";
+import RollableSegment from "./rollable-segment";
+
+// decks with similar notes that aren't yet connected to this one
+//
+export default function SegmentRelatedDecks({
+    slimdeck,
+}: {
+    slimdeck: SlimDeck;
+}) {
+    const [related, setRelated]: [Array<RelatedDeck>, Function] = useState(
+        [],
+    );
+
+    useEffect(() => {
+        Net.get<Array<RelatedDeck>>(`/api/decks/${slimdeck.id}/related`).then(
+            (res) => {
+                setRelated(res);
+            },
+        );
+    }, [slimdeck.id]);
+
+    if (nonEmptyArray<RelatedDeck>(related)) {
+        const heading = plural(related.length, "Related Deck", "s");
+
+        return (
+            <RollableSegment
+                heading={heading}
+                font={slimdeck.font}
+                initiallyRolledUp
+                extraClasses="c-segment-related-decks"
+            >
+                <CivContainer>
+                    <CivMain>
+                        <ul>
+                            {related.map((r) => (
+                                <ListingLink
+                                    slimDeck={r.deck}
+                                    snippet={r.sharedTerms.join(", ")}
+                                />
+                            ))}
+                        </ul>
+                    </CivMain>
+                </CivContainer>
+            </RollableSegment>
+        );
+    } else {
+        return <div></div>;
+    }
+}
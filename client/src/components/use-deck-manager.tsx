@@ -26,6 +26,7 @@ import { AppStateChange, getAppState } from "../app-state";
 import { bitset } from "../shared/bitops";
 import { buildUrl } from "../shared/civil";
 import { sortByDeckKindThenName } from "../shared/deck";
+import { changeAffectsDeck } from "../shared/live";
 import Net from "../shared/net";
 import {
     createMultiplePassages,
@@ -114,6 +115,29 @@ export default function useDeckManager<T extends FatDeck>(
         };
     }, [id]);
 
+    // re-fetch the deck when it has been changed elsewhere, e.g. in another
+    // window, but not while the user is typing into this one
+    //
+    useEffect(() => {
+        const change = appState.liveChange.value;
+        const deck = dms.deck;
+
+        if (
+            change &&
+            deck &&
+            !appState.componentRequiresFullKeyboardAccess.value &&
+            changeAffectsDeck(change, deck.id)
+        ) {
+            const url = buildUrl(deckKind, deck.id, "/api");
+            Net.get<T>(url).then((newDeck) => {
+                if (newDeck) {
+                    newDeck.hits = deck.hits;
+                    update(newDeck);
+                }
+            });
+        }
+    }, [appState.liveChange.value]);
+
     function update(newDeck: T) {
         let newDms = dmsUpdateDeck<T>(dms, preCacheFunction(newDeck), deckKind);
         setDms(newDms);
@@ -289,16 +289,18 @@ const ViewNote = <T extends FatDeck>({
     function onEdited(id: Key, updatedNote: Note) {
         // note: currently this will only update the content and the font
         //
-        Net.put<Note, Note>("/api/notes/" + id.toString(), updatedNote).then(
-            (newNote) => {
-                let newDeck: T = { ...parentDeck };
-                let index = newDeck.notes.findIndex((n) => n.id === id);
-                if (index !== -1) {
-                    newDeck.notes[index] = newNote;
-                }
-                onUpdateDeck(newDeck);
-            },
-        );
+        Net.put<Note, Note>(
+            "/api/notes/" + id.toString(),
+            updatedNote,
+            updatedNote.version,
+        ).then((newNote) => {
+            let newDeck: T = { ...parentDeck };
+            let index = newDeck.notes.findIndex((n) => n.id === id);
+            if (index !== -1) {
+                newDeck.notes[index] = newNote;
+            }
+            onUpdateDeck(newDeck);
+        });
     }
 
     function onDelete(id: Key) {
@@ -420,6 +422,8 @@ const ViewNote = <T extends FatDeck>({
                 kind: local.note.kind,
                 content: local.note.content,
                 font: local.note.font,
+                private: local.note.private,
+                version: note.version,
                 pointId: null,
                 refs: [],
                 flashcards: [],
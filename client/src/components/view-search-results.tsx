@@ -1,6 +1,12 @@
 import { useState, useRef } from "preact/hooks";
 
-import { CivilMode, NoteKind, RenderingDeckPart, RefKind } from "../enums";
+import {
+    CivilMode,
+    Font,
+    NoteKind,
+    RenderingDeckPart,
+    RefKind,
+} from "../enums";
 import type {
     Note,
     ReferencesApplied,
@@ -43,7 +49,10 @@ export default function ViewSearchResults({
             <CivMain>
                 <ul>
                     {searchResults.deckLevel.map((searchDeck) => (
-                        <ListingLink slimDeck={searchDeck.deck} />
+                        <ListingLink
+                            slimDeck={searchDeck.deck}
+                            snippet={searchDeck.snippet}
+                        />
                     ))}
                 </ul>
             </CivMain>
@@ -109,11 +118,42 @@ function ViewSearchDeck({
 
     return (
         <Expandable extraClasses={extraClasses} heading={heading}>
+            {searchDeck.source && (
+                <SearchSourceSnippet
+                    source={searchDeck.source}
+                    snippet={searchDeck.snippet}
+                    font={searchDeck.deck.font}
+                />
+            )}
             {searchNoteEntries}
         </Expandable>
     );
 }
 
+function SearchSourceSnippet({
+    source,
+    snippet,
+    font,
+}: {
+    source: "annotation" | "dialogue";
+    snippet?: string;
+    font: Font;
+}) {
+    const label =
+        source === "annotation"
+            ? "found in a ref annotation"
+            : "found in the original dialogue";
+
+    return (
+        <CivContainer extraClasses="c-search-source">
+            <CivLeft>
+                <span class="ui">{label}</span>
+            </CivLeft>
+            <CivMain>{snippet && buildMarkup(snippet, font, 0)}</CivMain>
+        </CivContainer>
+    );
+}
+
 function SearchNote({
     parent,
     deck,
@@ -203,7 +243,11 @@ function SearchNote({
             {maximisedFlashcards}
             <CivMain>
                 <div onClick={onNoteClicked} ref={hoveringRef}>
-                    {buildMarkup(note.content, note.font, note.id)}
+                    {buildMarkup(
+                        note.snippet ?? note.content,
+                        note.font,
+                        note.id,
+                    )}
                 </div>
             </CivMain>
             {appState.mode.value === CivilMode.Refs &&
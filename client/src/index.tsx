@@ -6,6 +6,7 @@ import type { UiConfig, UserWithUiConfig, UberSetup } from "./types";
 
 import { basicUiConfig } from "./shared/ui-config";
 import { getCssString, getCssBoolean } from "./shared/css";
+import { startLiveUpdates } from "./shared/live";
 import Net from "./shared/net";
 import { App } from "./app";
 import { AppStateChange, initialState } from "./app-state";
@@ -117,6 +118,7 @@ document.addEventListener("DOMContentLoaded", async function () {
             //
             const uber = await Net.get<UberSetup>("/api/ubersetup");
             AppStateChange.uberSetup({ uber });
+            startLiveUpdates();
             render(<App state={state} />, rootElement);
         } else {
             // use system default theme from css variable "--mode"
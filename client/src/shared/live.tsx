@@ -0,0 +1,78 @@
+import type { Key, LiveChange, UberSetup } from "../types";
+
+import Net from "../shared/net";
+import { AppStateChange } from "../app-state";
+
+// server-sent notifications of changes made to the user's data, this keeps
+// windows on different devices in sync with each other
+//
+let source: EventSource | undefined = undefined;
+let uberTimer: number | undefined = undefined;
+
+export function startLiveUpdates() {
+    if (source) {
+        return;
+    }
+
+    source = new EventSource("/api/live");
+
+    source.addEventListener("change", (event: MessageEvent) => {
+        const change = JSON.parse(event.data) as LiveChange;
+        AppStateChange.liveChange({ change });
+
+        if (
+            ["bookmark", "card", "deck", "ref", "saved-search"].includes(
+                change.entity,
+            )
+        ) {
+            refreshUberSetup();
+        }
+    });
+
+    source.addEventListener("resync", () => {
+        const change: LiveChange = {
+            entity: "resync",
+            action: "resync",
+            id: null,
+            deckId: null,
+        };
+        AppStateChange.liveChange({ change });
+        refreshUberSetup();
+    });
+}
+
+export function changeAffectsDeck(change: LiveChange, deckId: Key): boolean {
+    if (change.entity === "resync") {
+        return true;
+    }
+    if (change.deckId !== null) {
+        return change.deckId === deckId;
+    }
+    // deleted notes, refs and cards can no longer be traced back to their deck
+    return ["note", "ref", "card", "point"].includes(change.entity);
+}
+
+export function stopLiveUpdates() {
+    if (source) {
+        source.close();
+        source = undefined;
+    }
+}
+
+// several changes usually arrive together, only fetch once they've settled
+//
+function refreshUberSetup() {
+    window.clearTimeout(uberTimer);
+    uberTimer = window.setTimeout(() => {
+        Net.get<UberSetup>("/api/ubersetup").then((uber) => {
+            AppStateChange.setBookmarks({ bookmarks: uber.bookmarks });
+            AppStateChange.setSavedSearches({
+                savedSearches: uber.savedSearches,
+            });
+            AppStateChange.setRecentlyUsedDecks({
+                recents: uber.recentlyUsedDecks,
+            });
+            AppStateChange.setReviewCount({ count: uber.memoriseReviewCount });
+        });
+    }, 500);
+}
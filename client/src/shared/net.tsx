@@ -5,8 +5,13 @@ const Net = {
     put: async function <TData, TResp>(
         url: string,
         data: TData,
+        version?: number,
     ): Promise<TResp> {
-        return go<TResp>(url, addData<TData>(request("PUT"), data));
+        let options = addData<TData>(request("PUT"), data);
+        if (version !== undefined) {
+            addIfMatch(options, version);
+        }
+        return go<TResp>(url, options);
     },
     post: async function <TData, TResp>(
         url: string,
@@ -55,6 +60,13 @@ function addData<TData>(options: RequestInit, data: TData): RequestInit {
     return options;
 }
 
+// the server rejects an edit with a 409 if the item has changed since this version
+//
+function addIfMatch(options: RequestInit, version: number): RequestInit {
+    options.headers = { ...options.headers, "if-match": `"${version}"` };
+    return options;
+}
+
 function addSignal(options: RequestInit, signal: AbortSignal): RequestInit {
     options.signal = signal;
     return options;
@@ -63,7 +75,9 @@ function addSignal(options: RequestInit, signal: AbortSignal): RequestInit {
 async function go<TResp>(url: string, options: RequestInit): Promise<TResp> {
     let response = await fetch(url, options);
     if (!response.ok) {
-        throw new Error("Network response was not OK");
+        // api errors have a json body of { code, message, fields?, current? }
+        let body = await response.json().catch(() => undefined);
+        throw new Error(body?.message ?? "Network response was not OK");
     }
 
     let ret = await response.json();
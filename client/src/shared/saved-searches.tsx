@@ -0,0 +1,36 @@
+import type { Key, SavedSearch, UberSetup } from "../types";
+
+import Net from "../shared/net";
+import { AppStateChange } from "../app-state";
+
+// only the pinned searches are kept in the app state, so re-fetch them from
+// the ubersetup after any change
+//
+function refreshPinned() {
+    Net.get<UberSetup>("/api/ubersetup").then((uber) => {
+        AppStateChange.setSavedSearches({ savedSearches: uber.savedSearches });
+    });
+}
+
+export function addSavedSearch(name: string, query: string) {
+    type ProtoSavedSearch = {
+        name: string;
+        query: string;
+        pinned: boolean;
+    };
+    Net.post<ProtoSavedSearch, SavedSearch>("/api/search/saved", {
+        name,
+        query,
+        pinned: true,
+    }).then(refreshPinned);
+}
+
+export function deleteSavedSearch(id: Key) {
+    Net.delete<{}, Array<SavedSearch>>(`/api/search/saved/${id}`, {}).then(
+        (savedSearches) => {
+            AppStateChange.setSavedSearches({
+                savedSearches: savedSearches.filter((s) => s.pinned),
+            });
+        },
+    );
+}
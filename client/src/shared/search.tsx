@@ -23,6 +23,28 @@ export function sanitize(text: string): string {
     return blocked.reduce((a, b) => a.replaceAll(b, ""), text);
 }
 
+// like sanitize but keeps the field filters (e.g. kind:person, ref:"World War")
+// and excluded terms (-france) that the full search understands
+//
+export function sanitizeQuery(text: string): string {
+    const filter = /^(kind|before|after|insignia|font|ref):/;
+    const tokens = text.match(/[^\s"]*"[^"]*"?|[^\s]+/g) ?? [];
+
+    return tokens
+        .map((token) => {
+            if (filter.test(token)) {
+                return token;
+            }
+            if (token.startsWith("-")) {
+                const excluded = sanitize(token);
+                return excluded.length > 0 ? `-${excluded}` : "";
+            }
+            return sanitize(token);
+        })
+        .filter((token) => token.length > 0)
+        .join(" ");
+}
+
 export function emptySearchResults(): SearchResults {
     const res: SearchResults = {
         searchText: "",
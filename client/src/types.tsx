@@ -68,6 +68,7 @@ export type Hit = {
 export type FatDeck = SlimDeck & {
     // received from server
     //
+    version: number; // bumped on every edit, sent back in an If-Match header
     flashcards: Array<FlashCard>;
     points?: Array<Point>;
 
@@ -153,11 +154,14 @@ export type Note = {
     content: string;
     pointId: Key | null;
     font: Font;
+    private: boolean; // private notes are never shared, published or sent to an AI
+    version: number; // bumped on every edit
 
     refs: Array<Reference>;
     flashcards: Array<FlashCard>;
 
     chatMessage?: ChatMessage; // the original chat message for a dialogue
+    snippet?: string; // search results only: the text around the matches of a long note
 };
 
 // typescript has structural typing rather than nominal typing
@@ -231,16 +235,51 @@ export type SearchDeck = {
     // the notes from deck that match the search criteria
     // (this is optional, some searches will only populate deck)
     notes: Array<Note>;
+    // the matching text of one of the deck's points, or of the source below
+    snippet?: string;
+    // set when the notes were found through a ref annotation or the original
+    // text of a dialogue message rather than through their content
+    source?: "annotation" | "dialogue";
 };
 
 export type SlimResults = {
     results: Array<SlimDeck>;
 };
 
+export type RelatedDeck = {
+    deck: SlimDeck;
+    similarity: number;
+    sharedTerms: Array<string>; // the terms that contributed most to the similarity
+};
+
 export type SearchResults = {
     searchText: string;
     deckLevel: Array<SearchDeck>;
     noteLevel: Array<SearchDeck>;
+    didYouMean?: string;
+    // pass back as the cursor query parameter to get the next page of deckLevel
+    nextCursor?: string;
+};
+
+// the weights used by the server to order search results, title, aliases and
+// body weigh the matching text, the rest are added on top of its relevance
+//
+export type Ranking = {
+    title: number;
+    aliases: number;
+    body: number;
+    impact: number;
+    insignia: number;
+    visits: number;
+    recency: number;
+};
+
+// the language that the user's notes are mostly written in, anything other
+// than English is searched by substring rather than by stemmed words
+export type Language = "en" | "de" | "fr" | "es" | "it" | "ja" | "zh" | "ko";
+
+export type SearchLanguage = {
+    language: Language;
 };
 
 export type ColourSeeds = {
@@ -298,6 +337,17 @@ export type Bookmark = {
     deck: SlimDeck;
 };
 
+// a search query that's been given a name, pinned ones are shown alongside
+// the bookmarks as smart decks
+//
+export type SavedSearch = {
+    id: Key;
+    name: string;
+    query: string;
+    pinned: boolean;
+    createdAt: string;
+};
+
 export type ImmutableState = {
     readonly appName: string;
 
@@ -384,9 +434,13 @@ export type State = {
 
     bookmarks: Signal<Array<Bookmark>>;
     bookmarksMinimised: Signal<boolean>;
+    savedSearches: Signal<Array<SavedSearch>>;
 
     memoriseReviewCount: Signal<number>;
     memoriseEarliestReviewDate: Signal<undefined | string>;
+
+    // the most recent change made to the user's data, possibly in another window
+    liveChange: Signal<LiveChange | undefined>;
 };
 
 // [childId, _kind, _strength]
@@ -417,6 +471,18 @@ export type UberSetup = {
     memoriseReviewCount: number;
     memoriseEarliestReviewDate: string;
     bookmarks: Array<Bookmark>;
+    savedSearches: Array<SavedSearch>;
+};
+
+// sent by the server whenever the user's data changes, entity is one of
+// "deck", "note", "ref", "point", "bookmark", "card" etc. or "resync" if
+// some changes were missed and everything should be re-fetched
+//
+export type LiveChange = {
+    entity: string;
+    action: string;
+    id: Key | null;
+    deckId: Key | null; // the deck that should be re-fetched, if known
 };
 
 // graph stuff
@@ -588,6 +654,10 @@ export type StateChangeUber = StateChangeBase & {
     uber: UberSetup;
 };
 
+export type StateChangeLiveChange = StateChangeBase & {
+    change: LiveChange;
+};
+
 export type StateChangeUser = StateChangeBase & {
     user: User;
 };
@@ -614,6 +684,10 @@ export type StateChangeBookmarks = StateChangeBase & {
     bookmarks: Array<Bookmark>;
 };
 
+export type StateChangeSavedSearches = StateChangeBase & {
+    savedSearches: Array<SavedSearch>;
+};
+
 export type StateChangeRecentlyUsedDecks = StateChangeBase & {
     recents: Array<SlimDeck>;
 };
@@ -637,12 +711,14 @@ export type AppStateChangeArgs =
     | StateChangeGraph
     | StateChangeInputGiven
     | StateChangeKeyDown
+    | StateChangeLiveChange
     | StateChangeMode
     | StateChangeNoteForm
     | StateChangeNoteRefsModified
     | StateChangePageState
     | StateChangeRecentImages
     | StateChangeRecentlyUsedDecks
+    | StateChangeSavedSearches
     | StateChangeSetFocus
     | StateChangeSetSearch
     | StateChangeShowShortcuts
@@ -0,0 +1,61 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use civil_server::fsck_api;
+
+use r2d2_sqlite::SqliteConnectionManager;
+
+// checks the database for structural problems, run with --repair to also fix them
+//
+#[actix_web::main]
+async fn main() -> civil_server::Result<()> {
+    civil_server::init_dotenv();
+
+    let repair = std::env::args().skip(1).any(|arg| arg == "--repair");
+
+    let sqlite_db = civil_server::env_var_string("SQLITE_DB")?;
+    civil_server::db::sqlite_migrations::migration_check(&sqlite_db)?;
+
+    let user_content_path = civil_server::env_var_string_or("USER_CONTENT_PATH", "user-content");
+
+    let sqlite_manager = SqliteConnectionManager::file(&sqlite_db);
+    let sqlite_pool = r2d2::Pool::new(sqlite_manager)?;
+
+    let findings = fsck_api::fsck(&sqlite_pool, &user_content_path, repair)?;
+
+    for finding in &findings {
+        let status = if finding.repaired {
+            "repaired"
+        } else {
+            "found"
+        };
+        println!("[{}] {}: {}", status, finding.check, finding.description);
+    }
+
+    if findings.is_empty() {
+        println!("no problems found");
+    } else if repair {
+        println!("{} problems found and repaired", findings.len());
+    } else {
+        println!(
+            "{} problems found, run with --repair to fix them",
+            findings.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use civil_server::fsck_api;
+use civil_server::interop::fsck::FtsCommand;
+
+use r2d2_sqlite::SqliteConnectionManager;
+
+// runs an fts5 maintenance command on the full text search tables:
+// civil_fts <rebuild|optimize|integrity-check>
+//
+#[actix_web::main]
+async fn main() -> civil_server::Result<()> {
+    civil_server::init_dotenv();
+
+    let command: FtsCommand = match std::env::args().nth(1) {
+        Some(arg) => arg.parse()?,
+        None => {
+            eprintln!("usage: civil_fts <rebuild|optimize|integrity-check>");
+            return Err(civil_server::Error::InvalidFtsCommand);
+        }
+    };
+
+    let sqlite_db = civil_server::env_var_string("SQLITE_DB")?;
+    civil_server::db::sqlite_migrations::migration_check(&sqlite_db)?;
+
+    let sqlite_manager = SqliteConnectionManager::file(&sqlite_db);
+    let sqlite_pool = r2d2::Pool::new(sqlite_manager)?;
+
+    let reports = fsck_api::fts_maintenance(&sqlite_pool, command)?;
+
+    for report in &reports {
+        let status = if report.ok { "ok" } else { "FAILED" };
+        println!(
+            "{:<20} {:<16} {:<6} {:>6}ms {:>12} -> {:>12} bytes",
+            report.table,
+            report.command,
+            status,
+            report.duration_ms,
+            report.index_bytes_before,
+            report.index_bytes_after
+        );
+        if let Some(message) = &report.message {
+            println!("    {}", message);
+        }
+    }
+
+    Ok(())
+}
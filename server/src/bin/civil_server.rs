@@ -85,7 +85,8 @@ async fn main() -> civil_server::Result<()> {
                 server_api::internal_server_error,
             )
             .handler(http::StatusCode::BAD_REQUEST, server_api::bad_request)
-            .handler(http::StatusCode::NOT_FOUND, server_api::not_found);
+            .handler(http::StatusCode::NOT_FOUND, server_api::not_found)
+            .default_handler(server_api::api_error);
 
         App::new()
             .app_data(web::Data::new(sqlite_pool.clone()))
@@ -94,11 +95,17 @@ async fn main() -> civil_server::Result<()> {
                 user_content_path: user_content_path.clone(),
                 registration_magic_word: registration_magic_word.clone(),
             }))
-            .app_data(web::JsonConfig::default().limit(1024 * 1024))
+            .app_data(
+                web::JsonConfig::default()
+                    .limit(1024 * 1024)
+                    .error_handler(server_api::json_error),
+            )
+            .app_data(web::QueryConfig::default().error_handler(server_api::query_error))
             .wrap(middleware::DefaultHeaders::new().add(("Cache-control", "no-cache")))
             .wrap(session_store)
             .wrap(error_handlers)
             .service(server_api::public_api("/api"))
+            .service(server_api::share_links_api("/s"))
             .service(fs::Files::new("/u", &user_content_path))
             .service(fs::Files::new("/", &www_path).index_file("index.html"))
     })
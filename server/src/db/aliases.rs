@@ -0,0 +1,233 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::aliases as interop;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use rusqlite::{Row, named_params};
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+impl FromRow for interop::DeckAlias {
+    fn from_row(row: &Row) -> rusqlite::Result<interop::DeckAlias> {
+        Ok(interop::DeckAlias {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            name: row.get("name")?,
+        })
+    }
+}
+
+pub(crate) fn get_aliases(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Vec<interop::DeckAlias>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT a.id, a.deck_id, a.name
+         FROM deck_aliases a, decks d
+         WHERE a.deck_id = d.id AND d.user_id = :user_id AND d.id = :deck_id
+         ORDER BY a.id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )
+}
+
+fn get_alias(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    alias_id: Key,
+) -> Result<Option<interop::DeckAlias>, DbError> {
+    sqlite::one_optional(
+        conn,
+        "SELECT a.id, a.deck_id, a.name
+         FROM deck_aliases a, decks d
+         WHERE a.deck_id = d.id AND d.user_id = :user_id AND d.id = :deck_id AND a.id = :alias_id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":alias_id": alias_id},
+    )
+}
+
+// adding an empty alias or one that the deck already has is a no-op.
+//
+// note: deck_aliases has BEFORE and AFTER triggers that keep decks_fts and decks_trigram in sync,
+// so conflicts are avoided with explicit checks rather than OR IGNORE which would
+// skip the AFTER trigger and leave the deck out of the index
+//
+pub(crate) fn create_alias(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    name: &str,
+) -> Result<(), DbError> {
+    if name.is_empty() {
+        return Ok(());
+    }
+
+    sqlite::zero(
+        conn,
+        "INSERT INTO deck_aliases(deck_id, name)
+         SELECT id, :name
+         FROM decks
+         WHERE user_id = :user_id AND id = :deck_id
+               AND NOT EXISTS (SELECT 1 FROM deck_aliases WHERE deck_id = :deck_id AND name = :name)",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":name": name},
+    )?;
+
+    let alias_id = Key(conn.last_insert_rowid());
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::Alias,
+        Some(alias_id),
+        Payload::after(&interop::DeckAlias {
+            id: alias_id,
+            deck_id,
+            name: name.to_string(),
+        }),
+    )
+}
+
+pub(crate) fn edit_alias(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    alias_id: Key,
+    name: &str,
+) -> Result<(), DbError> {
+    if name.is_empty() {
+        return Ok(());
+    }
+
+    let before = get_alias(conn, user_id, deck_id, alias_id)?;
+
+    sqlite::zero(
+        conn,
+        "UPDATE deck_aliases
+         SET name = :name
+         WHERE id = :alias_id
+               AND deck_id = (SELECT id FROM decks WHERE user_id = :user_id AND id = :deck_id)
+               AND NOT EXISTS (SELECT 1 FROM deck_aliases WHERE deck_id = :deck_id AND name = :name)",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":alias_id": alias_id, ":name": name},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Edit,
+        AuditEntity::Alias,
+        Some(alias_id),
+        Payload::before(&before).with_after(&get_alias(conn, user_id, deck_id, alias_id)?),
+    )
+}
+
+pub(crate) fn delete_alias(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    alias_id: Key,
+) -> Result<(), DbError> {
+    let before = get_alias(conn, user_id, deck_id, alias_id)?;
+
+    sqlite::zero(
+        conn,
+        "DELETE FROM deck_aliases
+         WHERE id = :alias_id
+               AND deck_id = (SELECT id FROM decks WHERE user_id = :user_id AND id = :deck_id)",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":alias_id": alias_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Alias,
+        Some(alias_id),
+        Payload::before(&before),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::decks::{self, DeckBaseOrigin};
+    use crate::db::search;
+    use crate::db::test_util::{OWNER, create_deck, test_db};
+    use crate::interop::decks::DeckKind;
+    use crate::interop::font::Font;
+
+    fn get_or_create(conn: &rusqlite::Connection, name: &str) -> (Key, DeckBaseOrigin) {
+        let (deck, origin) = decks::deckbase_get_or_create(
+            conn,
+            OWNER,
+            DeckKind::Person,
+            name,
+            false,
+            0,
+            Font::Serif,
+            0,
+        )
+        .unwrap();
+        (deck.id, origin)
+    }
+
+    fn num_decks(conn: &rusqlite::Connection) -> i32 {
+        sqlite::one(conn, "SELECT count(*) FROM decks", []).unwrap()
+    }
+
+    #[test]
+    fn test_get_or_create_resolves_aliases() {
+        let db = test_db("alias-get-or-create");
+        let marcus = create_deck(&db.conn, "person", "Marcus Aurelius");
+        create_alias(&db.conn, OWNER, marcus, "The Philosopher Emperor").unwrap();
+
+        let (id, origin) = get_or_create(&db.conn, "The Philosopher Emperor");
+        assert_eq!(id, marcus);
+        assert!(matches!(origin, DeckBaseOrigin::PreExisting));
+        assert_eq!(num_decks(&db.conn), 1);
+
+        // a deck with the name is preferred over a deck that has it as an alias
+        let (named, origin) = get_or_create(&db.conn, "Marcus");
+        assert!(matches!(origin, DeckBaseOrigin::Created));
+        create_alias(&db.conn, OWNER, marcus, "Marcus").unwrap();
+        let (id, origin) = get_or_create(&db.conn, "Marcus");
+        assert_eq!(id, named);
+        assert!(matches!(origin, DeckBaseOrigin::PreExisting));
+        assert_eq!(num_decks(&db.conn), 2);
+    }
+
+    #[test]
+    fn test_search_names_resolve_aliases() {
+        let db = test_db("alias-search-names");
+        let marcus = create_deck(&db.conn, "person", "Marcus Aurelius");
+        create_deck(&db.conn, "person", "Epictetus");
+        create_alias(&db.conn, OWNER, marcus, "The Philosopher Emperor").unwrap();
+
+        let ids: Vec<Key> =
+            search::search_names_at_deck_level(&db.conn, OWNER, "Emperor".to_string(), None)
+                .unwrap()
+                .into_iter()
+                .map(|search_deck| search_deck.deck.id)
+                .collect();
+        assert_eq!(ids, [marcus]);
+    }
+}
@@ -15,11 +15,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::audit as audit_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::db::{DbError, decks, notes};
 use crate::interop::Key;
 use crate::interop::articles::{Article, ProtoArticle};
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::DeckKind;
 use crate::interop::font::Font;
 use rusqlite::{Row, named_params};
@@ -46,6 +48,7 @@ impl From<(decks::DeckBase, ArticleExtra)> for Article {
             insignia: deck.insignia,
             font: deck.font,
             impact: deck.impact,
+            version: deck.version,
 
             source: extra.source,
             author: extra.author,
@@ -69,6 +72,7 @@ impl FromRow for Article {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             source: row.get("source")?,
             author: row.get("author")?,
@@ -139,9 +143,12 @@ pub(crate) fn edit(
     user_id: Key,
     article: ProtoArticle,
     article_id: Key,
+    version: i32,
 ) -> Result<Article, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, article_id)?;
+
     let edited_deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -152,6 +159,7 @@ pub(crate) fn edit(
         article.insignia,
         article.font,
         article.impact,
+        version,
     )?;
 
     let article_extras_exists: Vec<ArticleExtra> = sqlite::many(
@@ -213,6 +221,8 @@ pub(crate) fn edit(
         },
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, article_id, &before)?;
+
     tx.commit()?;
 
     let mut article: Article = (edited_deck, article_extras).into();
@@ -0,0 +1,424 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::live as live_db;
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity, AuditEvent};
+use crate::interop::decks::Pagination;
+use rusqlite::{Row, named_params};
+use serde::Serialize;
+use serde_json::Value;
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+impl FromRow for AuditEvent {
+    fn from_row(row: &Row) -> rusqlite::Result<AuditEvent> {
+        let payload: Option<String> = row.get("payload")?;
+
+        Ok(AuditEvent {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            created_at: row.get("created_at")?,
+            entity: row.get("entity")?,
+            entity_id: row.get("entity_id")?,
+            action: row.get("action")?,
+            payload: payload.and_then(|p| serde_json::from_str(&p).ok()),
+        })
+    }
+}
+
+// the state of an entity before and after a change, either side can be absent
+// (e.g. there's nothing before a create). Stored as compact json.
+//
+#[derive(Default)]
+pub(crate) struct Payload {
+    before: Option<Value>,
+    after: Option<Value>,
+}
+
+impl Payload {
+    pub(crate) fn none() -> Payload {
+        Payload::default()
+    }
+
+    pub(crate) fn before(before: &impl Serialize) -> Payload {
+        Payload::none().with_before(before)
+    }
+
+    pub(crate) fn after(after: &impl Serialize) -> Payload {
+        Payload::none().with_after(after)
+    }
+
+    pub(crate) fn with_before(mut self, before: &impl Serialize) -> Payload {
+        self.before = serde_json::to_value(before).ok();
+        self
+    }
+
+    pub(crate) fn with_after(mut self, after: &impl Serialize) -> Payload {
+        self.after = serde_json::to_value(after).ok();
+        self
+    }
+
+    fn as_json(&self) -> Option<String> {
+        if self.before.is_none() && self.after.is_none() {
+            return None;
+        }
+
+        let mut payload = serde_json::Map::new();
+        if let Some(before) = &self.before {
+            payload.insert("before".to_string(), before.clone());
+        }
+        if let Some(after) = &self.after {
+            payload.insert("after".to_string(), after.clone());
+        }
+
+        serde_json::to_string(&payload).ok()
+    }
+}
+
+pub(crate) fn record(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    action: AuditAction,
+    entity: AuditEntity,
+    entity_id: Option<Key>,
+    payload: Payload,
+) -> Result<(), DbError> {
+    insert(conn, Some(user_id), action, entity, entity_id, payload)?;
+    live_db::queue(conn, user_id, action, entity, entity_id)
+}
+
+// for statements that may have matched nothing, e.g. because the entity
+// belongs to another user: only records the event if the most recent
+// statement on this connection changed any rows
+//
+pub(crate) fn record_if_changed(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    action: AuditAction,
+    entity: AuditEntity,
+    entity_id: Option<Key>,
+    payload: Payload,
+) -> Result<(), DbError> {
+    if conn.changes() == 0 {
+        return Ok(());
+    }
+    record(conn, user_id, action, entity, entity_id, payload)
+}
+
+// migrations aren't run on behalf of any user
+//
+pub(crate) fn record_migration(
+    conn: &rusqlite::Connection,
+    from_version: i64,
+    to_version: i64,
+) -> Result<(), DbError> {
+    insert(
+        conn,
+        None,
+        AuditAction::Migrate,
+        AuditEntity::Database,
+        None,
+        Payload::before(&from_version).with_after(&to_version),
+    )
+}
+
+fn insert(
+    conn: &rusqlite::Connection,
+    user_id: Option<Key>,
+    action: AuditAction,
+    entity: AuditEntity,
+    entity_id: Option<Key>,
+    payload: Payload,
+) -> Result<(), DbError> {
+    sqlite::zero(
+        conn,
+        "INSERT INTO audit_events(user_id, entity, entity_id, action, payload)
+         VALUES (:user_id, :entity, :entity_id, :action, :payload)",
+        named_params! {
+            ":user_id": user_id,
+            ":entity": entity,
+            ":entity_id": entity_id,
+            ":action": action,
+            ":payload": payload.as_json(),
+        },
+    )
+}
+
+// the parts of a deck that are worth keeping in an audit event: its own editable
+// fields plus any article extras and, for predictions and events, their points,
+// without copying all of the deck's notes
+//
+pub(crate) fn deck_snapshot(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Option<Value>, DbError> {
+    let snapshot: Option<String> = sqlite::one_optional(
+        conn,
+        "SELECT json_object('title', d.name,
+                            'kind', d.kind,
+                            'graphTerminator', json(CASE WHEN d.graph_terminator THEN 'true' ELSE 'false' END),
+                            'insignia', d.insignia,
+                            'font', d.font,
+                            'impact', d.impact,
+                            'numNotes', (SELECT count(*) FROM notes n WHERE n.deck_id = d.id),
+                            'article', json((SELECT json_object('source', ae.source,
+                                                                'author', ae.author,
+                                                                'shortDescription', ae.short_description,
+                                                                'publishedDate', ae.published_date)
+                                             FROM article_extras ae
+                                             WHERE ae.deck_id = d.id)),
+                            'points', json(CASE WHEN d.kind IN ('prediction', 'event')
+                                                THEN (SELECT json_group_array(json_object('title', p.title,
+                                                                                          'locationTextual', p.location_textual,
+                                                                                          'longitude', p.longitude,
+                                                                                          'latitude', p.latitude,
+                                                                                          'locationFuzz', p.location_fuzz,
+                                                                                          'dateTextual', p.date_textual,
+                                                                                          'exactDate', date(p.exact_realdate),
+                                                                                          'lowerDate', date(p.lower_realdate),
+                                                                                          'upperDate', date(p.upper_realdate),
+                                                                                          'dateFuzz', p.date_fuzz))
+                                                      FROM points p
+                                                      WHERE p.deck_id = d.id)
+                                           END))
+         FROM decks d
+         WHERE d.id = :deck_id AND d.user_id = :user_id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+
+    Ok(snapshot.and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+// the audit log is append-only so a note's content is never copied into it,
+// only its length and a hash that shows whether an edit actually changed it
+//
+pub(crate) fn note_snapshot(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<Value>, DbError> {
+    let snapshot: Option<(String, String)> = sqlite::one_optional(
+        conn,
+        "SELECT json_object('deckId', n.deck_id,
+                            'kind', n.kind,
+                            'pointId', n.point_id,
+                            'prevNoteId', n.prev_note_id,
+                            'contentLength', length(n.content),
+                            'private', n.private,
+                            'refs', (SELECT json_group_array(json_object('deckId', r.deck_id,
+                                                                         'kind', r.kind,
+                                                                         'annotation', r.annotation))
+                                     FROM refs r
+                                     WHERE r.note_id = n.id)),
+                n.content
+         FROM notes n
+         WHERE n.id = :note_id AND n.user_id = :user_id",
+        named_params! {":user_id": user_id, ":note_id": note_id},
+    )?;
+
+    Ok(snapshot.and_then(|(s, content)| {
+        let mut snapshot: Value = serde_json::from_str(&s).ok()?;
+        snapshot["contentHash"] = Value::String(content_hash(&content));
+        Some(snapshot)
+    }))
+}
+
+// 64-bit FNV-1a, which is stable across builds unlike std's DefaultHasher
+//
+fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+// records the change to a note, from the given snapshot to its current state
+//
+pub(crate) fn record_note_change(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    action: AuditAction,
+    note_id: Key,
+    before: &impl Serialize,
+) -> Result<(), DbError> {
+    let after = note_snapshot(conn, user_id, note_id)?;
+    record(
+        conn,
+        user_id,
+        action,
+        AuditEntity::Note,
+        Some(note_id),
+        Payload::before(before).with_after(&after),
+    )
+}
+
+pub(crate) fn record_deck_change(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    action: AuditAction,
+    deck_id: Key,
+    before: &impl Serialize,
+) -> Result<(), DbError> {
+    let after = deck_snapshot(conn, user_id, deck_id)?;
+    record(
+        conn,
+        user_id,
+        action,
+        AuditEntity::Deck,
+        Some(deck_id),
+        Payload::before(before).with_after(&after),
+    )
+}
+
+pub(crate) fn get_events(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    include_system: bool,
+    entity: Option<String>,
+    entity_id: Option<Key>,
+    offset: i32,
+    num_items: i32,
+) -> Result<Pagination<AuditEvent>, DbError> {
+    let filter = "(user_id = :user_id OR (:include_system AND user_id IS NULL))
+                  AND (:entity IS NULL OR entity = :entity)
+                  AND (:entity_id IS NULL OR entity_id = :entity_id)";
+
+    let items = sqlite::many(
+        conn,
+        &format!(
+            "SELECT id, user_id, created_at, entity, entity_id, action, payload
+             FROM audit_events
+             WHERE {filter}
+             ORDER BY id DESC
+             LIMIT :limit
+             OFFSET :offset"
+        ),
+        named_params! {
+            ":user_id": user_id,
+            ":include_system": include_system,
+            ":entity": entity,
+            ":entity_id": entity_id,
+            ":limit": num_items,
+            ":offset": offset,
+        },
+    )?;
+
+    let total_items = sqlite::one(
+        conn,
+        &format!("SELECT count(*) FROM audit_events WHERE {filter}"),
+        named_params! {
+            ":user_id": user_id,
+            ":include_system": include_system,
+            ":entity": entity,
+            ":entity_id": entity_id,
+        },
+    )?;
+
+    Ok(Pagination::<AuditEvent> { items, total_items })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ideas;
+    use crate::db::notes as notes_db;
+    use crate::db::test_util::{OWNER, add_note, create_deck, test_db};
+    use crate::interop::decks::{DeckKind, ProtoSlimDeck};
+    use crate::interop::font::Font;
+
+    #[test]
+    fn test_audit_snapshots() {
+        let mut db = test_db("audit-snapshots");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "a private thought");
+        notes_db::set_note_private(&db.conn, OWNER, note_id, true).unwrap();
+
+        let before = note_snapshot(&db.conn, OWNER, note_id).unwrap().unwrap();
+        assert!(before.get("content").is_none());
+        assert_eq!(before["contentLength"], 17);
+        assert_eq!(before["private"], 1);
+
+        let mut note = notes_db::get_note(&db.conn, OWNER, note_id)
+            .unwrap()
+            .unwrap();
+        note.content = "a revised private thought".to_string();
+        notes_db::edit_note(&db.conn, OWNER, note, note_id, None).unwrap();
+
+        let after = note_snapshot(&db.conn, OWNER, note_id).unwrap().unwrap();
+        assert_ne!(before["contentHash"], after["contentHash"]);
+        assert!(!after.to_string().contains("private thought"));
+
+        let deck = ProtoSlimDeck {
+            title: "Stoicism".to_string(),
+            deck_kind: DeckKind::Idea,
+            graph_terminator: false,
+            insignia: 4,
+            font: Font::Serif,
+            impact: 0,
+        };
+        ideas::edit(&mut db.conn, OWNER, deck, deck_id, 1).unwrap();
+
+        let events = get_events(
+            &db.conn,
+            OWNER,
+            false,
+            Some("deck".to_string()),
+            Some(deck_id),
+            0,
+            10,
+        )
+        .unwrap()
+        .items;
+        let payload = events[0].payload.as_ref().unwrap();
+        assert_eq!(payload["before"]["insignia"], 0);
+        assert_eq!(payload["after"]["insignia"], 4);
+    }
+
+    #[test]
+    fn test_events_are_append_only() {
+        let db = test_db("audit-append-only");
+        record(
+            &db.conn,
+            OWNER,
+            AuditAction::Create,
+            AuditEntity::Deck,
+            Some(Key(1)),
+            Payload::none(),
+        )
+        .unwrap();
+
+        let r = db
+            .conn
+            .execute("UPDATE audit_events SET action = 'delete'", []);
+        assert!(r.unwrap_err().to_string().contains("append-only"));
+        let r = db.conn.execute("DELETE FROM audit_events", []);
+        assert!(r.unwrap_err().to_string().contains("append-only"));
+
+        let created: i64 = sqlite::one(
+            &db.conn,
+            "SELECT count(*) FROM audit_events WHERE action = 'create'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(created, 1);
+    }
+}
@@ -16,9 +16,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
 use crate::interop::bookmarks as interop;
 use crate::interop::decks::SlimDeck;
 use rusqlite::{Row, named_params};
@@ -45,6 +47,19 @@ pub(crate) fn create_bookmark(
         &conn,
         &stmt,
         named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+
+    record_create(conn, user_id, deck_id)
+}
+
+fn record_create(conn: &rusqlite::Connection, user_id: Key, deck_id: Key) -> Result<(), DbError> {
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::Bookmark,
+        Some(Key(conn.last_insert_rowid())),
+        Payload::after(&serde_json::json!({ "deckId": deck_id })),
     )
 }
 
@@ -62,6 +77,7 @@ pub(crate) fn create_multiple_bookmarks(
             &stmt,
             named_params! {":user_id": user_id, ":deck_id": deck_id},
         )?;
+        record_create(&tx, user_id, deck_id)?;
     }
 
     tx.commit()?;
@@ -89,6 +105,12 @@ pub(crate) fn delete_bookmark(
     user_id: Key,
     bookmark_id: Key,
 ) -> Result<(), DbError> {
+    let before: Option<Key> = sqlite::one_optional(
+        conn,
+        "SELECT deck_id FROM bookmarks WHERE user_id = :user_id AND id = :bookmark_id",
+        named_params! {":user_id": user_id, ":bookmark_id": bookmark_id},
+    )?;
+
     let stmt = Qry::delete_from("bookmarks")
         .where_clause("user_id = :user_id")
         .and("id = :bookmark_id");
@@ -96,5 +118,14 @@ pub(crate) fn delete_bookmark(
         &conn,
         &stmt,
         named_params! {":user_id": user_id, ":bookmark_id": bookmark_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Bookmark,
+        Some(bookmark_id),
+        Payload::before(&serde_json::json!({ "deckId": before })),
     )
 }
@@ -15,11 +15,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::audit as audit_db;
 use crate::db::notes as notes_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::db::{DbError, decks};
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::concepts::Concept;
 use crate::interop::decks::{DeckKind, ProtoSlimDeck};
 use crate::interop::font::Font;
@@ -39,6 +41,7 @@ impl FromRow for Concept {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             notes: vec![],
             arrivals: vec![],
@@ -99,6 +102,8 @@ pub(crate) fn convert(
         i.arrivals = notes_db::arrivals_for_deck(conn, concept_id)?;
     }
 
+    let before = audit_db::deck_snapshot(conn, user_id, concept_id)?;
+
     let target_kind = DeckKind::Idea;
     let stmt = "UPDATE decks
                 SET kind = :deck_kind
@@ -109,6 +114,10 @@ pub(crate) fn convert(
         named_params! {":user_id": user_id, ":deck_id": concept_id, ":deck_kind": target_kind},
     )?;
 
+    if before.is_some() {
+        audit_db::record_deck_change(conn, user_id, AuditAction::Convert, concept_id, &before)?;
+    }
+
     Ok(concept)
 }
 
@@ -137,9 +146,12 @@ pub(crate) fn edit(
     user_id: Key,
     concept: ProtoSlimDeck,
     concept_id: Key,
+    version: i32,
 ) -> Result<Concept, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, concept_id)?;
+
     let deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -150,8 +162,11 @@ pub(crate) fn edit(
         concept.insignia,
         concept.font,
         concept.impact,
+        version,
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, concept_id, &before)?;
+
     tx.commit()?;
 
     let mut concept: Concept = deck.into();
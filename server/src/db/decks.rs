@@ -16,17 +16,28 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::aliases;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::notes;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
+use crate::db::tags::DECK_TAG_FILTER;
 use crate::interop::Key;
-use crate::interop::decks::{DeckKind, Hit, Pagination, SlimDeck};
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::decks::{
+    DeckConversion, DeckKind, ExtrasMergeStrategy, Hit, Pagination, SlimDeck,
+};
+use crate::interop::dialogues::AiKind;
 use crate::interop::font::Font;
 use rusqlite::{Connection, Row, named_params};
 
 #[allow(unused_imports)]
 use tracing::{info, warn};
 
+// the per-kind tables that hang off a deck with a deck_id column
+//
+const EXTRAS_TABLES: [&str; 3] = ["article_extras", "dialogue_extras", "prediction_extras"];
+
 pub(crate) enum DeckBaseOrigin {
     Created,
     PreExisting,
@@ -42,6 +53,7 @@ pub struct DeckBase {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 }
 
 impl FromRow for SlimDeck {
@@ -70,6 +82,7 @@ impl FromRow for DeckBase {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
         })
     }
 }
@@ -137,13 +150,15 @@ fn num_decks_for_deck_kind(
     conn: &rusqlite::Connection,
     user_id: Key,
     deck_kind: DeckKind,
+    tag_id: Option<Key>,
 ) -> Result<i32, DbError> {
     sqlite::one(
         &conn,
-        &Qry::query_count_decklike(),
+        &Qry::query_count_decklike().and(DECK_TAG_FILTER),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
         },
     )
 }
@@ -154,24 +169,27 @@ pub(crate) fn pagination(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
         &Qry::select_decklike()
             .from_decklike()
             .where_decklike_but_no_deck_id()
+            .and(DECK_TAG_FILTER)
             .order_by("d.created_at DESC")
             .limit()
             .offset(),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":limit": num_items,
             ":offset": offset
         },
     )?;
 
-    let total_items = num_decks_for_deck_kind(conn, user_id, deck_kind)?;
+    let total_items = num_decks_for_deck_kind(conn, user_id, deck_kind, tag_id)?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
 
@@ -184,6 +202,7 @@ pub(crate) fn pagination_events_chronologically(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -191,6 +210,7 @@ pub(crate) fn pagination_events_chronologically(
             .from_decklike()
             .left_join("points AS p ON p.deck_id = d.id")
             .where_decklike_but_no_deck_id()
+            .and(DECK_TAG_FILTER)
             .order_by("(p.exact_realdate IS NOT NULL OR p.lower_realdate IS NOT NULL OR p.upper_realdate IS NOT NULL),
                 COALESCE(p.exact_realdate, p.lower_realdate, p.upper_realdate) DESC")
         .limit()
@@ -198,12 +218,13 @@ pub(crate) fn pagination_events_chronologically(
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":limit": num_items,
             ":offset": offset
         },
     )?;
 
-    let total_items = num_decks_for_deck_kind(conn, user_id, deck_kind)?;
+    let total_items = num_decks_for_deck_kind(conn, user_id, deck_kind, tag_id)?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
 
@@ -258,6 +279,9 @@ pub(crate) fn get_hits(conn: &rusqlite::Connection, deck_id: Key) -> Result<Vec<
     sqlite::many(conn, stmt, named_params! {":deck_id": deck_id})
 }
 
+// name can either be the deck's name or one of its aliases, a deck whose
+// name matches is preferred over one that only has it as an alias
+//
 fn deckbase_get_by_name(
     conn: &Connection,
     user_id: Key,
@@ -269,7 +293,11 @@ fn deckbase_get_by_name(
         &Qry::select_decklike()
             .from_decklike()
             .where_decklike_but_no_deck_id()
-            .and("d.name = :name"),
+            .and(
+                "(d.name = :name OR d.id IN (SELECT deck_id FROM deck_aliases WHERE name = :name))",
+            )
+            .order_by("d.name = :name DESC, d.id")
+            .add(" LIMIT 1"),
         named_params! {":user_id": user_id, ":name": name, ":deck_kind": kind},
     )
 }
@@ -286,7 +314,7 @@ pub(crate) fn deckbase_create(
 ) -> Result<DeckBase, DbError> {
     let stmt = "INSERT INTO decks(user_id, kind, name, graph_terminator, insignia, font, impact)
                 VALUES (:user_id, :deck_kind, :name, :graph_terminator, :insignia, :font, :impact)
-                RETURNING id, name, kind, created_at, graph_terminator, insignia, font, impact";
+                RETURNING id, name, kind, created_at, graph_terminator, insignia, font, impact, version";
 
     let deckbase: DeckBase = sqlite::one(
         tx,
@@ -305,6 +333,15 @@ pub(crate) fn deckbase_create(
     // create the mandatory NoteKind::NoteDeckMeta
     let _note = notes::create_note_deck_meta(tx, user_id, deckbase.id)?;
 
+    audit_db::record(
+        tx,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::Deck,
+        Some(deckbase.id),
+        Payload::after(&audit_db::deck_snapshot(tx, user_id, deckbase.id)?),
+    )?;
+
     Ok(deckbase)
 }
 
@@ -318,6 +355,7 @@ pub(crate) fn deckbase_edit(
     insignia: i32,
     font: Font,
     impact: i32,
+    version: i32,
 ) -> Result<DeckBase, DbError> {
     // if the font has changed
     let original_font = get_font_of_deck(tx, deck_id)?;
@@ -327,12 +365,14 @@ pub(crate) fn deckbase_edit(
         notes::replace_note_fonts(tx, user_id, deck_id, original_font, font)?;
     }
 
+    // only edits the deck if it's still at the version that the client last saw
     let stmt = "UPDATE decks
-                SET name = :name, graph_terminator = :graph_terminator, insignia = :insignia, font = :font, impact = :impact
-                WHERE user_id = :user_id AND id = :deck_id AND kind = :deck_kind
-                RETURNING id, name, kind, created_at, graph_terminator, insignia, font, impact";
+                SET name = :name, graph_terminator = :graph_terminator, insignia = :insignia, font = :font, impact = :impact,
+                    version = version + 1
+                WHERE user_id = :user_id AND id = :deck_id AND kind = :deck_kind AND version = :version
+                RETURNING id, name, kind, created_at, graph_terminator, insignia, font, impact, version";
 
-    sqlite::one(
+    let deckbase: Option<DeckBase> = sqlite::one_optional(
         tx,
         stmt,
         named_params! {
@@ -343,9 +383,26 @@ pub(crate) fn deckbase_edit(
             ":graph_terminator": graph_terminator,
             ":insignia": insignia,
             ":font": font,
-            ":impact": impact
+            ":impact": impact,
+            ":version": version
         },
-    )
+    )?;
+
+    let Some(deckbase) = deckbase else {
+        let exists: Option<Key> = sqlite::one_optional(
+            tx,
+            "SELECT id FROM decks WHERE id = :deck_id AND user_id = :user_id",
+            named_params! {":user_id": user_id, ":deck_id": deck_id},
+        )?;
+        return Err(if exists.is_some() {
+            DbError::VersionConflict
+        } else {
+            DbError::NotFound
+        });
+    };
+
+    // the caller records the audit event once it has also edited any extras
+    Ok(deckbase)
 }
 
 pub(crate) fn insignia_filter(
@@ -355,6 +412,7 @@ pub(crate) fn insignia_filter(
     insignia: i32,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -362,6 +420,7 @@ pub(crate) fn insignia_filter(
             .from_decklike()
             .where_decklike_but_no_deck_id()
             .and("d.insignia & :insignia")
+            .and(DECK_TAG_FILTER)
             .order_by("d.created_at DESC")
             .limit()
             .offset(),
@@ -369,6 +428,7 @@ pub(crate) fn insignia_filter(
             ":user_id": user_id,
             ":insignia": insignia,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":limit": num_items,
             ":offset": offset
         },
@@ -376,8 +436,10 @@ pub(crate) fn insignia_filter(
 
     let total_items = sqlite::one(
         &conn,
-        &Qry::query_count_decklike().and("d.insignia & :insignia"),
-        named_params! {":user_id": user_id, ":insignia": insignia, ":deck_kind": deck_kind},
+        &Qry::query_count_decklike()
+            .and("d.insignia & :insignia")
+            .and(DECK_TAG_FILTER),
+        named_params! {":user_id": user_id, ":insignia": insignia, ":deck_kind": deck_kind, ":tag_id": tag_id},
     )?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
@@ -391,24 +453,27 @@ pub(crate) fn insignia_filter_any(
     insignia: i32,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
         &Qry::select_decklike()
             .from_decklike()
             .where_clause("user_id = :user_id AND d.insignia & :insignia")
+            .and(DECK_TAG_FILTER)
             .order_by("d.created_at DESC")
             .limit()
             .offset(),
-        named_params! {":user_id": user_id, ":insignia": insignia, ":limit": num_items, ":offset": offset},
+        named_params! {":user_id": user_id, ":insignia": insignia, ":tag_id": tag_id, ":limit": num_items, ":offset": offset},
     )?;
 
     let total_items = sqlite::one(
         &conn,
         &Qry::select_count()
             .from_decklike()
-            .where_clause("d.user_id = :user_id AND d.insignia & :insignia;"),
-        named_params! {":user_id": user_id, ":insignia": insignia},
+            .where_clause("d.user_id = :user_id AND d.insignia & :insignia")
+            .and(DECK_TAG_FILTER),
+        named_params! {":user_id": user_id, ":insignia": insignia, ":tag_id": tag_id},
     )?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
@@ -435,15 +500,405 @@ pub(crate) fn recent(
 // delete anything that's represented as a deck (article, person, idea, timeline, quote, dialogue)
 //
 pub(crate) fn delete(conn: &Connection, user_id: Key, id: Key) -> Result<(), DbError> {
+    let Some(before) = audit_db::deck_snapshot(conn, user_id, id)? else {
+        return Ok(());
+    };
+
     sqlite::zero(
         &conn,
         "DELETE FROM decks WHERE id = :id and user_id = :user_id",
         named_params! {":user_id": user_id, ":id": id},
     )?;
 
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Deck,
+        Some(id),
+        Payload::before(&before),
+    )?;
+
     Ok(())
 }
 
+// merge deck_id into target_id: all of deck_id's notes, points, hits, bookmarks, tags and aliases are moved over,
+// refs that pointed to deck_id now point to target_id, deck_id's name becomes an alias of target_id
+// and then deck_id is deleted.
+//
+// returns None if either deck can't be found
+//
+pub(crate) fn merge_into(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    target_id: Key,
+    extras_strategy: ExtrasMergeStrategy,
+) -> Result<Option<SlimDeck>, DbError> {
+    let tx = conn.transaction()?;
+
+    let stmt = Qry::select_decklike()
+        .from_decklike()
+        .where_clause("d.user_id = :user_id AND d.id = :deck_id");
+    let source: Option<SlimDeck> = sqlite::one_optional(
+        &tx,
+        &stmt,
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+    let target: Option<SlimDeck> = sqlite::one_optional(
+        &tx,
+        &stmt,
+        named_params! {":user_id": user_id, ":deck_id": target_id},
+    )?;
+
+    let (Some(source), Some(target)) = (source, target) else {
+        return Ok(None);
+    };
+    let before = [
+        audit_db::deck_snapshot(&tx, user_id, deck_id)?,
+        audit_db::deck_snapshot(&tx, user_id, target_id)?,
+    ];
+
+    // notes in deck_id that refer to target_id would become refs from target_id
+    // to itself, any that the target already had to itself are left alone
+    sqlite::zero(
+        &tx,
+        "DELETE FROM refs
+         WHERE deck_id = :target_id
+               AND note_id IN (SELECT id FROM notes WHERE deck_id = :deck_id)",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+
+    notes::move_notes_to_deck(&tx, user_id, deck_id, target_id)?;
+
+    // the same goes for refs to deck_id from notes that are now in target_id,
+    // whether they came from the target or were the source's refs to itself
+    sqlite::zero(
+        &tx,
+        "DELETE FROM refs
+         WHERE deck_id = :deck_id
+               AND note_id IN (SELECT id FROM notes WHERE deck_id = :target_id)",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+
+    // re-point incoming refs, if a note already refers to both decks then
+    // the ref to deck_id is left behind and cascade deleted along with the deck
+    sqlite::zero(
+        &tx,
+        "UPDATE OR IGNORE refs
+         SET deck_id = :target_id
+         WHERE deck_id = :deck_id",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+
+    sqlite::zero(
+        &tx,
+        "UPDATE OR IGNORE deck_tags
+         SET deck_id = :target_id
+         WHERE deck_id = :deck_id",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+
+    for table in ["points", "hits", "bookmarks"] {
+        sqlite::zero(
+            &tx,
+            &format!("UPDATE {table} SET deck_id = :target_id WHERE deck_id = :deck_id"),
+            named_params! {":deck_id": deck_id, ":target_id": target_id},
+        )?;
+    }
+
+    // a user who had bookmarked both decks should only have the one bookmark
+    sqlite::zero(
+        &tx,
+        "DELETE FROM bookmarks
+         WHERE deck_id = :target_id
+               AND id NOT IN (SELECT min(id) FROM bookmarks WHERE deck_id = :target_id GROUP BY user_id)",
+        named_params! {":target_id": target_id},
+    )?;
+
+    // the source deck's name and aliases become aliases of the target so that
+    // looking up the old name finds the merged deck
+    sqlite::zero(
+        &tx,
+        "DELETE FROM deck_aliases
+         WHERE deck_id = :deck_id
+               AND name IN (SELECT name FROM deck_aliases WHERE deck_id = :target_id)",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+    sqlite::zero(
+        &tx,
+        "UPDATE deck_aliases
+         SET deck_id = :target_id
+         WHERE deck_id = :deck_id",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+    if source.title != target.title {
+        aliases::create_alias(&tx, user_id, target_id, &source.title)?;
+    }
+
+    for table in EXTRAS_TABLES {
+        merge_extras(&tx, table, deck_id, target_id, extras_strategy)?;
+    }
+
+    sqlite::zero(
+        &tx,
+        "UPDATE prediction_extras
+         SET parent_deck_id = :target_id
+         WHERE parent_deck_id = :deck_id",
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )?;
+
+    delete(&tx, user_id, deck_id)?;
+
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Merge, target_id, &before)?;
+
+    tx.commit()?;
+
+    Ok(Some(target))
+}
+
+fn merge_extras(
+    tx: &Connection,
+    table: &str,
+    deck_id: Key,
+    target_id: Key,
+    extras_strategy: ExtrasMergeStrategy,
+) -> Result<(), DbError> {
+    let count_stmt = format!("SELECT count(*) FROM {table} WHERE deck_id = :deck_id");
+    let source_count: i32 = sqlite::one(tx, &count_stmt, named_params! {":deck_id": deck_id})?;
+    let target_count: i32 = sqlite::one(tx, &count_stmt, named_params! {":deck_id": target_id})?;
+
+    if source_count == 0 {
+        return Ok(());
+    }
+
+    if target_count > 0 {
+        match extras_strategy {
+            // the source's extras will be cascade deleted along with the source deck
+            ExtrasMergeStrategy::KeepTarget => return Ok(()),
+            ExtrasMergeStrategy::KeepSource => sqlite::zero(
+                tx,
+                &format!("DELETE FROM {table} WHERE deck_id = :target_id"),
+                named_params! {":target_id": target_id},
+            )?,
+        }
+    }
+
+    sqlite::zero(
+        tx,
+        &format!("UPDATE {table} SET deck_id = :target_id WHERE deck_id = :deck_id"),
+        named_params! {":deck_id": deck_id, ":target_id": target_id},
+    )
+}
+
+// converts deck_id into a deck of the given kind, creating and removing the
+// per-kind extras and points as required.
+//
+// conversions that would lose data are only carried out when force is true,
+// otherwise the deck is left as-is and the returned warnings describe the losses
+//
+// returns None if the deck can't be found
+//
+pub(crate) fn convert(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    deck_kind: DeckKind,
+    force: bool,
+) -> Result<Option<DeckConversion>, DbError> {
+    let tx = conn.transaction()?;
+
+    let stmt = Qry::select_decklike()
+        .from_decklike()
+        .where_clause("d.user_id = :user_id AND d.id = :deck_id");
+    let deck: Option<SlimDeck> = sqlite::one_optional(
+        &tx,
+        &stmt,
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+    let Some(deck) = deck else {
+        return Ok(None);
+    };
+
+    if deck.deck_kind == deck_kind {
+        return Ok(Some(DeckConversion {
+            converted: true,
+            warnings: vec![],
+            deck,
+        }));
+    }
+
+    let warnings = conversion_warnings(&tx, &deck, deck_kind)?;
+    if !warnings.is_empty() && !force {
+        return Ok(Some(DeckConversion {
+            converted: false,
+            warnings,
+            deck,
+        }));
+    }
+
+    let before = audit_db::deck_snapshot(&tx, user_id, deck_id)?;
+
+    if deck_kind != DeckKind::Article {
+        sqlite::zero(
+            &tx,
+            "DELETE FROM article_extras WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+    }
+    if deck_kind != DeckKind::Dialogue {
+        sqlite::zero(
+            &tx,
+            "DELETE FROM dialogue_extras WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+    }
+    if deck_kind != DeckKind::Prediction {
+        sqlite::zero(
+            &tx,
+            "DELETE FROM prediction_extras WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+    }
+
+    match deck_kind {
+        DeckKind::Article => sqlite::zero(
+            &tx,
+            "INSERT INTO article_extras(deck_id)
+             SELECT :deck_id
+             WHERE NOT EXISTS (SELECT 1 FROM article_extras WHERE deck_id = :deck_id)",
+            named_params! {":deck_id": deck_id},
+        )?,
+        DeckKind::Dialogue => sqlite::zero(
+            &tx,
+            "INSERT INTO dialogue_extras(deck_id, ai_kind)
+             SELECT :deck_id, :ai_kind
+             WHERE NOT EXISTS (SELECT 1 FROM dialogue_extras WHERE deck_id = :deck_id)",
+            named_params! {":deck_id": deck_id, ":ai_kind": AiKind::OpenAIGpt35Turbo.to_string()},
+        )?,
+        _ => (),
+    }
+
+    if has_single_point(deck_kind) {
+        // events and predictions are displayed using exactly one point
+        sqlite::zero(
+            &tx,
+            "DELETE FROM points
+             WHERE deck_id = :deck_id
+                   AND id <> (SELECT min(id) FROM points WHERE deck_id = :deck_id)",
+            named_params! {":deck_id": deck_id},
+        )?;
+        sqlite::zero(
+            &tx,
+            "INSERT INTO points(deck_id, title, kind, font)
+             SELECT :deck_id, :title, 'point', :font
+             WHERE NOT EXISTS (SELECT 1 FROM points WHERE deck_id = :deck_id)",
+            named_params! {":deck_id": deck_id, ":title": deck.title, ":font": deck.font},
+        )?;
+    } else if !has_points(deck_kind) {
+        sqlite::zero(
+            &tx,
+            "DELETE FROM points WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+    }
+
+    sqlite::zero(
+        &tx,
+        "UPDATE decks
+         SET kind = :deck_kind
+         WHERE user_id = :user_id AND id = :deck_id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":deck_kind": deck_kind},
+    )?;
+
+    let deck: SlimDeck = sqlite::one(
+        &tx,
+        &stmt,
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Convert, deck_id, &before)?;
+
+    tx.commit()?;
+
+    Ok(Some(DeckConversion {
+        converted: true,
+        warnings,
+        deck,
+    }))
+}
+
+fn has_points(deck_kind: DeckKind) -> bool {
+    matches!(
+        deck_kind,
+        DeckKind::Person | DeckKind::Timeline | DeckKind::Event | DeckKind::Prediction
+    )
+}
+
+fn has_single_point(deck_kind: DeckKind) -> bool {
+    matches!(deck_kind, DeckKind::Event | DeckKind::Prediction)
+}
+
+fn conversion_warnings(
+    tx: &Connection,
+    deck: &SlimDeck,
+    deck_kind: DeckKind,
+) -> Result<Vec<String>, DbError> {
+    let mut warnings: Vec<String> = vec![];
+
+    if deck.deck_kind == DeckKind::Article {
+        let num_filled: i32 = sqlite::one(
+            tx,
+            "SELECT count(*)
+             FROM article_extras
+             WHERE deck_id = :deck_id
+                   AND (coalesce(source, '') <> ''
+                        OR coalesce(author, '') <> ''
+                        OR coalesce(short_description, '') <> '')",
+            named_params! {":deck_id": deck.id},
+        )?;
+        if num_filled > 0 {
+            warnings.push("the article's source, author and description will be lost".to_string());
+        }
+    }
+
+    if deck.deck_kind == DeckKind::Dialogue {
+        warnings.push(
+            "the dialogue's AI settings will be lost and its messages can no longer be continued"
+                .to_string(),
+        );
+    }
+
+    let num_predictions: i32 = sqlite::one(
+        tx,
+        "SELECT count(*) FROM prediction_extras WHERE deck_id = :deck_id",
+        named_params! {":deck_id": deck.id},
+    )?;
+    if num_predictions > 0 && deck_kind != DeckKind::Prediction {
+        warnings.push("the prediction's status and score will be lost".to_string());
+    }
+
+    let num_points: i32 = sqlite::one(
+        tx,
+        "SELECT count(*) FROM points WHERE deck_id = :deck_id",
+        named_params! {":deck_id": deck.id},
+    )?;
+    if num_points > 0 && !has_points(deck_kind) {
+        warnings.push(format!(
+            "{num_points} point(s) will be deleted, {} decks have no points",
+            deck_kind.singular()
+        ));
+    } else if num_points > 1 && has_single_point(deck_kind) {
+        warnings.push(format!(
+            "{} point(s) will be deleted, {} decks only have one point",
+            num_points - 1,
+            deck_kind.singular()
+        ));
+    }
+
+    Ok(warnings)
+}
+
 fn get_font_of_deck(conn: &Connection, deck_id: Key) -> Result<Font, DbError> {
     sqlite::one(
         conn,
@@ -473,6 +928,7 @@ pub(crate) fn paginated_unnoted(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -481,12 +937,14 @@ pub(crate) fn paginated_unnoted(
             .left_join("notes n ON (d.id = n.deck_id AND n.kind != 4)")
             .where_decklike_but_no_deck_id()
             .and("n.deck_id IS NULL")
+            .and(DECK_TAG_FILTER)
             .order_by("d.created_at DESC")
             .limit()
             .offset(),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":limit": num_items,
             ":offset": offset,
         },
@@ -498,10 +956,12 @@ pub(crate) fn paginated_unnoted(
             .from_decklike()
             .left_join("notes n ON (d.id = n.deck_id AND n.kind != 4)")
             .where_decklike_but_no_deck_id()
-            .and("n.deck_id IS NULL"),
+            .and("n.deck_id IS NULL")
+            .and(DECK_TAG_FILTER),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
         },
     )?;
 
@@ -516,17 +976,20 @@ pub(crate) fn paginated_recents(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
         &Qry::select_decklike()
             .from_decklike()
             .where_decklike_but_no_deck_id()
+            .and(DECK_TAG_FILTER)
             .order_by("d.created_at DESC")
             .limit()
             .offset(),
         named_params! {
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":user_id": user_id,
             ":limit": num_items,
             ":offset": offset,
@@ -535,10 +998,11 @@ pub(crate) fn paginated_recents(
 
     let total_items = sqlite::one(
         &conn,
-        &Qry::query_count_decklike(),
+        &Qry::query_count_decklike().and(DECK_TAG_FILTER),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
         },
     )?;
 
@@ -553,6 +1017,7 @@ pub(crate) fn paginated_orphans(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -561,12 +1026,14 @@ pub(crate) fn paginated_orphans(
             .where_decklike_but_no_deck_id()
             .and("d.id NOT IN (SELECT deck_id FROM refs GROUP BY deck_id)")
             .and("d.id NOT IN (SELECT n.deck_id FROM notes n INNER JOIN refs r ON n.id = r.note_id GROUP BY n.deck_id)")
+            .and(DECK_TAG_FILTER)
             .order_by("d.created_at DESC")
             .limit()
             .offset(),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":limit": num_items,
             ":offset": offset
         },
@@ -578,10 +1045,12 @@ pub(crate) fn paginated_orphans(
             .from_decklike()
             .where_decklike_but_no_deck_id()
             .and("d.id NOT IN (SELECT deck_id FROM refs GROUP BY deck_id)")
-            .and("d.id NOT IN (SELECT n.deck_id FROM notes n INNER JOIN refs r ON n.id = r.note_id GROUP BY n.deck_id)"),
+            .and("d.id NOT IN (SELECT n.deck_id FROM notes n INNER JOIN refs r ON n.id = r.note_id GROUP BY n.deck_id)")
+            .and(DECK_TAG_FILTER),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
         })?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
@@ -595,6 +1064,7 @@ pub(crate) fn paginated_rated(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -602,12 +1072,14 @@ pub(crate) fn paginated_rated(
             .from_decklike()
             .where_decklike_but_no_deck_id()
             .and("d.impact > 0")
+            .and(DECK_TAG_FILTER)
             .order_by("d.impact desc, d.id desc")
             .limit()
             .offset(),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
             ":limit": num_items,
             ":offset": offset,
         },
@@ -618,10 +1090,12 @@ pub(crate) fn paginated_rated(
         &Qry::select_count()
             .from_decklike()
             .where_decklike_but_no_deck_id()
-            .and("d.impact > 0"),
+            .and("d.impact > 0")
+            .and(DECK_TAG_FILTER),
         named_params! {
             ":user_id": user_id,
             ":deck_kind": deck_kind,
+            ":tag_id": tag_id,
         },
     )?;
 
@@ -629,3 +1103,141 @@ pub(crate) fn paginated_rated(
 
     Ok(res)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, RECIPIENT, add_note, add_ref, create_deck, test_db};
+
+    #[test]
+    fn test_merge_only_removes_new_self_refs() {
+        let mut db = test_db("merge-self-refs");
+        let source = create_deck(&db.conn, "idea", "Stoa");
+        let target = create_deck(&db.conn, "idea", "Stoicism");
+        let elsewhere = create_deck(&db.conn, "idea", "Cynicism");
+
+        // kept, the target already referred to itself
+        let own = add_note(&mut db.conn, target, "a note about itself");
+        add_ref(&db.conn, own, target);
+        // dropped, each of these would become a ref from the target to itself
+        let moved = add_note(&mut db.conn, source, "refers to the target");
+        add_ref(&db.conn, moved, target);
+        let to_source = add_note(&mut db.conn, target, "refers to the source");
+        add_ref(&db.conn, to_source, source);
+        let source_self = add_note(&mut db.conn, source, "the source about itself");
+        add_ref(&db.conn, source_self, source);
+        // re-pointed at the target
+        let incoming = add_note(&mut db.conn, elsewhere, "refers to the source");
+        add_ref(&db.conn, incoming, source);
+
+        merge_into(
+            &mut db.conn,
+            OWNER,
+            source,
+            target,
+            ExtrasMergeStrategy::KeepTarget,
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut referring: Vec<Key> = sqlite::many(
+            &db.conn,
+            "SELECT note_id FROM refs WHERE deck_id = :target_id",
+            named_params! {":target_id": target},
+        )
+        .unwrap();
+        referring.sort_by_key(|id| id.0);
+        assert_eq!(referring, vec![own, incoming]);
+
+        // the notes themselves have all been moved across
+        let in_target = notes::notes_for_deck(&db.conn, target).unwrap();
+        assert_eq!(in_target.len(), 4);
+    }
+
+    fn add_point(conn: &Connection, deck_id: Key, title: &str) {
+        conn.execute(
+            "INSERT INTO points(deck_id, title, kind, font) VALUES (?1, ?2, 'point', ?3)",
+            rusqlite::params![deck_id, title, Font::Serif],
+        )
+        .unwrap();
+    }
+
+    fn count(conn: &Connection, table: &str, deck_id: Key) -> i32 {
+        sqlite::one(
+            conn,
+            &format!("SELECT count(*) FROM {table} WHERE deck_id = :deck_id"),
+            named_params! {":deck_id": deck_id},
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_convert_idea_to_article() {
+        let mut db = test_db("convert-article");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+
+        let conversion = convert(&mut db.conn, OWNER, deck_id, DeckKind::Article, false)
+            .unwrap()
+            .unwrap();
+        assert!(conversion.converted);
+        assert!(conversion.warnings.is_empty());
+        assert_eq!(conversion.deck.deck_kind, DeckKind::Article);
+        assert_eq!(count(&db.conn, "article_extras", deck_id), 1);
+
+        assert!(
+            convert(&mut db.conn, RECIPIENT, deck_id, DeckKind::Idea, true)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_convert_person_to_timeline_keeps_points() {
+        let mut db = test_db("convert-timeline");
+        let deck_id = create_deck(&db.conn, "person", "Seneca");
+        add_point(&db.conn, deck_id, "Born");
+        add_point(&db.conn, deck_id, "Died");
+
+        let conversion = convert(&mut db.conn, OWNER, deck_id, DeckKind::Timeline, false)
+            .unwrap()
+            .unwrap();
+        assert!(conversion.converted);
+        assert!(conversion.warnings.is_empty());
+        assert_eq!(conversion.deck.deck_kind, DeckKind::Timeline);
+        assert_eq!(count(&db.conn, "points", deck_id), 2);
+    }
+
+    #[test]
+    fn test_lossy_conversion_needs_force() {
+        let mut db = test_db("convert-lossy");
+        let deck_id = create_deck(&db.conn, "person", "Seneca");
+        add_point(&db.conn, deck_id, "Born");
+        add_point(&db.conn, deck_id, "Died");
+
+        let conversion = convert(&mut db.conn, OWNER, deck_id, DeckKind::Idea, false)
+            .unwrap()
+            .unwrap();
+        assert!(!conversion.converted);
+        assert_eq!(
+            conversion.warnings,
+            ["2 point(s) will be deleted, idea decks have no points"]
+        );
+        assert_eq!(conversion.deck.deck_kind, DeckKind::Person);
+        assert_eq!(count(&db.conn, "points", deck_id), 2);
+        let kind: String = sqlite::one(
+            &db.conn,
+            "SELECT kind FROM decks WHERE id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )
+        .unwrap();
+        assert_eq!(kind, "person");
+
+        let conversion = convert(&mut db.conn, OWNER, deck_id, DeckKind::Idea, true)
+            .unwrap()
+            .unwrap();
+        assert!(conversion.converted);
+        assert_eq!(conversion.warnings.len(), 1);
+        assert_eq!(conversion.deck.deck_kind, DeckKind::Idea);
+        assert_eq!(count(&db.conn, "points", deck_id), 0);
+    }
+}
@@ -19,12 +19,14 @@ use std::str::FromStr;
 
 use crate::ai::openai_interface;
 use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::decks;
 use crate::db::notes as notes_db;
 use crate::db::notes as db_notes;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
 use crate::interop::decks::{DeckKind, SlimDeck};
 use crate::interop::dialogues as interop;
 use crate::interop::dialogues::{Dialogue, ProtoDialogue};
@@ -57,6 +59,7 @@ impl TryFrom<(decks::DeckBase, DialogueExtra)> for interop::Dialogue {
             insignia: deck.insignia,
             font: deck.font,
             impact: deck.impact,
+            version: deck.version,
 
             notes: vec![],
             arrivals: vec![],
@@ -78,6 +81,7 @@ impl FromRow for interop::Dialogue {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             notes: vec![],
             arrivals: vec![],
@@ -184,9 +188,12 @@ pub(crate) fn edit(
     user_id: Key,
     dialogue: ProtoDialogue,
     dialogue_id: Key,
+    version: i32,
 ) -> Result<Dialogue, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, dialogue_id)?;
+
     let edited_deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -197,6 +204,7 @@ pub(crate) fn edit(
         dialogue.insignia,
         dialogue.font,
         dialogue.impact,
+        version,
     )?;
 
     let sql_query: &str = "SELECT deck_id, ai_kind
@@ -209,6 +217,8 @@ pub(crate) fn edit(
     let mut dialogue: interop::Dialogue = (edited_deck, dialogue_extras).try_into()?;
     dialogue.messages = get_original_chat_messages(&tx, user_id, dialogue_id)?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, dialogue_id, &before)?;
+
     tx.commit()?;
 
     dialogue.notes = notes_db::notes_for_deck(conn, dialogue_id)?;
@@ -332,9 +342,20 @@ fn create_chat_message(
         },
     )?;
 
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::Note,
+        Some(new_note.id),
+        Payload::after(&audit_db::note_snapshot(conn, user_id, new_note.id)?),
+    )?;
+
     Ok(new_note.id)
 }
 
+// private notes are never sent to the AI, so they're left out of the history
+//
 pub(crate) fn get_chat_history(
     conn: &mut rusqlite::Connection,
     user_id: Key,
@@ -345,7 +366,7 @@ pub(crate) fn get_chat_history(
                      LEFT JOIN notes ON notes.id = dm.note_id
                      LEFT JOIN decks ON decks.id = notes.deck_id
                 WHERE
-                      decks.user_id=:user_id AND decks.id = :deck_id
+                      decks.user_id=:user_id AND decks.id = :deck_id AND NOT notes.private
                 ORDER BY dm.id";
 
     let messages: Vec<openai_interface::ChatMessage> = sqlite::many(
@@ -16,11 +16,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit as audit_db;
 use crate::db::decks::{self, DeckBase, DeckBaseOrigin};
 use crate::db::notes as notes_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::{DeckKind, SlimDeck};
 use crate::interop::events::{Event, ProtoEvent};
 use crate::interop::font::Font;
@@ -58,6 +60,7 @@ impl From<(DeckBase, EventExtra)> for Event {
             insignia: deck.insignia,
             font: deck.font,
             impact: deck.impact,
+            version: deck.version,
 
             location_textual: extra.location_textual,
             longitude: extra.longitude,
@@ -104,6 +107,7 @@ impl FromRow for Event {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             location_textual: row.get("location_textual")?,
             longitude: row.get("longitude")?,
@@ -216,9 +220,12 @@ pub(crate) fn edit(
     user_id: Key,
     event: ProtoEvent,
     event_id: Key,
+    version: i32,
 ) -> Result<Event, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, event_id)?;
+
     let edited_deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -229,6 +236,7 @@ pub(crate) fn edit(
         event.insignia,
         event.font,
         event.impact,
+        version,
     )?;
 
     let sql_query = "
@@ -259,6 +267,8 @@ pub(crate) fn edit(
         },
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, event_id, &before)?;
+
     tx.commit()?;
 
     let mut event: Event = (edited_deck, event_extras).into();
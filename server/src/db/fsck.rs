@@ -0,0 +1,573 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::db::DbError;
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::fsck::{Finding, FtsCommand, FtsReport};
+use crate::interop::notes::NoteKind;
+
+use rusqlite::{Connection, Row, named_params};
+
+// the external content full text search tables, kept in sync by triggers
+//
+pub(crate) const FTS_TABLES: [&str; 8] = [
+    "decks_fts",
+    "decks_trigram",
+    "points_fts",
+    "notes_fts",
+    "notes_trigram",
+    "article_extras_fts",
+    "refs_fts",
+    "dialogue_messages_fts",
+];
+
+struct ChainNote {
+    id: Key,
+    deck_id: Key,
+    kind: i32,
+    point_id: Option<Key>,
+    prev_note_id: Option<Key>,
+}
+
+impl FromRow for ChainNote {
+    fn from_row(row: &Row) -> rusqlite::Result<ChainNote> {
+        Ok(ChainNote {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            kind: row.get("kind")?,
+            point_id: row.get("point_id")?,
+            prev_note_id: row.get("prev_note_id")?,
+        })
+    }
+}
+
+struct DanglingPoint {
+    note_id: Key,
+    deck_id: Key,
+    point_id: Key,
+}
+
+impl FromRow for DanglingPoint {
+    fn from_row(row: &Row) -> rusqlite::Result<DanglingPoint> {
+        Ok(DanglingPoint {
+            note_id: row.get("note_id")?,
+            deck_id: row.get("deck_id")?,
+            point_id: row.get("point_id")?,
+        })
+    }
+}
+
+pub(crate) struct ImageRow {
+    pub id: Key,
+    pub user_id: Key,
+    pub filename: String,
+}
+
+impl FromRow for ImageRow {
+    fn from_row(row: &Row) -> rusqlite::Result<ImageRow> {
+        Ok(ImageRow {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            filename: row.get("filename")?,
+        })
+    }
+}
+
+pub(crate) struct UserImageCount {
+    pub user_id: Key,
+    pub image_count: i32,
+}
+
+impl FromRow for UserImageCount {
+    fn from_row(row: &Row) -> rusqlite::Result<UserImageCount> {
+        Ok(UserImageCount {
+            user_id: row.get("id")?,
+            image_count: row.get("image_count")?,
+        })
+    }
+}
+
+fn finding(check: &'static str, description: String, repaired: bool) -> Finding {
+    Finding {
+        check,
+        description,
+        repaired,
+    }
+}
+
+// notes whose point_id refers to a point that doesn't exist or belongs to another deck.
+// repairing detaches the note from the point so that it becomes a deck level note
+//
+pub(crate) fn check_dangling_point_ids(
+    conn: &Connection,
+    repair: bool,
+) -> Result<Vec<Finding>, DbError> {
+    let dangling: Vec<DanglingPoint> = sqlite::many(
+        conn,
+        "SELECT n.id AS note_id, n.deck_id, n.point_id
+         FROM notes n LEFT JOIN points p ON p.id = n.point_id
+         WHERE n.point_id IS NOT NULL AND (p.id IS NULL OR p.deck_id <> n.deck_id)
+         ORDER BY n.id",
+        [],
+    )?;
+
+    let mut findings = vec![];
+    for d in dangling {
+        if repair {
+            sqlite::zero(
+                conn,
+                "UPDATE notes
+                 SET point_id = NULL
+                 WHERE id = :note_id",
+                named_params! {":note_id": d.note_id},
+            )?;
+        }
+        findings.push(finding(
+            "dangling_point_id",
+            format!(
+                "note {} in deck {} refers to point {} which isn't in that deck",
+                d.note_id, d.deck_id, d.point_id
+            ),
+            repair,
+        ));
+    }
+
+    Ok(findings)
+}
+
+// returns the notes of a passage in the order that their prev_note_id links should describe.
+// Starting from each head (in id order) the chain is followed, taking the lowest id whenever
+// a note has more than one successor. Anything not reachable from a head (cycles, forks,
+// links to notes in other passages) is then appended, again in id order.
+//
+fn canonical_order(notes: &[ChainNote]) -> Vec<Key> {
+    let ids: HashSet<Key> = notes.iter().map(|n| n.id).collect();
+
+    let mut successors: HashMap<Key, Vec<Key>> = HashMap::new();
+    let mut heads: Vec<Key> = vec![];
+    for n in notes {
+        match n.prev_note_id {
+            Some(prev) if ids.contains(&prev) && prev != n.id => {
+                successors.entry(prev).or_default().push(n.id)
+            }
+            _ => heads.push(n.id),
+        }
+    }
+    heads.sort_by_key(|k| k.0);
+
+    let mut remaining: Vec<Key> = notes.iter().map(|n| n.id).collect();
+    remaining.sort_by_key(|k| k.0);
+
+    let mut visited: HashSet<Key> = HashSet::new();
+    let mut order: Vec<Key> = vec![];
+
+    for start in heads.into_iter().chain(remaining) {
+        let mut current = Some(start);
+        while let Some(id) = current {
+            if !visited.insert(id) {
+                break;
+            }
+            order.push(id);
+            current = successors.get(&id).and_then(|s| {
+                s.iter()
+                    .filter(|k| !visited.contains(k))
+                    .min_by_key(|k| k.0)
+                    .copied()
+            });
+        }
+    }
+
+    order
+}
+
+// broken prev_note_id chains: passages with several heads, forks, cycles or links to notes
+// outside of the passage. Repairing relinks each passage into a single chain
+//
+pub(crate) fn check_note_chains(conn: &Connection, repair: bool) -> Result<Vec<Finding>, DbError> {
+    let notes: Vec<ChainNote> = sqlite::many(
+        conn,
+        "SELECT id, deck_id, kind, point_id, prev_note_id
+         FROM notes
+         WHERE kind != :deck_meta
+         ORDER BY deck_id, kind, point_id, id",
+        named_params! {":deck_meta": i32::from(NoteKind::NoteDeckMeta)},
+    )?;
+
+    let mut passages: HashMap<(Key, i32, Option<Key>), Vec<ChainNote>> = HashMap::new();
+    for n in notes {
+        passages
+            .entry((n.deck_id, n.kind, n.point_id))
+            .or_default()
+            .push(n);
+    }
+
+    let mut keys: Vec<(Key, i32, Option<Key>)> = passages.keys().copied().collect();
+    keys.sort_by_key(|(deck_id, kind, point_id)| (deck_id.0, *kind, point_id.map(|p| p.0)));
+
+    let mut findings = vec![];
+    for key in keys {
+        let passage = &passages[&key];
+        let order = canonical_order(passage);
+
+        let current: HashMap<Key, Option<Key>> =
+            passage.iter().map(|n| (n.id, n.prev_note_id)).collect();
+
+        let mut relinked = 0;
+        for (i, id) in order.iter().enumerate() {
+            let expected = if i == 0 { None } else { Some(order[i - 1]) };
+            if current[id] != expected {
+                relinked += 1;
+                if repair {
+                    sqlite::zero(
+                        conn,
+                        "UPDATE notes
+                         SET prev_note_id = :prev_note_id
+                         WHERE id = :note_id",
+                        named_params! {":note_id": id, ":prev_note_id": expected},
+                    )?;
+                }
+            }
+        }
+
+        if relinked > 0 {
+            let (deck_id, kind, point_id) = key;
+            let location = match point_id {
+                Some(point_id) => format!("point {point_id} of deck {deck_id}"),
+                None => format!("deck {deck_id}"),
+            };
+            findings.push(finding(
+                "note_chain",
+                format!(
+                    "passage (note kind {kind}) in {location} has {relinked} of {} notes incorrectly linked",
+                    passage.len()
+                ),
+                repair,
+            ));
+        }
+    }
+
+    Ok(findings)
+}
+
+// fts5's integrity-check with a rank of 1 also compares the index against the content table,
+// an out of sync index is reported as an error. Repairing rebuilds the index from its content
+//
+pub(crate) fn check_fts_tables(conn: &Connection, repair: bool) -> Result<Vec<Finding>, DbError> {
+    let mut findings = vec![];
+
+    for table in FTS_TABLES {
+        if let Err(e) = fts_command(conn, table, FtsCommand::IntegrityCheck) {
+            if repair {
+                fts_command(conn, table, FtsCommand::Rebuild)?;
+            }
+            findings.push(finding(
+                "fts",
+                format!("{table} is out of sync with its content: {e}"),
+                repair,
+            ));
+        }
+    }
+
+    Ok(findings)
+}
+
+fn fts_command(conn: &Connection, table: &str, command: FtsCommand) -> Result<(), DbError> {
+    let sql = match command {
+        FtsCommand::IntegrityCheck => {
+            format!("INSERT INTO {table}({table}, rank) VALUES('integrity-check', 1)")
+        }
+        _ => format!("INSERT INTO {table}({table}) VALUES('{command}')"),
+    };
+    sqlite::zero(conn, &sql, [])
+}
+
+// the size of an fts5 index is the size of the blocks in its %_data shadow table
+//
+fn fts_index_bytes(conn: &Connection, table: &str) -> Result<i64, DbError> {
+    sqlite::one(
+        conn,
+        &format!("SELECT coalesce(sum(length(block)), 0) FROM {table}_data"),
+        [],
+    )
+}
+
+// runs the command on every full text search table. A failing integrity-check is
+// part of the report rather than an error, as is any other command that sqlite rejects
+//
+pub(crate) fn fts_maintenance(
+    conn: &Connection,
+    command: FtsCommand,
+) -> Result<Vec<FtsReport>, DbError> {
+    let mut reports = vec![];
+
+    for table in FTS_TABLES {
+        let index_bytes_before = fts_index_bytes(conn, table)?;
+
+        let start = Instant::now();
+        let res = fts_command(conn, table, command);
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        reports.push(FtsReport {
+            table,
+            command,
+            ok: res.is_ok(),
+            message: res.err().map(|e| e.to_string()),
+            duration_ms,
+            index_bytes_before,
+            index_bytes_after: fts_index_bytes(conn, table)?,
+        });
+    }
+
+    Ok(reports)
+}
+
+// prediction decks without the point that holds their date and location, see
+// predictions::get_or_create. Repairing adds an empty point titled after the deck
+//
+pub(crate) fn check_prediction_points(
+    conn: &Connection,
+    repair: bool,
+) -> Result<Vec<Finding>, DbError> {
+    let deck_ids: Vec<Key> = sqlite::many(
+        conn,
+        "SELECT d.id
+         FROM decks d
+         WHERE d.kind = 'prediction'
+               AND NOT EXISTS (SELECT 1 FROM points p WHERE p.deck_id = d.id)
+         ORDER BY d.id",
+        [],
+    )?;
+
+    let mut findings = vec![];
+    for deck_id in deck_ids {
+        if repair {
+            sqlite::zero(
+                conn,
+                "INSERT INTO points(deck_id, title, kind, font)
+                 SELECT id, name, 'point', font FROM decks WHERE id = :deck_id",
+                named_params! {":deck_id": deck_id},
+            )?;
+        }
+        findings.push(finding(
+            "prediction_points",
+            format!("prediction deck {deck_id} has no point"),
+            repair,
+        ));
+    }
+
+    Ok(findings)
+}
+
+pub(crate) fn get_all_images(conn: &Connection) -> Result<Vec<ImageRow>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id, user_id, filename
+         FROM images
+         ORDER BY user_id, id",
+        [],
+    )
+}
+
+pub(crate) fn delete_image_entry(conn: &Connection, image_id: Key) -> Result<(), DbError> {
+    sqlite::zero(
+        conn,
+        "DELETE FROM images
+         WHERE id = :image_id",
+        named_params! {":image_id": image_id},
+    )
+}
+
+pub(crate) fn get_image_counts(conn: &Connection) -> Result<Vec<UserImageCount>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id, image_count
+         FROM users
+         ORDER BY id",
+        [],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::predictions;
+    use crate::db::test_util::{OWNER, add_note, create_deck, passage, test_db};
+
+    fn chain_note(id: i64, prev_note_id: Option<i64>) -> ChainNote {
+        ChainNote {
+            id: Key(id),
+            deck_id: Key(1),
+            kind: i32::from(NoteKind::Note),
+            point_id: None,
+            prev_note_id: prev_note_id.map(Key),
+        }
+    }
+
+    fn order(notes: &[ChainNote]) -> Vec<i64> {
+        canonical_order(notes).into_iter().map(|k| k.0).collect()
+    }
+
+    #[test]
+    fn test_canonical_order_of_a_single_chain() {
+        let notes = [
+            chain_note(3, Some(1)),
+            chain_note(1, None),
+            chain_note(2, Some(3)),
+        ];
+        assert_eq!(order(&notes), [1, 3, 2]);
+    }
+
+    #[test]
+    fn test_canonical_order_of_a_fork() {
+        // 2 and 3 both follow 1, the lower id wins and 3 is appended after the chain
+        let notes = [
+            chain_note(1, None),
+            chain_note(3, Some(1)),
+            chain_note(2, Some(1)),
+            chain_note(4, Some(2)),
+        ];
+        assert_eq!(order(&notes), [1, 2, 4, 3]);
+    }
+
+    #[test]
+    fn test_canonical_order_of_a_cycle() {
+        let notes = [
+            chain_note(1, None),
+            chain_note(2, Some(3)),
+            chain_note(3, Some(2)),
+        ];
+        assert_eq!(order(&notes), [1, 2, 3]);
+
+        // a note that is its own predecessor counts as a head
+        let notes = [chain_note(1, Some(1)), chain_note(2, Some(1))];
+        assert_eq!(order(&notes), [1, 2]);
+
+        // a cycle without any head is still walked in id order
+        let notes = [chain_note(5, Some(6)), chain_note(6, Some(5))];
+        assert_eq!(order(&notes), [5, 6]);
+    }
+
+    #[test]
+    fn test_canonical_order_of_multiple_heads() {
+        let notes = [
+            chain_note(3, None),
+            chain_note(4, Some(3)),
+            chain_note(1, None),
+            chain_note(2, Some(1)),
+        ];
+        assert_eq!(order(&notes), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_canonical_order_of_cross_passage_links() {
+        // 2 links to a note in another passage so it becomes a head of its own
+        let notes = [
+            chain_note(1, None),
+            chain_note(2, Some(99)),
+            chain_note(3, Some(2)),
+        ];
+        assert_eq!(order(&notes), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_repair_note_chains() {
+        let mut db = test_db("fsck-note-chains");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let other_id = create_deck(&db.conn, "idea", "Cynicism");
+        let a = add_note(&mut db.conn, deck_id, "a");
+        let b = add_note(&mut db.conn, deck_id, "b");
+        let c = add_note(&mut db.conn, deck_id, "c");
+        let elsewhere = add_note(&mut db.conn, other_id, "elsewhere");
+        assert!(check_note_chains(&db.conn, false).unwrap().is_empty());
+
+        // c forks from a, and b links to a note in another deck
+        db.conn
+            .execute("UPDATE notes SET prev_note_id = ?1 WHERE id = ?2", [a, c])
+            .unwrap();
+        db.conn
+            .execute(
+                "UPDATE notes SET prev_note_id = ?1 WHERE id = ?2",
+                [elsewhere, b],
+            )
+            .unwrap();
+
+        let findings = check_note_chains(&db.conn, false).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(!findings[0].repaired);
+
+        let findings = check_note_chains(&db.conn, true).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].repaired);
+
+        assert!(check_note_chains(&db.conn, false).unwrap().is_empty());
+        assert_eq!(passage(&db.conn, deck_id, None), ["a", "c", "b"]);
+        assert_eq!(passage(&db.conn, other_id, None), ["elsewhere"]);
+    }
+
+    #[test]
+    fn test_fsck_accepts_new_predictions() {
+        let mut db = test_db("fsck-predictions");
+        let prediction =
+            predictions::get_or_create(&mut db.conn, OWNER, "Rain tomorrow".to_string()).unwrap();
+
+        let findings = check_prediction_points(&db.conn, false).unwrap();
+        assert!(findings.is_empty());
+
+        db.conn
+            .execute("DELETE FROM points WHERE deck_id = ?1", [prediction.id])
+            .unwrap();
+        let findings = check_prediction_points(&db.conn, true).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert!(
+            predictions::get(&db.conn, OWNER, prediction.id)
+                .unwrap()
+                .is_some()
+        );
+        assert!(check_prediction_points(&db.conn, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fts_maintenance() {
+        let mut db = test_db("fsck-fts");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        add_note(&mut db.conn, deck_id, "the obstacle is the way");
+
+        for command in [
+            FtsCommand::IntegrityCheck,
+            FtsCommand::Optimize,
+            FtsCommand::Rebuild,
+            FtsCommand::IntegrityCheck,
+        ] {
+            let reports = fts_maintenance(&db.conn, command).unwrap();
+            assert_eq!(reports.len(), FTS_TABLES.len());
+            for report in reports {
+                assert!(
+                    report.ok,
+                    "{} {}: {:?}",
+                    report.table, command, report.message
+                );
+                assert_eq!(report.command, command);
+            }
+        }
+
+        assert!(check_fts_tables(&db.conn, false).unwrap().is_empty());
+    }
+}
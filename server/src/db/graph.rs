@@ -17,13 +17,14 @@
 
 use crate::db::DbError;
 use crate::db::qry::Qry;
+use crate::db::shares as shares_db;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
 use crate::interop::decks::{DeckKind, RefKind, SlimDeck};
 use crate::interop::font::Font;
-use crate::interop::graph::{ConnectivityData, Direction, Edge};
+use crate::interop::graph::{ConnectivityData, Direction, Edge, GraphReach};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use rusqlite::types::{FromSql, FromSqlResult, ValueRef};
 use rusqlite::{Connection, Row, named_params};
@@ -129,54 +130,114 @@ fn add_edge(edges_map: &mut HashMap<(Key, Key, Direction), Edge>, edge: Edge) {
     edges_map.insert(edges_map_key, edge);
 }
 
+// a breadth first walk out from the deck, one neighbours query for each deck
+// that's expanded. Once the budget is spent any further decks (and the edges
+// to them) are left out
+//
 pub(crate) fn get(
     conn: &mut rusqlite::Connection,
     user_id: Key,
     deck_id: Key,
-) -> Result<ConnectivityData, DbError> {
+    reach: &GraphReach,
+) -> Result<Option<ConnectivityData>, DbError> {
     let stmt = Qry::select_decklike()
         .from_decklike()
         .where_clause("d.user_id = :user_id AND d.id = :deck_id");
-    let source_deck: SlimDeck = sqlite::one(
+    let own_deck: Option<SlimDeck> = sqlite::one_optional(
         conn,
         &stmt,
         named_params! {":user_id": user_id, ":deck_id": deck_id},
     )?;
 
+    // a deck that's been shared by another user shows the owner's
+    // connections, but only to the decks that have also been shared
+    //
+    let (source_deck, owner_id, shared, foreign) = match own_deck {
+        Some(deck) => (deck, user_id, None, None),
+        None => match shares_db::get_foreign_deck(conn, user_id, deck_id)? {
+            Some(foreign_deck) => (
+                foreign_deck.deck,
+                shares_db::get_deck_owner_id(conn, deck_id)?,
+                Some(shares_db::shared_deck_ids(conn, user_id)?),
+                Some(foreign_deck.foreign),
+            ),
+            None => return Ok(None),
+        },
+    };
+
     let mut decks_map: HashMap<Key, SlimDeck> = HashMap::new();
     let mut edges_map: HashMap<(Key, Key, Direction), Edge> = HashMap::new();
 
-    let direct_neighbours = neighbours(&conn, user_id, deck_id)?;
-
-    for connectivity in &direct_neighbours {
-        add_edge(
-            &mut edges_map,
-            Edge {
-                from_id: deck_id,
-                to_id: connectivity.deck_id,
-                ref_kind: connectivity.ref_kind,
-                direction: connectivity.direction,
-            },
-        );
+    let mut visited: HashSet<Key> = HashSet::from([deck_id]);
+    let mut frontier: Vec<Key> = vec![deck_id];
+    let mut truncated = false;
 
-        decks_map.insert(
-            connectivity.deck_id,
-            slimdeck_from_connectivity(connectivity),
-        );
+    for _ in 0..reach.depth {
+        let mut next_frontier: Vec<Key> = vec![];
+
+        for from_id in frontier {
+            let mut connectivities = neighbours(conn, owner_id, from_id, shared.is_none())?;
+            connectivities.retain(|connectivity| {
+                shared
+                    .as_ref()
+                    .is_none_or(|shared| shared.contains(&connectivity.deck_id))
+                    && (reach.ref_kinds.is_empty()
+                        || reach.ref_kinds.contains(&connectivity.ref_kind))
+                    && (reach.deck_kinds.is_empty()
+                        || reach.deck_kinds.contains(&connectivity.deck_kind))
+            });
+
+            for connectivity in &connectivities {
+                if !visited.contains(&connectivity.deck_id) {
+                    if decks_map.len() >= reach.budget {
+                        truncated = true;
+                        continue;
+                    }
+                    visited.insert(connectivity.deck_id);
+                    decks_map.insert(
+                        connectivity.deck_id,
+                        slimdeck_from_connectivity(connectivity),
+                    );
+                    if !connectivity.graph_terminator {
+                        next_frontier.push(connectivity.deck_id);
+                    }
+                }
+
+                add_edge(
+                    &mut edges_map,
+                    Edge {
+                        from_id,
+                        to_id: connectivity.deck_id,
+                        ref_kind: connectivity.ref_kind,
+                        direction: connectivity.direction,
+                    },
+                );
+            }
+        }
+
+        frontier = next_frontier;
     }
 
     let decks = decks_map.values().map(fucking_copy_slimdeck).collect();
     let edges = edges_map.values().map(fucking_copy_edge).collect();
 
-    Ok(ConnectivityData {
+    Ok(Some(ConnectivityData {
         source_deck,
         edges,
         decks,
-    })
+        foreign,
+        truncated,
+    }))
 }
 
-fn neighbours(conn: &Connection, user_id: Key, deck_id: Key) -> Result<Vec<Connectivity>, DbError> {
+fn neighbours(
+    conn: &Connection,
+    user_id: Key,
+    deck_id: Key,
+    include_private: bool,
+) -> Result<Vec<Connectivity>, DbError> {
     // 'incoming query' union 'outgoing query'
+    // connections made by private notes are only included for the owner
     //
     let stmt = Qry::select("0 as direction, r.kind as ref_kind, d.id as deck_id, d.name as name, d.kind as deck_kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact")
         .from("refs r, notes n, decks d")
@@ -184,17 +245,139 @@ fn neighbours(conn: &Connection, user_id: Key, deck_id: Key) -> Result<Vec<Conne
         .and("n.id = r.note_id")
         .and("d.id = n.deck_id")
         .and("d.user_id = :user_id")
+        .and("(:include_private OR NOT n.private)")
         .union()
         .add(&Qry::select("1 as direction, r.kind as ref_kind, d.id as deck_id, d.name as name, d.kind as deck_kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact")
              .from("notes n, refs r, decks d")
              .where_clause("n.deck_id = :deck_id")
              .and("r.note_id = n.id")
              .and("d.id = r.deck_id")
-             .and("d.user_id = :user_id"));
+             .and("d.user_id = :user_id")
+             .and("(:include_private OR NOT n.private)"));
 
     sqlite::many(
         conn,
         &stmt,
-        named_params! {":user_id": user_id, ":deck_id": deck_id},
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":include_private": include_private},
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, add_note, add_ref, create_deck, test_db};
+
+    // a chain of ideas, stoicism -> virtue -> ethics -> aristotle, with zeno as a
+    // person that stoicism refers to as its parent
+    fn graph_fixture(conn: &mut Connection) -> [Key; 5] {
+        let stoicism = create_deck(conn, "idea", "Stoicism");
+        let virtue = create_deck(conn, "idea", "Virtue");
+        let ethics = create_deck(conn, "idea", "Ethics");
+        let aristotle = create_deck(conn, "person", "Aristotle");
+        let zeno = create_deck(conn, "person", "Zeno");
+
+        for (from, to) in [(stoicism, virtue), (virtue, ethics), (ethics, aristotle)] {
+            let note_id = add_note(conn, from, "see also");
+            add_ref(conn, note_id, to);
+        }
+        let note_id = add_note(conn, stoicism, "founded by");
+        add_ref(conn, note_id, zeno);
+        conn.execute(
+            "UPDATE refs SET kind = 'ref_to_parent' WHERE note_id = ?1",
+            [note_id],
+        )
+        .unwrap();
+
+        [stoicism, virtue, ethics, aristotle, zeno]
+    }
+
+    fn graph_deck_ids(graph: &ConnectivityData) -> Vec<Key> {
+        let mut ids: Vec<Key> = graph.decks.iter().map(|d| d.id).collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    #[test]
+    fn test_graph_hops() {
+        let mut db = test_db("graph-hops");
+        let [stoicism, virtue, ethics, _aristotle, zeno] = graph_fixture(&mut db.conn);
+
+        let reach = GraphReach {
+            depth: 2,
+            ..Default::default()
+        };
+        let graph = get(&mut db.conn, OWNER, stoicism, &reach).unwrap().unwrap();
+        assert_eq!(graph_deck_ids(&graph), vec![virtue, ethics, zeno]);
+        assert_eq!(graph.edges.len(), 3);
+        assert!(!graph.truncated);
+
+        // a terminator is in the graph but the walk doesn't go through it
+        db.conn
+            .execute(
+                "UPDATE decks SET graph_terminator = TRUE WHERE id = ?1",
+                [virtue],
+            )
+            .unwrap();
+        let graph = get(&mut db.conn, OWNER, stoicism, &reach).unwrap().unwrap();
+        assert_eq!(graph_deck_ids(&graph), vec![virtue, zeno]);
+    }
+
+    #[test]
+    fn test_graph_filters() {
+        let mut db = test_db("graph-filters");
+        let [stoicism, virtue, ethics, aristotle, zeno] = graph_fixture(&mut db.conn);
+
+        let reach = GraphReach {
+            depth: 4,
+            ref_kinds: vec![RefKind::RefToParent],
+            ..Default::default()
+        };
+        let graph = get(&mut db.conn, OWNER, stoicism, &reach).unwrap().unwrap();
+        assert_eq!(graph_deck_ids(&graph), vec![zeno]);
+
+        let reach = GraphReach {
+            depth: 4,
+            deck_kinds: vec![DeckKind::Idea],
+            ..Default::default()
+        };
+        let graph = get(&mut db.conn, OWNER, stoicism, &reach).unwrap().unwrap();
+        assert_eq!(graph_deck_ids(&graph), vec![virtue, ethics]);
+
+        let reach = GraphReach {
+            depth: 4,
+            ..Default::default()
+        };
+        let graph = get(&mut db.conn, OWNER, stoicism, &reach).unwrap().unwrap();
+        assert_eq!(
+            graph_deck_ids(&graph),
+            vec![virtue, ethics, aristotle, zeno]
+        );
+    }
+
+    #[test]
+    fn test_graph_budget() {
+        let mut db = test_db("graph-budget");
+        let [stoicism, ..] = graph_fixture(&mut db.conn);
+
+        let reach = GraphReach {
+            depth: 4,
+            budget: 1,
+            ..Default::default()
+        };
+        let graph = get(&mut db.conn, OWNER, stoicism, &reach).unwrap().unwrap();
+        assert_eq!(graph.decks.len(), 1);
+        assert!(graph.truncated);
+
+        // every edge is between decks that are in the graph
+        let ids = graph_deck_ids(&graph);
+        for edge in &graph.edges {
+            for id in [edge.from_id, edge.to_id] {
+                assert!(
+                    id == stoicism || ids.contains(&id),
+                    "{id:?} isn't in the graph"
+                );
+            }
+        }
+        assert_eq!(graph.edges.len(), 1);
+    }
+}
@@ -15,11 +15,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::audit as audit_db;
 use crate::db::notes as notes_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::db::{DbError, decks};
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::{DeckKind, ProtoSlimDeck};
 use crate::interop::font::Font;
 use crate::interop::ideas::Idea;
@@ -36,6 +38,7 @@ impl FromRow for Idea {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             notes: vec![],
             arrivals: vec![],
@@ -96,6 +99,8 @@ pub(crate) fn convert(
         i.arrivals = notes_db::arrivals_for_deck(conn, idea_id)?;
     }
 
+    let before = audit_db::deck_snapshot(conn, user_id, idea_id)?;
+
     let target_kind = DeckKind::Concept;
     let stmt = "UPDATE decks
                 SET kind = :deck_kind
@@ -106,6 +111,10 @@ pub(crate) fn convert(
         named_params! {":user_id": user_id, ":deck_id": idea_id, ":deck_kind": target_kind},
     )?;
 
+    if before.is_some() {
+        audit_db::record_deck_change(conn, user_id, AuditAction::Convert, idea_id, &before)?;
+    }
+
     Ok(idea)
 }
 
@@ -134,9 +143,12 @@ pub(crate) fn edit(
     user_id: Key,
     idea: ProtoSlimDeck,
     idea_id: Key,
+    version: i32,
 ) -> Result<Idea, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, idea_id)?;
+
     let deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -147,8 +159,11 @@ pub(crate) fn edit(
         idea.insignia,
         idea.font,
         idea.impact,
+        version,
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, idea_id, &before)?;
+
     tx.commit()?;
 
     let mut idea: Idea = deck.into();
@@ -0,0 +1,160 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Change notifications for open clients. Every audited mutation queues an
+// event, the events are only broadcast once the db_thread closure that made
+// them has returned successfully so that a client never re-fetches data
+// before it has been committed.
+
+use crate::db::DbError;
+use crate::db::sqlite;
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::live::LiveEvent;
+use rusqlite::named_params;
+use std::cell::RefCell;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+// slow clients that fall further behind than this are told to re-sync
+const CHANNEL_CAPACITY: usize = 256;
+
+static CHANNEL: LazyLock<broadcast::Sender<LiveEvent>> =
+    LazyLock::new(|| broadcast::channel(CHANNEL_CAPACITY).0);
+
+thread_local! {
+    // db_thread closures run on tokio's blocking pool, one at a time per thread
+    static PENDING: RefCell<Vec<LiveEvent>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn subscribe() -> broadcast::Receiver<LiveEvent> {
+    CHANNEL.subscribe()
+}
+
+pub(crate) fn queue(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    action: AuditAction,
+    entity: AuditEntity,
+    entity_id: Option<Key>,
+) -> Result<(), DbError> {
+    if CHANNEL.receiver_count() == 0 {
+        return Ok(());
+    }
+
+    let deck_id = match entity_id {
+        Some(id) => deck_for(conn, entity, id)?,
+        None => None,
+    };
+    let event = LiveEvent {
+        user_id,
+        entity: entity.as_str(),
+        action: action.as_str(),
+        id: entity_id,
+        deck_id,
+    };
+
+    PENDING.with_borrow_mut(|pending| {
+        if !pending.iter().any(|e| is_same(e, &event)) {
+            pending.push(event);
+        }
+    });
+
+    Ok(())
+}
+
+// called before a db_thread closure runs, drops anything left behind by
+// work that didn't go through db_thread
+pub(crate) fn discard() {
+    PENDING.with_borrow_mut(|pending| pending.clear());
+}
+
+// the number of queued events, pass it to rollback_to when the work that
+// follows is rolled back to a savepoint
+pub(crate) fn mark() -> usize {
+    PENDING.with_borrow(|pending| pending.len())
+}
+
+// drops the events queued since the mark was taken
+pub(crate) fn rollback_to(mark: usize) {
+    PENDING.with_borrow_mut(|pending| pending.truncate(mark));
+}
+
+// called after a db_thread closure has succeeded
+pub(crate) fn flush() {
+    let pending = PENDING.with_borrow_mut(std::mem::take);
+    for event in pending {
+        // an error only means that nobody is listening anymore
+        let _ = CHANNEL.send(event);
+    }
+}
+
+fn is_same(a: &LiveEvent, b: &LiveEvent) -> bool {
+    a.user_id == b.user_id && a.entity == b.entity && a.action == b.action && a.id == b.id
+}
+
+// the deck that a client should re-fetch to see the change
+fn deck_for(
+    conn: &rusqlite::Connection,
+    entity: AuditEntity,
+    id: Key,
+) -> Result<Option<Key>, DbError> {
+    let stmt = match entity {
+        AuditEntity::Deck => return Ok(Some(id)),
+        AuditEntity::Note | AuditEntity::Ref => "SELECT deck_id FROM notes WHERE id = :id",
+        AuditEntity::Point => "SELECT deck_id FROM points WHERE id = :id",
+        AuditEntity::Bookmark => "SELECT deck_id FROM bookmarks WHERE id = :id",
+        AuditEntity::Card => {
+            "SELECT n.deck_id FROM cards c JOIN notes n ON n.id = c.note_id WHERE c.id = :id"
+        }
+        _ => return Ok(None),
+    };
+
+    sqlite::one_optional::<Key, _>(conn, stmt, named_params! {":id": id})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: i64) -> LiveEvent {
+        LiveEvent {
+            user_id: Key(1),
+            entity: "note",
+            action: "edit",
+            id: Some(Key(id)),
+            deck_id: None,
+        }
+    }
+
+    #[test]
+    fn test_rollback_to_mark() {
+        discard();
+        PENDING.with_borrow_mut(|pending| pending.push(event(1)));
+        let mark = mark();
+        PENDING.with_borrow_mut(|pending| pending.push(event(2)));
+        rollback_to(mark);
+
+        let ids: Vec<Option<Key>> =
+            PENDING.with_borrow(|pending| pending.iter().map(|e| e.id).collect());
+        assert_eq!(ids, vec![Some(Key(1))]);
+        discard();
+    }
+}
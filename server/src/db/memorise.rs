@@ -16,13 +16,15 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
 use crate::interop::decks::SlimDeck;
 use crate::interop::memorise::{Card, CardUpcomingReview, FlashCard, ProtoCard};
 use chrono::Utc;
-use rusqlite::{Row, named_params};
+use rusqlite::{Connection, Row, named_params};
 
 #[allow(unused_imports)]
 use tracing::info;
@@ -157,21 +159,24 @@ pub(crate) fn all_flashcards_for_deck_additional_query(
     )
 }
 
+// index is whichever full text table of notes the search itself went through
+//
 pub(crate) fn all_flashcards_for_search_query(
     conn: &rusqlite::Connection,
     user_id: Key,
     query: String,
+    index: &str,
 ) -> Result<Vec<FlashCard>, DbError> {
     sqlite::many(
         &conn,
         &memorise_query_common_select()
-            .comma("notes_fts.rank as rank")
-            .from("notes_fts")
-            .left_join("notes n ON n.id = notes_fts.rowid")
+            .comma(&format!("{index}.rank as rank"))
+            .from(index)
+            .left_join(&format!("notes n ON n.id = {index}.rowid"))
             .left_join("decks d ON d.id = n.deck_id")
             .left_join("dialogue_messages dm ON dm.note_id = n.id")
             .inner_join("cards c on c.note_id = n.id")
-            .where_clause("notes_fts match :query")
+            .where_clause(&format!("{index} match :query"))
             .and("d.user_id = :user_id")
             .and("(dm.role IS null OR dm.role <> 'system')")
             .order_by("rank ASC")
@@ -194,7 +199,7 @@ pub(crate) fn create_card(
     let repetition: i32 = 1;
     let next_test_date = Utc::now().naive_utc();
 
-    let flashcard = sqlite::one(
+    let flashcard: FlashCard = sqlite::one(
         &tx,
         "INSERT INTO cards(user_id, note_id, prompt, next_test_date, easiness_factor, interval, repetition)
          VALUES (:user_id, :note_id, :prompt, :next_test_date, :easiness_factor, :interval, :repetition)
@@ -210,6 +215,15 @@ pub(crate) fn create_card(
         },
     )?;
 
+    audit_db::record(
+        &tx,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::Card,
+        Some(flashcard.id),
+        Payload::after(&flashcard),
+    )?;
+
     tx.commit()?;
 
     Ok(flashcard)
@@ -233,15 +247,29 @@ pub(crate) fn get_card_full_fat(
 
 pub(crate) fn card_rated(
     conn: &mut rusqlite::Connection,
+    user_id: Key,
     card: FlashCard,
     rating: i16,
 ) -> Result<(), DbError> {
-    info!("card_rated");
-
     let tx = conn.transaction()?;
+    card_rated_in_tx(&tx, user_id, card, rating)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+// note: this should be part of a transaction
+//
+pub(crate) fn card_rated_in_tx(
+    tx: &Connection,
+    user_id: Key,
+    card: FlashCard,
+    rating: i16,
+) -> Result<(), DbError> {
+    info!("card_rated");
 
     sqlite::zero(
-        &tx,
+        tx,
         "UPDATE cards
          SET next_test_date = :next_test_date, easiness_factor = :easiness_factor, interval = :interval, repetition = :repetition
          WHERE id = :card_id",
@@ -255,13 +283,20 @@ pub(crate) fn card_rated(
     )?;
 
     sqlite::zero(
-        &tx,
+        tx,
         "INSERT INTO card_ratings(card_id, rating)
          VALUES (:card_id, :rating)",
         named_params! {":card_id": card.id, ":rating": rating},
     )?;
 
-    tx.commit()?;
+    audit_db::record(
+        tx,
+        user_id,
+        AuditAction::Rate,
+        AuditEntity::Card,
+        Some(card.id),
+        Payload::after(&serde_json::json!({ "rating": rating })),
+    )?;
 
     Ok(())
 }
@@ -272,14 +307,27 @@ pub(crate) fn edit_flashcard(
     flashcard: FlashCard,
     flashcard_id: Key,
 ) -> Result<FlashCard, DbError> {
-    sqlite::one(
+    let before = get_card_full_fat(conn, user_id, flashcard_id)?;
+
+    let after: FlashCard = sqlite::one(
         &conn,
         "UPDATE cards
          SET prompt = :prompt
          WHERE id = :card_id and user_id = :user_id
          RETURNING id, note_id, prompt, next_test_date, easiness_factor, interval, repetition",
         named_params! {":user_id": user_id, ":card_id": flashcard_id, ":prompt": flashcard.prompt},
-    )
+    )?;
+
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Edit,
+        AuditEntity::Card,
+        Some(flashcard_id),
+        Payload::before(&before).with_after(&after),
+    )?;
+
+    Ok(after)
 }
 
 pub(crate) fn delete_flashcard(
@@ -288,20 +336,50 @@ pub(crate) fn delete_flashcard(
     flashcard_id: Key,
 ) -> Result<(), DbError> {
     let tx = conn.transaction()?;
+    delete_flashcard_in_tx(&tx, user_id, flashcard_id)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+// note: this should be part of a transaction
+//
+pub(crate) fn delete_flashcard_in_tx(
+    tx: &Connection,
+    user_id: Key,
+    flashcard_id: Key,
+) -> Result<(), DbError> {
+    let before: Option<FlashCard> = sqlite::one_optional(
+        tx,
+        "SELECT id, note_id, prompt, next_test_date, easiness_factor, interval, repetition
+         FROM cards
+         WHERE user_id = :user_id and id = :card_id",
+        named_params! {":user_id": user_id, ":card_id": flashcard_id},
+    )?;
+    let Some(before) = before else {
+        return Ok(());
+    };
 
     sqlite::zero(
-        &tx,
+        tx,
         "DELETE FROM card_ratings WHERE card_id = :card_id",
         named_params! {":card_id": flashcard_id},
     )?;
 
     sqlite::zero(
-        &tx,
+        tx,
         "DELETE FROM cards WHERE id = :card_id AND user_id = :user_id",
         named_params! {":card_id": flashcard_id, ":user_id": user_id},
     )?;
 
-    tx.commit()?;
+    audit_db::record(
+        tx,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Card,
+        Some(flashcard_id),
+        Payload::before(&before),
+    )?;
 
     Ok(())
 }
@@ -15,14 +15,18 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod aliases;
 pub mod articles;
+pub mod audit;
 pub mod bookmarks;
 pub mod concepts;
 pub mod decks;
 pub mod dialogues;
 pub mod events;
+pub mod fsck;
 pub mod graph;
 pub mod ideas;
+pub mod live;
 pub mod memorise;
 pub mod notes;
 pub mod people;
@@ -30,9 +34,16 @@ pub mod points;
 pub mod predictions;
 pub mod qry;
 pub mod quotes;
+pub mod ranking;
 pub mod references;
+pub mod related;
+pub mod saved_searches;
 pub mod search;
+pub mod share_links;
+pub mod shares;
 pub mod stats;
+pub mod sync;
+pub mod tags;
 pub mod timelines;
 pub mod uploader;
 pub mod users;
@@ -40,6 +51,9 @@ pub mod users;
 pub mod sqlite;
 pub mod sqlite_migrations;
 
+#[cfg(test)]
+pub(crate) mod test_util;
+
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 pub(crate) type SqlitePool = Pool<SqliteConnectionManager>;
@@ -59,6 +73,12 @@ pub enum DbError {
     TooManyFound,
     #[error("String Conversion To Enum")]
     StringConversionToEnum,
+    #[error("Version Conflict")]
+    VersionConflict,
+    #[error("Not Found")]
+    NotFound,
+    #[error(transparent)]
+    CivilShared(#[from] civil_shared::Error),
 }
 
 // Blocking helper: only DbError crosses the thread boundary.
@@ -72,7 +92,12 @@ where
     Ok(
         tokio::task::spawn_blocking(move || {
             let mut conn = pool.get()?; // r2d2::Error -> DbError via `From`
-            f(&mut conn)                // Result<T, DbError>
+            live::discard();
+            let res = f(&mut conn); // Result<T, DbError>
+            if res.is_ok() {
+                live::flush(); // only tell clients about committed changes
+            }
+            res
         })
         .await? // JoinError -> Error via `From`  -- this is a DBError
         ?, // DbError  -> Error via `From`   -- this converts the DBError into crate::Result
@@ -15,14 +15,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::memorise as memorise_db;
 use crate::db::sqlite::{self, FromRow};
 use crate::db::{DbError, SqlitePool};
 use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
 use crate::interop::decks::{Arrival, Ref, SlimDeck};
 use crate::interop::font::Font;
 use crate::interop::memorise::FlashCard;
-use crate::interop::notes::{Note, NoteKind, PreviewNotes, ProtoNote};
+use crate::interop::notes::{Note, NoteKind, PreviewNotes, Privacy, ProtoNote};
 use rusqlite::{Connection, Row, named_params};
 
 #[allow(unused_imports)]
@@ -37,9 +39,20 @@ impl FromRow for Note {
             content: row.get("content")?,
             point_id: row.get("point_id")?,
             font: row.get("font")?,
+            private: row.get("private")?,
+            version: row.get("version")?,
 
             refs: vec![],
             flashcards: vec![],
+            snippet: None,
+        })
+    }
+}
+
+impl FromRow for Privacy {
+    fn from_row(row: &Row) -> rusqlite::Result<Privacy> {
+        Ok(Privacy {
+            private: row.get("private")?,
         })
     }
 }
@@ -89,9 +102,12 @@ impl FromRow for NoteAndRef {
                 content: row.get("note_content")?,
                 point_id: row.get("note_point_id")?,
                 font: row.get("note_font")?,
+                private: row.get("note_private")?,
+                version: row.get("note_version")?,
 
                 refs: vec![],
                 flashcards: vec![],
+                snippet: None,
             },
             reference_maybe,
         })
@@ -127,9 +143,12 @@ impl FromRow for NoteAndRefAndDeck {
                 content: row.get("note_content")?,
                 point_id: row.get("note_point_id")?,
                 font: row.get("note_font")?,
+                private: row.get("note_private")?,
+                version: row.get("note_version")?,
 
                 refs: vec![],
                 flashcards: vec![],
+                snippet: None,
             },
             reference_maybe,
             deck: SlimDeck {
@@ -167,7 +186,9 @@ pub(crate) fn notes_for_deck(
                          d.graph_terminator as deck_graph_terminator,
                          d.insignia as deck_insignia,
                          d.font as deck_font,
-                         d.impact as deck_impact
+                         d.impact as deck_impact,
+                         n.private as note_private,
+                         n.version as note_version
                 FROM     notes n
                          FULL JOIN refs r on r.note_id = n.id
                          FULL JOIN decks d on r.deck_id = d.id
@@ -261,7 +282,9 @@ pub(crate) fn arrivals_for_deck(
                          owner_deck.graph_terminator as owner_deck_graph_terminator,
                          owner_deck.insignia as owner_deck_insignia,
                          owner_deck.font as owner_deck_font,
-                         owner_deck.impact as owner_deck_impact
+                         owner_deck.impact as owner_deck_impact,
+                         n.private as note_private,
+                         n.version as note_version
                 FROM     refs r
                          FULL JOIN notes n on r.note_id = n.id
                          FULL JOIN decks owner_deck on n.deck_id = owner_deck.id
@@ -337,17 +360,30 @@ pub(crate) fn delete_note_properly(
     note_id: Key,
 ) -> Result<Vec<Note>, DbError> {
     let tx = conn.transaction()?;
+    let notes = delete_note_properly_in_tx(&tx, user_id, note_id)?;
+    tx.commit()?;
 
+    Ok(notes)
+}
+
+// note: this should be part of a transaction
+//
+pub(crate) fn delete_note_properly_in_tx(
+    tx: &Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Vec<Note>, DbError> {
     let stmt = "SELECT deck_id
                 FROM notes
                 WHERE id = :note_id";
-    let deck_id: Key = sqlite::one(&tx, stmt, named_params! {":note_id": note_id})?;
+    let deck_id: Key = sqlite::one(tx, stmt, named_params! {":note_id": note_id})?;
+    let before = audit_db::note_snapshot(tx, user_id, note_id)?;
 
     // point the next note to the previous note
     let stmt = "SELECT id
                 FROM notes
                 WHERE prev_note_id = :note_id";
-    let next_ids: Vec<Key> = sqlite::many(&tx, stmt, named_params! {":note_id": note_id})?;
+    let next_ids: Vec<Key> = sqlite::many(tx, stmt, named_params! {":note_id": note_id})?;
     if next_ids.len() == 1 {
         let next_id = next_ids[0];
 
@@ -356,17 +392,17 @@ pub(crate) fn delete_note_properly(
                     FROM notes
                     WHERE id = :note_id AND user_id = :user_id";
         let prev_note_id: Option<Key> = sqlite::one(
-            &tx,
+            tx,
             stmt,
             named_params! {":user_id": user_id, ":note_id": note_id},
         )?;
 
         if let Some(prev_note_id) = prev_note_id {
             // if there is a note that points to this note, change it to point to prev_note_id
-            update_prev_note_id(&tx, next_id, prev_note_id)?;
+            update_prev_note_id(tx, next_id, prev_note_id)?;
         } else {
             // if there is a note that points to this note, change it to point to null
-            clear_prev_note_id(&tx, next_id)?;
+            clear_prev_note_id(tx, next_id)?;
         }
     }
 
@@ -374,17 +410,24 @@ pub(crate) fn delete_note_properly(
     let stmt = "DELETE
                 FROM refs
                 WHERE note_id = :note_id";
-    sqlite::zero(&tx, stmt, named_params! {":note_id": note_id})?;
+    sqlite::zero(tx, stmt, named_params! {":note_id": note_id})?;
 
     let stmt = "DELETE
                 FROM notes
                 WHERE id = :note_id AND user_id = :user_id";
-    sqlite::zero(&tx, stmt, named_params! {":note_id": note_id})?;
+    sqlite::zero(tx, stmt, named_params! {":note_id": note_id})?;
 
-    tx.commit()?;
+    audit_db::record(
+        tx,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Note,
+        Some(note_id),
+        Payload::before(&before),
+    )?;
 
     // return all the notes that the parent deck has
-    notes_for_deck(conn, deck_id)
+    notes_for_deck(tx, deck_id)
 }
 
 pub(crate) fn create_common(
@@ -398,9 +441,15 @@ pub(crate) fn create_common(
     prev_note_id: Option<Key>,
     next_note_id: Option<Key>,
 ) -> Result<Note, DbError> {
-    let stmt = "INSERT INTO notes(user_id, deck_id, font, kind, point_id, content, prev_note_id)
-                VALUES (:user_id, :deck_id, :font, :kind, :point_id, :content, :prev_note_id)
-                RETURNING id, content, kind, point_id, prev_note_id, font";
+    // new notes take the deck's default for the private flag. Except in dialogues,
+    // private notes are left out of the chat history and a message needs to be
+    // seen by the model that it's being sent to
+    let stmt = "INSERT INTO notes(user_id, deck_id, font, kind, point_id, content, prev_note_id,
+                              private)
+                VALUES (:user_id, :deck_id, :font, :kind, :point_id, :content, :prev_note_id,
+                        coalesce((SELECT private_notes_default AND kind <> 'dialogue'
+                                  FROM decks WHERE id = :deck_id), FALSE))
+                RETURNING id, content, kind, point_id, prev_note_id, font, private, version";
     let note: Note = sqlite::one(
         conn,
         stmt,
@@ -447,16 +496,28 @@ pub(crate) fn create_notes(
     user_id: Key,
     note: ProtoNote,
 ) -> Result<Vec<Note>, DbError> {
-    // let mut notes: Vec<Note> = Vec::new();
     let tx = conn.transaction()?;
+    let notes = create_notes_in_tx(&tx, user_id, note)?;
+    tx.commit()?;
+
+    Ok(notes)
+}
 
+// note: this should be part of a transaction
+//
+pub(crate) fn create_notes_in_tx(
+    tx: &Connection,
+    user_id: Key,
+    note: ProtoNote,
+) -> Result<Vec<Note>, DbError> {
+    // let mut notes: Vec<Note> = Vec::new();
     let mut new_prev = note.prev_note_id;
     let next_note_id = note.next_note_id;
 
     if let Some(next_note_id) = next_note_id {
         // when the ProtoNote has a Some(next_note_id) then it can have a None for prev_note_id,
         // it's upto this code to get the correct prev_note_id
-        new_prev = get_prev_note_id(&tx, next_note_id)?;
+        new_prev = get_prev_note_id(tx, next_note_id)?;
     }
 
     let mut it = note.content.iter().peekable();
@@ -469,7 +530,7 @@ pub(crate) fn create_notes(
         }
 
         let new_note = create_common(
-            &tx,
+            tx,
             user_id,
             note.deck_id,
             note.font,
@@ -483,14 +544,629 @@ pub(crate) fn create_notes(
                 None
             },
         )?;
+        audit_db::record(
+            tx,
+            user_id,
+            AuditAction::Create,
+            AuditEntity::Note,
+            Some(new_note.id),
+            Payload::after(&audit_db::note_snapshot(tx, user_id, new_note.id)?),
+        )?;
         new_prev = Some(new_note.id);
         // notes.push(new_note);
     }
 
+    let all_notes = notes_for_deck(tx, note.deck_id)?;
+    Ok(all_notes)
+}
+
+// the first note of a passage, a passage is made up of notes that share the same kind and point_id
+//
+struct PassageHead {
+    id: Key,
+    kind: NoteKind,
+    point_id: Option<Key>,
+}
+
+impl FromRow for PassageHead {
+    fn from_row(row: &Row) -> rusqlite::Result<PassageHead> {
+        Ok(PassageHead {
+            id: row.get("id")?,
+            kind: row.get("kind")?,
+            point_id: row.get("point_id")?,
+        })
+    }
+}
+
+// returns the last note in the deck's passage for the given kind and point_id
+//
+fn passage_tail(
+    conn: &Connection,
+    deck_id: Key,
+    kind: NoteKind,
+    point_id: Option<Key>,
+) -> Result<Option<Key>, DbError> {
+    let stmt = "SELECT n.id
+                FROM notes n
+                WHERE n.deck_id = :deck_id AND n.kind = :kind AND n.point_id IS :point_id
+                      AND NOT EXISTS (SELECT 1 FROM notes n2 WHERE n2.prev_note_id = n.id)";
+    let tails: Vec<Key> = sqlite::many(
+        conn,
+        stmt,
+        named_params! {":deck_id": deck_id, ":kind": kind, ":point_id": point_id},
+    )?;
+
+    if tails.len() > 1 {
+        warn!(
+            "deck {} has {} passage tails for note kind {:?}",
+            deck_id,
+            tails.len(),
+            kind
+        );
+    }
+
+    Ok(tails.first().copied())
+}
+
+// note: this should be part of a transaction
+//
+// moves every note (apart from the NoteDeckMeta) from one deck and appends them onto
+// the end of the matching passages in another deck. The refs that are attached to the
+// source deck's NoteDeckMeta are carried over to the destination deck's NoteDeckMeta
+//
+pub(crate) fn move_notes_to_deck(
+    tx: &Connection,
+    user_id: Key,
+    from_deck_id: Key,
+    to_deck_id: Key,
+) -> Result<(), DbError> {
+    let stmt = "SELECT id, kind, point_id
+                FROM notes
+                WHERE deck_id = :deck_id AND user_id = :user_id AND prev_note_id IS NULL AND kind <> :deck_meta";
+    let heads: Vec<PassageHead> = sqlite::many(
+        tx,
+        stmt,
+        named_params! {":deck_id": from_deck_id, ":user_id": user_id, ":deck_meta": NoteKind::NoteDeckMeta},
+    )?;
+
+    for head in heads {
+        if let Some(tail_id) = passage_tail(tx, to_deck_id, head.kind, head.point_id)? {
+            update_prev_note_id(tx, head.id, tail_id)?;
+        }
+    }
+
+    let stmt = "UPDATE notes
+                SET deck_id = :to_deck_id
+                WHERE deck_id = :from_deck_id AND user_id = :user_id AND kind <> :deck_meta";
+    sqlite::zero(
+        tx,
+        stmt,
+        named_params! {
+            ":from_deck_id": from_deck_id,
+            ":to_deck_id": to_deck_id,
+            ":user_id": user_id,
+            ":deck_meta": NoteKind::NoteDeckMeta
+        },
+    )?;
+
+    let stmt = "SELECT id
+                FROM notes
+                WHERE deck_id = :deck_id AND kind = :deck_meta";
+    let from_meta: Option<Key> = sqlite::one_optional(
+        tx,
+        stmt,
+        named_params! {":deck_id": from_deck_id, ":deck_meta": NoteKind::NoteDeckMeta},
+    )?;
+    let to_meta: Option<Key> = sqlite::one_optional(
+        tx,
+        stmt,
+        named_params! {":deck_id": to_deck_id, ":deck_meta": NoteKind::NoteDeckMeta},
+    )?;
+
+    if let (Some(from_meta), Some(to_meta)) = (from_meta, to_meta) {
+        // any refs that the destination deck already has are left alone,
+        // the duplicates are removed when the source deck is deleted
+        let stmt = "UPDATE OR IGNORE refs
+                    SET note_id = :to_meta
+                    WHERE note_id = :from_meta";
+        sqlite::zero(
+            tx,
+            stmt,
+            named_params! {":from_meta": from_meta, ":to_meta": to_meta},
+        )?;
+    }
+
+    Ok(())
+}
+
+// a note along with where it sits in its passage
+//
+struct NoteLink {
+    id: Key,
+    deck_id: Key,
+    kind: NoteKind,
+    point_id: Option<Key>,
+    prev_note_id: Option<Key>,
+    content: String,
+    font: Font,
+    private: bool,
+}
+
+impl FromRow for NoteLink {
+    fn from_row(row: &Row) -> rusqlite::Result<NoteLink> {
+        Ok(NoteLink {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            kind: row.get("kind")?,
+            point_id: row.get("point_id")?,
+            prev_note_id: row.get("prev_note_id")?,
+            content: row.get("content")?,
+            font: row.get("font")?,
+            private: row.get("private")?,
+        })
+    }
+}
+
+// NoteDeckMeta notes aren't part of a passage so they're never returned
+//
+fn get_note_link(
+    conn: &Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<NoteLink>, DbError> {
+    let stmt = "SELECT id, deck_id, kind, point_id, prev_note_id, content, font, private
+                FROM notes
+                WHERE id = :note_id AND user_id = :user_id AND kind <> :deck_meta";
+    sqlite::one_optional(
+        conn,
+        stmt,
+        named_params! {":note_id": note_id, ":user_id": user_id, ":deck_meta": NoteKind::NoteDeckMeta},
+    )
+}
+
+fn get_next_note_id(conn: &Connection, note_id: Key) -> Result<Option<Key>, DbError> {
+    let stmt = "SELECT id
+                FROM notes
+                WHERE prev_note_id = :note_id";
+    let next_ids: Vec<Key> = sqlite::many(conn, stmt, named_params! {":note_id": note_id})?;
+
+    if next_ids.len() > 1 {
+        warn!(
+            "note {} is the prev_note_id of {} notes",
+            note_id,
+            next_ids.len()
+        );
+    }
+
+    Ok(next_ids.first().copied())
+}
+
+// removes the note from its passage, joining up the notes on either side of it
+//
+fn unlink_note(conn: &Connection, note: &NoteLink) -> Result<(), DbError> {
+    if let Some(next_id) = get_next_note_id(conn, note.id)? {
+        match note.prev_note_id {
+            Some(prev_note_id) => update_prev_note_id(conn, next_id, prev_note_id)?,
+            None => clear_prev_note_id(conn, next_id)?,
+        }
+    }
+    clear_prev_note_id(conn, note.id)
+}
+
+// inserts an unlinked note into its passage after prev_note_id,
+// or at the start of the passage if prev_note_id is None
+//
+fn link_note_after(
+    conn: &Connection,
+    note: &NoteLink,
+    prev_note_id: Option<Key>,
+) -> Result<(), DbError> {
+    let next_id = match prev_note_id {
+        Some(prev_note_id) => get_next_note_id(conn, prev_note_id)?,
+        None => {
+            let stmt = "SELECT id
+                        FROM notes
+                        WHERE deck_id = :deck_id AND kind = :kind AND point_id IS :point_id
+                              AND prev_note_id IS NULL AND id <> :note_id";
+            sqlite::one_optional(
+                conn,
+                stmt,
+                named_params! {
+                    ":deck_id": note.deck_id,
+                    ":kind": note.kind,
+                    ":point_id": note.point_id,
+                    ":note_id": note.id
+                },
+            )?
+        }
+    };
+
+    if let Some(next_id) = next_id {
+        update_prev_note_id(conn, next_id, note.id)?;
+    }
+    if let Some(prev_note_id) = prev_note_id {
+        update_prev_note_id(conn, note.id, prev_note_id)?;
+    }
+
+    Ok(())
+}
+
+// swaps the note with the one before it, returns the deck's notes
+//
+pub(crate) fn move_note_up(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<Vec<Note>>, DbError> {
+    let tx = conn.transaction()?;
+
+    let Some(note) = get_note_link(&tx, user_id, note_id)? else {
+        return Ok(None);
+    };
+    let before = audit_db::note_snapshot(&tx, user_id, note.id)?;
+
+    if let Some(prev_note_id) = note.prev_note_id {
+        let prev_prev_note_id = get_prev_note_id(&tx, prev_note_id)?;
+        unlink_note(&tx, &note)?;
+        link_note_after(&tx, &note, prev_prev_note_id)?;
+    }
+
+    audit_db::record_note_change(&tx, user_id, AuditAction::Move, note.id, &before)?;
+
     tx.commit()?;
 
-    let all_notes = notes_for_deck(conn, note.deck_id)?;
-    Ok(all_notes)
+    Ok(Some(notes_for_deck(conn, note.deck_id)?))
+}
+
+// swaps the note with the one after it, returns the deck's notes
+//
+pub(crate) fn move_note_down(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<Vec<Note>>, DbError> {
+    let tx = conn.transaction()?;
+
+    let Some(note) = get_note_link(&tx, user_id, note_id)? else {
+        return Ok(None);
+    };
+    let before = audit_db::note_snapshot(&tx, user_id, note.id)?;
+
+    if let Some(next_id) = get_next_note_id(&tx, note.id)? {
+        unlink_note(&tx, &note)?;
+        link_note_after(&tx, &note, Some(next_id))?;
+    }
+
+    audit_db::record_note_change(&tx, user_id, AuditAction::Move, note.id, &before)?;
+
+    tx.commit()?;
+
+    Ok(Some(notes_for_deck(conn, note.deck_id)?))
+}
+
+// appends the note onto the end of the matching passage in another deck. The note
+// keeps its refs, flashcards and tags, apart from a ref to the deck it's moving into.
+//
+// returns the notes of the deck that the note was moved out of
+//
+pub(crate) fn move_note_to_deck(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+    deck_id: Key,
+) -> Result<Option<Vec<Note>>, DbError> {
+    let tx = conn.transaction()?;
+
+    let Some(note) = get_note_link(&tx, user_id, note_id)? else {
+        return Ok(None);
+    };
+
+    let stmt = "SELECT id
+                FROM decks
+                WHERE id = :deck_id AND user_id = :user_id";
+    let deck: Option<Key> = sqlite::one_optional(
+        &tx,
+        stmt,
+        named_params! {":deck_id": deck_id, ":user_id": user_id},
+    )?;
+    if deck.is_none() {
+        return Ok(None);
+    }
+    let before = audit_db::note_snapshot(&tx, user_id, note.id)?;
+
+    if note.deck_id != deck_id {
+        unlink_note(&tx, &note)?;
+
+        // a note attached to one of the deck's points becomes a regular note in the new deck
+        let tail_id = passage_tail(&tx, deck_id, note.kind, None)?;
+
+        let stmt = "UPDATE notes
+                    SET deck_id = :deck_id, point_id = NULL, prev_note_id = :prev_note_id
+                    WHERE id = :note_id";
+        sqlite::zero(
+            &tx,
+            stmt,
+            named_params! {":deck_id": deck_id, ":prev_note_id": tail_id, ":note_id": note.id},
+        )?;
+
+        let stmt = "DELETE FROM refs
+                    WHERE note_id = :note_id AND deck_id = :deck_id";
+        sqlite::zero(
+            &tx,
+            stmt,
+            named_params! {":note_id": note.id, ":deck_id": deck_id},
+        )?;
+
+        audit_db::record_note_change(&tx, user_id, AuditAction::Move, note.id, &before)?;
+    }
+
+    tx.commit()?;
+
+    Ok(Some(notes_for_deck(conn, note.deck_id)?))
+}
+
+// splits the note's content into its top level sections (paragraphs, headers, lists etc),
+// the first section stays in the original note and the others become new notes that
+// follow it. The original note keeps its refs, flashcards and tags, the new notes
+// have the same private flag as the original.
+//
+pub(crate) fn split_note(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<Vec<Note>>, DbError> {
+    let tx = conn.transaction()?;
+
+    let Some(note) = get_note_link(&tx, user_id, note_id)? else {
+        return Ok(None);
+    };
+
+    let sections = civil_shared::split_markup(&note.content)?;
+
+    if sections.len() > 1 {
+        let before = audit_db::note_snapshot(&tx, user_id, note.id)?;
+
+        let stmt = "UPDATE notes
+                    SET content = :content, version = version + 1
+                    WHERE id = :note_id";
+        sqlite::zero(
+            &tx,
+            stmt,
+            named_params! {":content": sections[0], ":note_id": note.id},
+        )?;
+
+        let next_note_id = get_next_note_id(&tx, note.id)?;
+        let mut prev_note_id = note.id;
+
+        let mut it = sections[1..].iter().peekable();
+        while let Some(content) = it.next() {
+            let new_note = create_common(
+                &tx,
+                user_id,
+                note.deck_id,
+                note.font,
+                note.kind,
+                note.point_id,
+                content,
+                Some(prev_note_id),
+                if it.peek().is_none() {
+                    next_note_id
+                } else {
+                    None
+                },
+            )?;
+            set_private(&tx, new_note.id, note.private)?;
+            prev_note_id = new_note.id;
+        }
+
+        audit_db::record_note_change(&tx, user_id, AuditAction::Split, note.id, &before)?;
+    }
+
+    tx.commit()?;
+
+    Ok(Some(notes_for_deck(conn, note.deck_id)?))
+}
+
+// appends the content of the following note onto this one and deletes the following note,
+// its refs, flashcards, tags and dialogue messages are carried over
+//
+pub(crate) fn merge_note_with_next(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<Vec<Note>>, DbError> {
+    let tx = conn.transaction()?;
+
+    let Some(note) = get_note_link(&tx, user_id, note_id)? else {
+        return Ok(None);
+    };
+
+    let next = match get_next_note_id(&tx, note.id)? {
+        Some(next_id) => get_note_link(&tx, user_id, next_id)?,
+        None => None,
+    };
+
+    if let Some(next) = next {
+        let before = [
+            audit_db::note_snapshot(&tx, user_id, note.id)?,
+            audit_db::note_snapshot(&tx, user_id, next.id)?,
+        ];
+
+        let content = format!(
+            "{}\n\n{}",
+            note.content.trim_end(),
+            next.content.trim_start()
+        );
+        let stmt = "UPDATE notes
+                    SET content = :content, version = version + 1
+                    WHERE id = :note_id";
+        sqlite::zero(
+            &tx,
+            stmt,
+            named_params! {":content": content, ":note_id": note.id},
+        )?;
+
+        // the merged note is private if either of the notes were
+        set_private(&tx, note.id, note.private || next.private)?;
+
+        // if both notes refer to the same deck then the next note's ref is left behind
+        for stmt in [
+            "UPDATE OR IGNORE refs SET note_id = :note_id WHERE note_id = :next_id",
+            "UPDATE OR IGNORE note_tags SET note_id = :note_id WHERE note_id = :next_id",
+            "UPDATE cards SET note_id = :note_id WHERE note_id = :next_id",
+            "UPDATE dialogue_messages SET note_id = :note_id WHERE note_id = :next_id",
+        ] {
+            sqlite::zero(
+                &tx,
+                stmt,
+                named_params! {":note_id": note.id, ":next_id": next.id},
+            )?;
+        }
+
+        unlink_note(&tx, &next)?;
+
+        for stmt in [
+            "DELETE FROM refs WHERE note_id = :next_id",
+            "DELETE FROM note_tags WHERE note_id = :next_id",
+            "DELETE FROM notes WHERE id = :next_id",
+        ] {
+            sqlite::zero(&tx, stmt, named_params! {":next_id": next.id})?;
+        }
+
+        audit_db::record_note_change(&tx, user_id, AuditAction::Merge, note.id, &before)?;
+    }
+
+    tx.commit()?;
+
+    Ok(Some(notes_for_deck(conn, note.deck_id)?))
+}
+
+fn set_private(conn: &Connection, note_id: Key, private: bool) -> Result<(), DbError> {
+    let stmt = "UPDATE notes
+                SET private = :private
+                WHERE id = :note_id";
+    sqlite::zero(
+        conn,
+        stmt,
+        named_params! {":note_id": note_id, ":private": private},
+    )
+}
+
+// returns the notes of the note's deck
+//
+pub(crate) fn set_note_private(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+    private: bool,
+) -> Result<Option<Vec<Note>>, DbError> {
+    let Some(note) = get_note_link(conn, user_id, note_id)? else {
+        return Ok(None);
+    };
+
+    let before = audit_db::note_snapshot(conn, user_id, note.id)?;
+    set_private(conn, note.id, private)?;
+    audit_db::record_note_change(conn, user_id, AuditAction::Edit, note.id, &before)?;
+
+    Ok(Some(notes_for_deck(conn, note.deck_id)?))
+}
+
+pub(crate) fn get_private_notes_default(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Option<Privacy>, DbError> {
+    sqlite::one_optional(
+        conn,
+        "SELECT private_notes_default AS private
+         FROM decks
+         WHERE id = :deck_id AND user_id = :user_id",
+        named_params! {":deck_id": deck_id, ":user_id": user_id},
+    )
+}
+
+// only affects notes created from now on, existing notes keep their private flag
+//
+pub(crate) fn set_private_notes_default(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    private: bool,
+) -> Result<Option<Privacy>, DbError> {
+    let Some(before) = get_private_notes_default(conn, user_id, deck_id)? else {
+        return Ok(None);
+    };
+
+    sqlite::zero(
+        conn,
+        "UPDATE decks
+         SET private_notes_default = :private
+         WHERE id = :deck_id AND user_id = :user_id",
+        named_params! {":deck_id": deck_id, ":user_id": user_id, ":private": private},
+    )?;
+
+    let after = get_private_notes_default(conn, user_id, deck_id)?;
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Edit,
+        AuditEntity::Deck,
+        Some(deck_id),
+        Payload::before(&before).with_after(&after),
+    )?;
+
+    Ok(after)
+}
+
+pub(crate) fn without_private_notes(notes: Vec<Note>) -> Vec<Note> {
+    notes.into_iter().filter(|note| !note.private).collect()
+}
+
+// arrivals whose only notes were private are removed entirely
+//
+pub(crate) fn without_private_arrivals(arrivals: Vec<Arrival>) -> Vec<Arrival> {
+    arrivals
+        .into_iter()
+        .map(|arrival| Arrival {
+            notes: without_private_notes(arrival.notes),
+            deck: arrival.deck,
+        })
+        .filter(|arrival| !arrival.notes.is_empty())
+        .collect()
+}
+
+// the contents of the deck's main passage in order, without any private notes.
+// This is the only note content that's sent to an AI for summarizing
+//
+pub(crate) fn non_private_passage_contents(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Vec<String>, DbError> {
+    let stmt = "SELECT id, content, kind, point_id, prev_note_id, font, private, version
+                FROM notes
+                WHERE deck_id = :deck_id AND user_id = :user_id AND kind = :kind AND point_id IS NULL";
+    let notes: Vec<Note> = sqlite::many(
+        conn,
+        stmt,
+        named_params! {":deck_id": deck_id, ":user_id": user_id, ":kind": NoteKind::Note},
+    )?;
+
+    // follow the prev_note_id links from the start of the passage,
+    // bounded by the number of notes in case the links contain a cycle
+    let mut contents = vec![];
+    let mut current = notes.iter().find(|n| n.prev_note_id.is_none());
+    for _ in 0..notes.len() {
+        let Some(note) = current else {
+            break;
+        };
+        if !note.private {
+            contents.push(note.content.clone());
+        }
+        current = notes.iter().find(|n| n.prev_note_id == Some(note.id));
+    }
+
+    Ok(contents)
 }
 
 fn update_prev_note_id(conn: &Connection, note_id: Key, prev_note_id: Key) -> Result<(), DbError> {
@@ -521,7 +1197,9 @@ pub(crate) fn preview(
                        n.kind as kind,
                        n.point_id as point_id,
                        n.prev_note_id as prev_note_id,
-                       n.font as font
+                       n.font as font,
+                       n.private as private,
+                       n.version as version
                 FROM notes n
                 WHERE n.point_id is null AND n.deck_id = :deck_id AND n.user_id = :user_id";
     let notes = sqlite::many(
@@ -540,7 +1218,7 @@ pub(crate) fn add_auto_summary(
     prev_id: Option<Key>,
     summary: String,
 ) -> Result<Note, DbError> {
-    create_common(
+    let note = create_common(
         &conn,
         user_id,
         deck_id,
@@ -550,27 +1228,67 @@ pub(crate) fn add_auto_summary(
         &summary,
         prev_id,
         None,
-    )
+    )?;
+
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Summarize,
+        AuditEntity::Note,
+        Some(note.id),
+        Payload::after(&audit_db::note_snapshot(conn, user_id, note.id)?),
+    )?;
+
+    Ok(note)
 }
 
 // this is public because it's used by the civil note parser
 // isg todo: this won't get the note's refs, so maybe use two queries: one to edit another to return the full note
 //
+// when given a version the edit only happens if the note is still at that version,
+// otherwise another edit has got there first and this returns a VersionConflict
+//
 pub fn edit_note(
     conn: &rusqlite::Connection,
     user_id: Key,
     note: Note,
     note_id: Key,
+    version: Option<i32>,
 ) -> Result<Note, DbError> {
+    let Some(before) = audit_db::note_snapshot(conn, user_id, note_id)? else {
+        return Err(DbError::NotFound);
+    };
+
     let stmt = "UPDATE notes
-                SET content = :content, font= :font
-                WHERE id = :note_id AND user_id = :user_id";
+                SET content = :content, font= :font, version = version + 1
+                WHERE id = :note_id AND user_id = :user_id AND (:version IS NULL OR version = :version)";
     sqlite::zero(
         &conn,
         stmt,
-        named_params! {":user_id": user_id, ":note_id": note_id, ":content": note.content, ":font": note.font},
+        named_params! {
+            ":user_id": user_id,
+            ":note_id": note_id,
+            ":content": note.content,
+            ":font": note.font,
+            ":version": version
+        },
     )?;
 
+    if conn.changes() == 0 {
+        return Err(DbError::VersionConflict);
+    }
+    audit_db::record_note_change(conn, user_id, AuditAction::Edit, note_id, &before)?;
+
+    get_note(conn, user_id, note_id)?.ok_or(DbError::NotFound)
+}
+
+// a single note along with its refs and flashcards
+//
+pub(crate) fn get_note(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Option<Note>, DbError> {
     let stmt = "SELECT   n.id as note_id,
                          n.prev_note_id as prev_note_id,
                          n.kind as note_kind,
@@ -586,22 +1304,27 @@ pub fn edit_note(
                          d.graph_terminator as deck_graph_terminator,
                          d.insignia as deck_insignia,
                          d.font as deck_font,
-                         d.impact as deck_impact
+                         d.impact as deck_impact,
+                         n.private as note_private,
+                         n.version as note_version
                 FROM     notes n
                          FULL JOIN refs r on r.note_id = n.id
                          FULL JOIN decks d on r.deck_id = d.id
-                WHERE    n.id = :note_id";
-    let notes_and_refs: Vec<NoteAndRef> =
-        sqlite::many(&conn, stmt, named_params! {":note_id": note_id})?;
+                WHERE    n.id = :note_id AND n.user_id = :user_id";
+    let notes_and_refs: Vec<NoteAndRef> = sqlite::many(
+        conn,
+        stmt,
+        named_params! {":note_id": note_id, ":user_id": user_id},
+    )?;
 
     let mut notes = notes_from_notes_and_refs(notes_and_refs)?;
     let flashcards = memorise_db::all_flashcards_for_note(conn, note_id)?;
     assign_flashcards_to_notes(&mut notes, &flashcards)?;
 
-    if notes.len() == 1 {
-        Ok(notes[0].clone())
-    } else {
-        Err(DbError::TooManyFound)
+    match notes.len() {
+        0 => Ok(None),
+        1 => Ok(notes.pop()),
+        _ => Err(DbError::TooManyFound),
     }
 }
 
@@ -612,7 +1335,9 @@ pub fn get_all_notes_in_db(sqlite_pool: &SqlitePool) -> crate::Result<Vec<Note>>
                        n.kind as kind,
                        n.point_id as point_id,
                        n.prev_note_id as prev_note_id,
-                       n.font as font
+                       n.font as font,
+                       n.private as private,
+                       n.version as version
                 FROM   notes n
                 ORDER BY n.id";
     sqlite::many(&conn, stmt, {}).map_err(Into::into)
@@ -664,3 +1389,402 @@ pub(crate) fn overwrite_note_fonts(
         named_params! {":user_id": user_id, ":deck_id": deck_id, ":font": new_font},
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::openai_interface;
+    use crate::db::test_util::{
+        OWNER, RECIPIENT, add_note, add_ref, create_deck, passage, test_db,
+    };
+    use crate::db::{dialogues, graph, share_links, shares};
+    use crate::interop::graph::GraphReach;
+    use crate::interop::share_links::ProtoShareLink;
+
+    fn contents(notes: &[Note]) -> Vec<&str> {
+        notes
+            .iter()
+            .filter(|n| n.kind == NoteKind::Note)
+            .map(|n| n.content.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_private_notes_excluded_from_summary() {
+        let mut db = test_db("private-summary");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        add_note(&mut db.conn, deck_id, "public one");
+        let secret = add_note(&mut db.conn, deck_id, "secret");
+        add_note(&mut db.conn, deck_id, "public two");
+
+        set_note_private(&db.conn, OWNER, secret, true)
+            .unwrap()
+            .unwrap();
+
+        let passage = non_private_passage_contents(&db.conn, OWNER, deck_id).unwrap();
+        assert_eq!(passage, vec!["public one", "public two"]);
+    }
+
+    #[test]
+    fn test_private_notes_excluded_from_chat_history() {
+        let mut db = test_db("private-chat");
+        let deck_id = create_deck(&db.conn, "dialogue", "Conversation");
+        db.conn
+            .execute(
+                "INSERT INTO dialogue_extras(deck_id, ai_kind) VALUES (?1, 'OpenAI::Gpt4')",
+                [deck_id],
+            )
+            .unwrap();
+        for content in ["hello", "my password is hunter2"] {
+            let note_id = add_note(&mut db.conn, deck_id, content);
+            db.conn
+                .execute(
+                    "INSERT INTO dialogue_messages(role, content, note_id) VALUES ('user', ?1, ?2)",
+                    rusqlite::params![content, note_id],
+                )
+                .unwrap();
+            if content.contains("password") {
+                set_note_private(&db.conn, OWNER, note_id, true).unwrap();
+            }
+        }
+
+        let (_, messages) = dialogues::get_chat_history(&mut db.conn, OWNER, deck_id).unwrap();
+        let messages: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(messages, vec!["hello"]);
+    }
+
+    #[test]
+    fn test_private_notes_excluded_from_shares() {
+        let mut db = test_db("private-shares");
+        let shared = create_deck(&db.conn, "idea", "Shared");
+        let via_public = create_deck(&db.conn, "idea", "Via Public");
+        let via_private = create_deck(&db.conn, "idea", "Via Private");
+
+        let public_note = add_note(&mut db.conn, shared, "public");
+        let private_note = add_note(&mut db.conn, shared, "private");
+        add_ref(&db.conn, public_note, via_public);
+        add_ref(&db.conn, private_note, via_private);
+        set_note_private(&db.conn, OWNER, private_note, true).unwrap();
+
+        shares::create_share(&db.conn, OWNER, shared, Some(RECIPIENT), true).unwrap();
+
+        // decks that are only referenced from private notes aren't part of the subtree
+        let shared_ids = shares::shared_deck_ids(&db.conn, RECIPIENT).unwrap();
+        assert!(shared_ids.contains(&via_public));
+        assert!(!shared_ids.contains(&via_private));
+
+        let deck = shares::get_shared_deck(&db.conn, RECIPIENT, shared)
+            .unwrap()
+            .unwrap();
+        assert_eq!(contents(&deck.notes), vec!["public"]);
+
+        let deck = shares::get_shared_deck(&db.conn, RECIPIENT, via_public)
+            .unwrap()
+            .unwrap();
+        assert_eq!(deck.arrivals.len(), 1);
+
+        // the recipient's graph only has the edge from the public note
+        let reach = GraphReach::default();
+        let graph = graph::get(&mut db.conn, RECIPIENT, shared, &reach)
+            .unwrap()
+            .unwrap();
+        assert_eq!(graph.decks.len(), 1);
+        let graph = graph::get(&mut db.conn, OWNER, shared, &reach)
+            .unwrap()
+            .unwrap();
+        assert_eq!(graph.decks.len(), 2);
+
+        // once the only note referencing a deck is private, that deck is no longer shared
+        set_note_private(&db.conn, OWNER, public_note, true).unwrap();
+        assert!(
+            shares::get_shared_deck(&db.conn, RECIPIENT, via_public)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_private_notes_excluded_from_share_links() {
+        let mut db = test_db("private-links");
+        let shared = create_deck(&db.conn, "idea", "Shared");
+        let elsewhere = create_deck(&db.conn, "idea", "Elsewhere");
+
+        add_note(&mut db.conn, shared, "public");
+        let private_note = add_note(&mut db.conn, shared, "private");
+        set_note_private(&db.conn, OWNER, private_note, true).unwrap();
+
+        let arriving = add_note(&mut db.conn, elsewhere, "private arrival");
+        add_ref(&db.conn, arriving, shared);
+        set_note_private(&db.conn, OWNER, arriving, true).unwrap();
+
+        let link = ProtoShareLink {
+            expires_at: None,
+            include_arrivals: true,
+        };
+        share_links::create_share_link(&db.conn, OWNER, shared, link, "token".to_string()).unwrap();
+
+        let public_deck = share_links::view_share_link(&mut db.conn, "token")
+            .unwrap()
+            .unwrap();
+        assert_eq!(contents(&public_deck.notes), vec!["public"]);
+        assert!(public_deck.arrivals.is_empty());
+    }
+
+    #[test]
+    fn test_private_notes_default() {
+        let mut db = test_db("private-default");
+        let deck_id = create_deck(&db.conn, "idea", "Journal");
+
+        let privacy = set_private_notes_default(&db.conn, OWNER, deck_id, true)
+            .unwrap()
+            .unwrap();
+        assert!(privacy.private);
+        assert!(
+            set_private_notes_default(&db.conn, RECIPIENT, deck_id, false)
+                .unwrap()
+                .is_none()
+        );
+
+        add_note(&mut db.conn, deck_id, "diary entry");
+        let notes = notes_for_deck(&db.conn, deck_id).unwrap();
+        assert!(
+            notes
+                .iter()
+                .filter(|n| n.kind == NoteKind::Note)
+                .all(|n| n.private)
+        );
+    }
+
+    #[test]
+    fn test_private_notes_default_ignored_in_dialogues() {
+        let mut db = test_db("private-dialogue-default");
+        let deck_id = create_deck(&db.conn, "dialogue", "Conversation");
+        db.conn
+            .execute(
+                "INSERT INTO dialogue_extras(deck_id, ai_kind) VALUES (?1, 'OpenAI::Gpt4')",
+                [deck_id],
+            )
+            .unwrap();
+        set_private_notes_default(&db.conn, OWNER, deck_id, true)
+            .unwrap()
+            .unwrap();
+
+        let message = openai_interface::AppendChatMessage {
+            role: openai_interface::Role::User,
+            content: "what did the stoics think of fate?".to_string(),
+            prev_note_id: None,
+        };
+        dialogues::add_chat_message(&mut db.conn, OWNER, deck_id, message).unwrap();
+
+        let (_, messages) = dialogues::get_chat_history(&mut db.conn, OWNER, deck_id).unwrap();
+        let messages: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(messages, vec!["what did the stoics think of fate?"]);
+    }
+
+    #[test]
+    fn test_edit_missing_note_is_not_found() {
+        let mut db = test_db("edit-missing-note");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "owned by someone else");
+        let note = get_note(&db.conn, OWNER, note_id).unwrap().unwrap();
+
+        let r = edit_note(
+            &db.conn,
+            RECIPIENT,
+            note.clone(),
+            note_id,
+            Some(note.version),
+        );
+        assert!(matches!(r, Err(DbError::NotFound)));
+
+        let r = edit_note(&db.conn, OWNER, note, Key(9999), None);
+        assert!(matches!(r, Err(DbError::NotFound)));
+    }
+
+    #[test]
+    fn test_edit_stale_version_is_a_conflict() {
+        let mut db = test_db("edit-stale-version");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "first draft");
+        let stale = get_note(&db.conn, OWNER, note_id).unwrap().unwrap();
+
+        let mut edit = stale.clone();
+        edit.content = "second draft".to_string();
+        let edited = edit_note(&db.conn, OWNER, edit, note_id, Some(stale.version)).unwrap();
+        assert_eq!(edited.version, stale.version + 1);
+
+        let mut edit = stale.clone();
+        edit.content = "written against the first draft".to_string();
+        let r = edit_note(&db.conn, OWNER, edit, note_id, Some(stale.version));
+        assert!(matches!(r, Err(DbError::VersionConflict)));
+
+        let current = get_note(&db.conn, OWNER, note_id).unwrap().unwrap();
+        assert_eq!(current.content, "second draft");
+        assert_eq!(current.version, edited.version);
+    }
+
+    fn ref_ids(conn: &Connection, note_id: Key) -> Vec<Key> {
+        sqlite::many(
+            conn,
+            "SELECT deck_id FROM refs WHERE note_id = :note_id ORDER BY deck_id",
+            named_params! {":note_id": note_id},
+        )
+        .unwrap()
+    }
+
+    fn add_card(conn: &Connection, note_id: Key) {
+        conn.execute(
+            "INSERT INTO cards(user_id, note_id, prompt, next_test_date, easiness_factor)
+             VALUES (?1, ?2, 'prompt', CURRENT_TIMESTAMP, 2.5)",
+            rusqlite::params![OWNER, note_id],
+        )
+        .unwrap();
+    }
+
+    fn card_note_ids(conn: &Connection) -> Vec<Key> {
+        sqlite::many(conn, "SELECT note_id FROM cards ORDER BY id", []).unwrap()
+    }
+
+    #[test]
+    fn test_move_notes_up_and_down() {
+        let mut db = test_db("move-notes");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let other_id = create_deck(&db.conn, "person", "Epictetus");
+        let a = add_note(&mut db.conn, deck_id, "a");
+        add_note(&mut db.conn, deck_id, "b");
+        let c = add_note(&mut db.conn, deck_id, "c");
+        add_ref(&db.conn, c, other_id);
+        add_card(&db.conn, c);
+
+        let order = |conn: &Connection| passage(conn, deck_id, None);
+
+        // the head can't move up and the tail can't move down
+        move_note_up(&mut db.conn, OWNER, a).unwrap().unwrap();
+        assert_eq!(order(&db.conn), vec!["a", "b", "c"]);
+        move_note_down(&mut db.conn, OWNER, c).unwrap().unwrap();
+        assert_eq!(order(&db.conn), vec!["a", "b", "c"]);
+
+        // the tail moves up into the middle and then to the head
+        move_note_up(&mut db.conn, OWNER, c).unwrap().unwrap();
+        assert_eq!(order(&db.conn), vec!["a", "c", "b"]);
+        move_note_up(&mut db.conn, OWNER, c).unwrap().unwrap();
+        assert_eq!(order(&db.conn), vec!["c", "a", "b"]);
+
+        // the head moves down into the middle and then to the tail
+        move_note_down(&mut db.conn, OWNER, c).unwrap().unwrap();
+        assert_eq!(order(&db.conn), vec!["a", "c", "b"]);
+        move_note_down(&mut db.conn, OWNER, c).unwrap().unwrap();
+        assert_eq!(order(&db.conn), vec!["a", "b", "c"]);
+
+        assert_eq!(ref_ids(&db.conn, c), vec![other_id]);
+        assert_eq!(card_note_ids(&db.conn), vec![c]);
+
+        // only the owner can move their notes
+        assert!(move_note_up(&mut db.conn, RECIPIENT, c).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_split_note_with_successor() {
+        let mut db = test_db("split-note");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let other_id = create_deck(&db.conn, "person", "Epictetus");
+        add_note(&mut db.conn, deck_id, "before");
+        let note_id = add_note(&mut db.conn, deck_id, "one\n\ntwo\n\nthree");
+        add_note(&mut db.conn, deck_id, "after");
+        add_ref(&db.conn, note_id, other_id);
+        add_card(&db.conn, note_id);
+
+        let notes = split_note(&mut db.conn, OWNER, note_id).unwrap().unwrap();
+        assert_eq!(
+            passage(&db.conn, deck_id, None),
+            vec!["before", "one", "two", "three", "after"]
+        );
+
+        // the refs and cards stay with the original note
+        assert_eq!(ref_ids(&db.conn, note_id), vec![other_id]);
+        assert_eq!(card_note_ids(&db.conn), vec![note_id]);
+        for note in notes.iter().filter(|n| n.id != note_id) {
+            assert!(ref_ids(&db.conn, note.id).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_merge_notes_that_ref_the_same_deck() {
+        let mut db = test_db("merge-notes");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let shared_id = create_deck(&db.conn, "person", "Epictetus");
+        let other_id = create_deck(&db.conn, "person", "Seneca");
+        let first = add_note(&mut db.conn, deck_id, "first");
+        let second = add_note(&mut db.conn, deck_id, "second");
+        add_note(&mut db.conn, deck_id, "third");
+        add_ref(&db.conn, first, shared_id);
+        add_ref(&db.conn, second, shared_id);
+        add_ref(&db.conn, second, other_id);
+        add_card(&db.conn, second);
+
+        merge_note_with_next(&mut db.conn, OWNER, first)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            passage(&db.conn, deck_id, None),
+            vec!["first\n\nsecond", "third"]
+        );
+
+        // the ref that both notes had is only kept once
+        assert_eq!(ref_ids(&db.conn, first), vec![shared_id, other_id]);
+        assert!(ref_ids(&db.conn, second).is_empty());
+        assert_eq!(card_note_ids(&db.conn), vec![first]);
+        assert!(get_note(&db.conn, OWNER, second).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_move_note_with_point_to_deck() {
+        let mut db = test_db("move-note-to-deck");
+        let event_id = create_deck(&db.conn, "event", "Battle of Actium");
+        let idea_id = create_deck(&db.conn, "idea", "Naval Warfare");
+        let other_id = create_deck(&db.conn, "person", "Agrippa");
+        let point_id: Key = sqlite::one(
+            &db.conn,
+            "INSERT INTO points(deck_id, title, kind) VALUES (:deck_id, 'Actium', 'point')
+             RETURNING id",
+            named_params! {":deck_id": event_id},
+        )
+        .unwrap();
+        let point_notes = create_notes(
+            &mut db.conn,
+            OWNER,
+            ProtoNote {
+                kind: NoteKind::Note,
+                font: Font::Serif,
+                content: vec!["the fleets met".to_string(), "antony fled".to_string()],
+                deck_id: event_id,
+                point_id: Some(point_id),
+                prev_note_id: None,
+                next_note_id: None,
+            },
+        )
+        .unwrap();
+        let moved = point_notes[0].id;
+        add_note(&mut db.conn, idea_id, "triremes");
+        add_ref(&db.conn, moved, idea_id);
+        add_ref(&db.conn, moved, other_id);
+        add_card(&db.conn, moved);
+
+        move_note_to_deck(&mut db.conn, OWNER, moved, idea_id)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            passage(&db.conn, event_id, Some(point_id)),
+            vec!["antony fled"]
+        );
+        // the note is no longer attached to a point and goes on the end of the passage
+        assert_eq!(
+            passage(&db.conn, idea_id, None),
+            vec!["triremes", "the fleets met"]
+        );
+        // apart from the ref to the deck it's now in, its refs and cards are kept
+        assert_eq!(ref_ids(&db.conn, moved), vec![other_id]);
+        assert_eq!(card_note_ids(&db.conn), vec![moved]);
+    }
+}
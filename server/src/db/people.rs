@@ -16,12 +16,15 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit as audit_db;
 use crate::db::decks;
 use crate::db::notes as notes_db;
 use crate::db::points as points_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
+use crate::db::tags::DECK_TAG_FILTER;
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::{DeckKind, Pagination, ProtoSlimDeck, SlimDeck};
 use crate::interop::font::Font;
 use crate::interop::people::Person;
@@ -41,6 +44,7 @@ impl FromRow for Person {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
             sort_date: row.get("birth_date")?,
 
             points: vec![],
@@ -106,6 +110,7 @@ pub(crate) fn paginated_uncategorised(
     user_id: Key,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -115,9 +120,10 @@ pub(crate) fn paginated_uncategorised(
             .left_join("points p ON p.deck_id = d.id")
             .where_decklike_but_no_deck_id()
             .and("p.deck_id is null")
+            .and(DECK_TAG_FILTER)
             .limit()
             .offset(),
-        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":limit": num_items, ":offset": offset},
+        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":tag_id": tag_id, ":limit": num_items, ":offset": offset},
     )?;
 
     let total_items = sqlite::one(
@@ -126,8 +132,9 @@ pub(crate) fn paginated_uncategorised(
             .from_decklike()
             .left_join("points p ON p.deck_id = d.id")
             .where_decklike_but_no_deck_id()
-            .and("p.deck_id is null"),
-        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person},
+            .and("p.deck_id is null")
+            .and(DECK_TAG_FILTER),
+        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":tag_id": tag_id},
     )?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
@@ -140,10 +147,11 @@ pub(crate) fn paginated_ancient(
     user_id: Key,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     // the "-" seems to capture all of the early dates, some of the other formats that should work, don't.
     // So sticking with this for the moment
-    paginated_date_period(conn, user_id, "-", "0354-01-01", offset, num_items)
+    paginated_date_period(conn, user_id, "-", "0354-01-01", offset, num_items, tag_id)
 }
 
 pub(crate) fn paginated_medieval(
@@ -151,8 +159,17 @@ pub(crate) fn paginated_medieval(
     user_id: Key,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
-    paginated_date_period(conn, user_id, "0354-01-01", "1469-01-01", offset, num_items)
+    paginated_date_period(
+        conn,
+        user_id,
+        "0354-01-01",
+        "1469-01-01",
+        offset,
+        num_items,
+        tag_id,
+    )
 }
 
 pub(crate) fn paginated_modern(
@@ -160,8 +177,17 @@ pub(crate) fn paginated_modern(
     user_id: Key,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
-    paginated_date_period(conn, user_id, "1469-01-01", "1856-01-01", offset, num_items)
+    paginated_date_period(
+        conn,
+        user_id,
+        "1469-01-01",
+        "1856-01-01",
+        offset,
+        num_items,
+        tag_id,
+    )
 }
 
 pub(crate) fn paginated_contemporary(
@@ -169,8 +195,17 @@ pub(crate) fn paginated_contemporary(
     user_id: Key,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
-    paginated_date_period(conn, user_id, "1856-01-01", "2356-01-01", offset, num_items)
+    paginated_date_period(
+        conn,
+        user_id,
+        "1856-01-01",
+        "2356-01-01",
+        offset,
+        num_items,
+        tag_id,
+    )
 }
 
 pub(crate) fn get(
@@ -202,9 +237,12 @@ pub(crate) fn edit(
     user_id: Key,
     person: ProtoSlimDeck,
     person_id: Key,
+    version: i32,
 ) -> Result<Person, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, person_id)?;
+
     let deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -215,8 +253,11 @@ pub(crate) fn edit(
         person.insignia,
         person.font,
         person.impact,
+        version,
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, person_id, &before)?;
+
     tx.commit()?;
 
     let mut person: Person = deck.into();
@@ -235,6 +276,7 @@ fn paginated_date_period(
     until_date: &str,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> Result<Pagination<SlimDeck>, DbError> {
     let items = sqlite::many(
         &conn,
@@ -246,10 +288,11 @@ fn paginated_date_period(
             .where_decklike_but_no_deck_id()
             .and("p.kind = 'point_begin'")
             .and("birth_date >= :from_date AND birth_date < :until_date")
+            .and(DECK_TAG_FILTER)
             .order_by("COALESCE(p.exact_realdate, p.lower_realdate)")
             .limit()
             .offset(),
-        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":from_date": from_date, ":until_date": until_date, ":limit": num_items, ":offset": offset},
+        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":tag_id": tag_id, ":from_date": from_date, ":until_date": until_date, ":limit": num_items, ":offset": offset},
     )?;
 
     let total_items = sqlite::one(
@@ -260,9 +303,10 @@ fn paginated_date_period(
                 .join("points p ON p.deck_id = d.id")
                 .where_decklike_but_no_deck_id()
                 .and("p.kind = 'point_begin'")
-                .and("birth_date >= :from_date AND birth_date < :until_date"),
+                .and("birth_date >= :from_date AND birth_date < :until_date")
+                .and(DECK_TAG_FILTER),
         ),
-        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":from_date": from_date, ":until_date": until_date},
+        named_params! {":user_id": user_id, ":deck_kind": DeckKind::Person, ":tag_id": tag_id, ":from_date": from_date, ":until_date": until_date},
     )?;
 
     let res = Pagination::<SlimDeck> { items, total_items };
@@ -16,16 +16,20 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
-use crate::interop::points::{Point, PointKind, ProtoPoint};
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::points::{Area, Point, PointKind, ProtoPoint};
 use rusqlite::{Row, named_params};
 use std::fmt;
 
 #[allow(unused_imports)]
 use tracing::error;
 
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
 impl fmt::Display for PointKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -92,34 +96,19 @@ pub(crate) fn all(
     )
 }
 
-fn year_as_date_string(year: i32) -> String {
-    let mut res;
+// the first day of the year as an ISO 8601 date with a 4 digit year (e.g. -0500-01-01),
+// the form that sqlite's date functions expect for years before the common era
+//
+pub(crate) fn year_as_date_string(year: i32) -> String {
+    if year <= -10000 {
+        error!("year given to year_as_date_string is too old: {}", year);
+    }
 
     if year >= 0 {
-        res = year.to_string() + "-01-01";
-
-        if (100..1000).contains(&year) {
-            res = "0".to_owned() + &res;
-        } else if (10..100).contains(&year) {
-            res = "00".to_owned() + &res;
-        } else if (0..10).contains(&year) {
-            res = "000".to_owned() + &res;
-        }
+        format!("{year:04}-01-01")
     } else {
-        res = (-year).to_string() + "-01-01";
-
-        if year > -10 && year < 0 {
-            res = "-00".to_owned() + &res;
-        } else if year > -100 && year < -9 {
-            res = "-0".to_owned() + &res;
-        } else if year > -1000 && year < -99 {
-            res = "-".to_owned() + &res;
-        } else {
-            error!("year given to year_as_date_string is too old: {}", year);
-        }
+        format!("{year:05}-01-01")
     }
-
-    res
 }
 
 fn query_points_common() -> Qry {
@@ -154,14 +143,168 @@ pub(crate) fn all_points_within_interval(
     sqlite::many(
         &conn,
         &query_points_common()
-            .and("date(coalesce(p.exact_realdate, p.upper_realdate)) >= :lower_year")
-            .and("date(coalesce(p.exact_realdate, p.lower_realdate)) < :upper_year")
+            .and("coalesce(p.exact_realdate, p.upper_realdate) >= julianday(:lower_year)")
+            .and("coalesce(p.exact_realdate, p.lower_realdate) < julianday(:upper_year)")
             .and("d.impact > 0")
             .order_by("sortdate"),
         named_params! {":user_id": user_id, ":lower_year": lower_year, ":upper_year": upper_year},
     )
 }
 
+// a point along with where it is, only used while filtering points by area
+struct LocatedPoint {
+    point: Point,
+    latitude: f64,
+    longitude: f64,
+}
+
+impl FromRow for LocatedPoint {
+    fn from_row(row: &Row) -> rusqlite::Result<LocatedPoint> {
+        Ok(LocatedPoint {
+            point: Point::from_row(row)?,
+            latitude: row.get("latitude")?,
+            longitude: row.get("longitude")?,
+        })
+    }
+}
+
+// the points that are located within the area, optionally restricted to those that
+// overlap the years from lower to upper (inclusive)
+//
+pub(crate) fn all_points_within_area(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    area: &Area,
+    years: Option<(i32, i32)>,
+) -> Result<Vec<Point>, DbError> {
+    let (south, west, north, east) = bounding_box(area);
+
+    // a box that crosses the antimeridian wraps around from west to east
+    let longitude_check = if west <= east {
+        "p.longitude BETWEEN :west AND :east"
+    } else {
+        "(p.longitude >= :west OR p.longitude <= :east)"
+    };
+
+    let stmt = Qry::select("p.id as id")
+        .comma("p.kind as kind")
+        .comma("p.title as title")
+        .comma("p.font as font")
+        .comma("p.location_textual as location_textual")
+        .comma("p.date_textual as date_textual")
+        .comma("coalesce(date(p.exact_realdate), date(p.lower_realdate)) as date")
+        .comma("p.latitude as latitude")
+        .comma("p.longitude as longitude")
+        .comma("d.id as deck_id")
+        .comma("d.name as deck_name")
+        .comma("d.kind as deck_kind")
+        .comma("d.insignia as deck_insignia")
+        .comma("d.font as deck_font")
+        .comma("d.impact as deck_impact")
+        .comma("coalesce(p.exact_realdate, p.lower_realdate) as sortdate")
+        .from("points p")
+        .join("decks d ON p.deck_id = d.id")
+        .where_clause("d.user_id = :user_id")
+        .and("p.latitude BETWEEN :south AND :north")
+        .and(longitude_check)
+        // julian days, since dates before the common era don't sort as strings
+        .and("(:lower_year IS NULL OR coalesce(p.exact_realdate, p.upper_realdate) >= julianday(:lower_year))")
+        .and("(:upper_year IS NULL OR coalesce(p.exact_realdate, p.lower_realdate) < julianday(:upper_year))")
+        .and("d.impact > 0")
+        .order_by("sortdate");
+
+    let lower_year = years.map(|(lower, _)| year_as_date_string(lower));
+    let upper_year = years.map(|(_, upper)| year_as_date_string(upper + 1));
+
+    let located: Vec<LocatedPoint> = sqlite::many(
+        conn,
+        &stmt,
+        named_params! {
+            ":user_id": user_id,
+            ":south": south,
+            ":west": west,
+            ":north": north,
+            ":east": east,
+            ":lower_year": lower_year,
+            ":upper_year": upper_year,
+        },
+    )?;
+
+    Ok(located
+        .into_iter()
+        .filter(|lp| match *area {
+            Area::BoundingBox { .. } => true,
+            Area::Radius {
+                latitude,
+                longitude,
+                radius_km,
+            } => haversine_km(latitude, longitude, lp.latitude, lp.longitude) <= radius_km,
+        })
+        .map(|lp| lp.point)
+        .collect())
+}
+
+// (south, west, north, east) of a box that contains the area, a radius is
+// narrowed down further with haversine_km
+//
+fn bounding_box(area: &Area) -> (f64, f64, f64, f64) {
+    match *area {
+        Area::BoundingBox {
+            south,
+            west,
+            north,
+            east,
+        } => (south, west, north, east),
+        Area::Radius {
+            latitude,
+            longitude,
+            radius_km,
+        } => {
+            let angular = radius_km / EARTH_RADIUS_KM;
+            let delta_latitude = angular.to_degrees();
+            let south = latitude - delta_latitude;
+            let north = latitude + delta_latitude;
+
+            // near a pole (or for a big enough radius) every longitude is in range
+            let spans_pole = south <= -90.0 || north >= 90.0;
+            let sin_ratio = angular.sin() / latitude.to_radians().cos();
+            if spans_pole || sin_ratio >= 1.0 || angular >= std::f64::consts::PI / 2.0 {
+                return (south.max(-90.0), -180.0, north.min(90.0), 180.0);
+            }
+
+            let delta_longitude = sin_ratio.asin().to_degrees();
+            (
+                south,
+                wrap_longitude(longitude - delta_longitude),
+                north,
+                wrap_longitude(longitude + delta_longitude),
+            )
+        }
+    }
+}
+
+fn wrap_longitude(longitude: f64) -> f64 {
+    if longitude < -180.0 {
+        longitude + 360.0
+    } else if longitude > 180.0 {
+        longitude - 360.0
+    } else {
+        longitude
+    }
+}
+
+// the great-circle distance between two locations given in degrees
+//
+fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
 pub(crate) fn all_points_during_life(
     conn: &rusqlite::Connection,
     user_id: Key,
@@ -192,6 +335,7 @@ pub(crate) fn all_points_during_life(
 
 pub(crate) fn create(
     conn: &rusqlite::Connection,
+    user_id: Key,
     point: ProtoPoint,
     deck_id: Key,
 ) -> Result<(), DbError> {
@@ -214,5 +358,106 @@ pub(crate) fn create(
             ":lower_realdate": point.lower_date,
             ":upper_realdate": point.upper_date,
             ":date_fuzz": point.date_fuzz,
-        })
+        })?;
+
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::Point,
+        Some(Key(conn.last_insert_rowid())),
+        Payload::after(&point),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, create_deck, test_db};
+
+    fn radius(latitude: f64, longitude: f64, radius_km: f64) -> Area {
+        Area::Radius {
+            latitude,
+            longitude,
+            radius_km,
+        }
+    }
+
+    #[test]
+    fn test_year_as_date_string() {
+        assert_eq!(year_as_date_string(1800), "1800-01-01");
+        assert_eq!(year_as_date_string(5), "0005-01-01");
+        assert_eq!(year_as_date_string(0), "0000-01-01");
+        assert_eq!(year_as_date_string(-5), "-0005-01-01");
+        assert_eq!(year_as_date_string(-500), "-0500-01-01");
+    }
+
+    #[test]
+    fn test_bounding_box_across_antimeridian() {
+        let (south, west, north, east) = bounding_box(&radius(-17.7, 179.0, 300.0));
+        assert!(south < -17.7 && north > -17.7);
+        // wraps around from west to east
+        assert!(west > 170.0 && west < 179.0);
+        assert!(east > -180.0 && east < -170.0);
+    }
+
+    #[test]
+    fn test_bounding_box_near_pole() {
+        let (south, west, north, east) = bounding_box(&radius(89.5, 20.0, 200.0));
+        assert!(south > 87.0 && south < 89.5);
+        assert_eq!((west, north, east), (-180.0, 90.0, 180.0));
+    }
+
+    #[test]
+    fn test_bounding_box_every_longitude() {
+        // a quarter of the way around the earth covers every longitude at the equator
+        let (south, west, north, east) = bounding_box(&radius(0.0, 0.0, 10_100.0));
+        assert_eq!((west, east), (-180.0, 180.0));
+        assert!(south < -89.0 && north > 89.0);
+    }
+
+    #[test]
+    fn test_haversine_km() {
+        // Florence to Rome is about 232km as the crow flies
+        let florence_rome = haversine_km(43.7696, 11.2558, 41.9028, 12.4964);
+        assert!((florence_rome - 232.0).abs() < 3.0, "{florence_rome}");
+
+        assert_eq!(haversine_km(41.9028, 12.4964, 41.9028, 12.4964), 0.0);
+
+        // either side of the antimeridian
+        let across = haversine_km(0.0, 179.5, 0.0, -179.5);
+        assert!((across - 111.2).abs() < 1.0, "{across}");
+    }
+
+    #[test]
+    fn test_points_within_area_before_common_era() {
+        let db = test_db("points-bce");
+        let deck_id = create_deck(&db.conn, "event", "Battle of Thermopylae");
+        db.conn
+            .execute_batch(&format!(
+                "UPDATE decks SET impact = 1 WHERE id = {deck_id};
+                 INSERT INTO points(deck_id, title, kind, latitude, longitude, exact_realdate)
+                 VALUES ({deck_id}, 'Thermopylae', 'point', 38.8, 22.5, julianday('-0479-08-20'));"
+            ))
+            .unwrap();
+
+        let area = Area::BoundingBox {
+            south: 35.0,
+            west: 19.0,
+            north: 42.0,
+            east: 28.0,
+        };
+        let titles = |years| -> Vec<String> {
+            all_points_within_area(&db.conn, OWNER, &area, years)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.title)
+                .collect()
+        };
+
+        assert_eq!(titles(None), vec!["Thermopylae"]);
+        assert_eq!(titles(Some((-500, -450))), vec!["Thermopylae"]);
+        assert!(titles(Some((-450, -400))).is_empty());
+        assert!(titles(Some((-600, -500))).is_empty());
+    }
 }
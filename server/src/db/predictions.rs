@@ -16,10 +16,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit as audit_db;
 use crate::db::decks::{self, DeckBase, DeckBaseOrigin};
 use crate::db::notes as notes_db;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::{DeckKind, SlimDeck};
 use crate::interop::font::Font;
 use crate::interop::predictions::{Prediction, ProtoPrediction};
@@ -55,6 +57,7 @@ impl From<(DeckBase, PredictionExtra)> for Prediction {
             insignia: deck.insignia,
             font: deck.font,
             impact: deck.impact,
+            version: deck.version,
 
             location_textual: extra.location_textual,
             longitude: extra.longitude,
@@ -101,6 +104,7 @@ impl FromRow for Prediction {
             insignia: row.get(5)?,
             font: row.get(6)?,
             impact: row.get(7)?,
+            version: row.get(17)?,
 
             location_textual: row.get(8)?,
             longitude: row.get(9)?,
@@ -190,7 +194,7 @@ pub(crate) fn get(
                        points.latitude, points.location_fuzz,
                        points.date_textual, date(points.exact_realdate),
                        date(points.lower_realdate), date(points.upper_realdate),
-                       points.date_fuzz
+                       points.date_fuzz, decks.version
                 FROM decks LEFT JOIN points ON points.deck_id = decks.id
                 WHERE user_id = :user_id AND decks.id = :deck_id AND decks.kind = :deck_kind";
 
@@ -214,9 +218,12 @@ pub(crate) fn edit(
     user_id: Key,
     prediction: ProtoPrediction,
     prediction_id: Key,
+    version: i32,
 ) -> Result<Prediction, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, prediction_id)?;
+
     let edited_deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -227,6 +234,7 @@ pub(crate) fn edit(
         prediction.insignia,
         prediction.font,
         prediction.impact,
+        version,
     )?;
 
     let sql_query = "
@@ -256,6 +264,8 @@ pub(crate) fn edit(
         },
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, prediction_id, &before)?;
+
     tx.commit()?;
 
     let mut prediction: Prediction = (edited_deck, prediction_extras).into();
@@ -19,7 +19,7 @@ use std::ops::Deref;
 
 const SELECT_DECKLIKE: &str = "SELECT d.id as id, d.name as name, d.kind as kind, d.created_at as created_at,
                                d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font,
-                               d.impact as impact ";
+                               d.impact as impact, d.version as version ";
 
 pub(crate) struct Qry {
     s: String,
@@ -16,11 +16,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit as audit_db;
 use crate::db::decks;
 use crate::db::notes as notes_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::DeckKind;
 use crate::interop::notes::NoteKind;
 use crate::interop::quotes::{ProtoQuote, Quote};
@@ -41,6 +43,7 @@ impl From<decks::DeckBase> for Quote {
             insignia: deck.insignia,
             font: deck.font,
             impact: deck.impact,
+            version: deck.version,
 
             notes: vec![],
             arrivals: vec![],
@@ -59,6 +62,7 @@ impl FromRow for Quote {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             notes: vec![],
             arrivals: vec![],
@@ -226,9 +230,12 @@ pub(crate) fn edit(
     user_id: Key,
     quote: ProtoQuote,
     quote_id: Key,
+    version: i32,
 ) -> Result<Quote, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, quote_id)?;
+
     let deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -239,8 +246,11 @@ pub(crate) fn edit(
         quote.insignia,
         quote.font,
         quote.impact,
+        version,
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, quote_id, &before)?;
+
     tx.commit()?;
 
     let mut quote: Quote = deck.into();
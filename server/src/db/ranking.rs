@@ -0,0 +1,401 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::search::{Cursor, Ranking, SearchDeck};
+use chrono::NaiveDateTime;
+use rusqlite::{Row, named_params};
+
+#[allow(unused_imports)]
+use tracing::info;
+
+// the deck level results are ranked together and then returned a page at a time
+pub(crate) const PAGE_SIZE: usize = 30;
+
+const MAX_IMPACT: f64 = 4.0;
+
+// the number of days after a deck was last visited that its recency score has halved
+const RECENCY_DAYS: f64 = 30.0;
+
+impl FromRow for Ranking {
+    fn from_row(row: &Row) -> rusqlite::Result<Ranking> {
+        Ok(Ranking {
+            title: row.get("title")?,
+            aliases: row.get("aliases")?,
+            body: row.get("body")?,
+            impact: row.get("impact")?,
+            insignia: row.get("insignia")?,
+            visits: row.get("visits")?,
+            recency: row.get("recency")?,
+        })
+    }
+}
+
+struct Visits {
+    deck_id: Key,
+    count: i32,
+    latest: NaiveDateTime,
+}
+
+impl FromRow for Visits {
+    fn from_row(row: &Row) -> rusqlite::Result<Visits> {
+        Ok(Visits {
+            deck_id: row.get("deck_id")?,
+            count: row.get("count")?,
+            latest: row.get("latest")?,
+        })
+    }
+}
+
+pub(crate) fn get_ranking(conn: &rusqlite::Connection, user_id: Key) -> Result<Ranking, DbError> {
+    let ranking: Option<Ranking> = sqlite::one_optional(
+        conn,
+        "SELECT title, aliases, body, impact, insignia, visits, recency
+         FROM search_rankings
+         WHERE user_id = :user_id",
+        named_params! {":user_id": user_id},
+    )?;
+
+    Ok(ranking.unwrap_or_default())
+}
+
+pub(crate) fn edit_ranking(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    ranking: &Ranking,
+) -> Result<Ranking, DbError> {
+    let before = get_ranking(conn, user_id)?;
+
+    sqlite::zero(
+        conn,
+        "INSERT INTO search_rankings(user_id, title, aliases, body, impact, insignia, visits, recency)
+         VALUES (:user_id, :title, :aliases, :body, :impact, :insignia, :visits, :recency)
+         ON CONFLICT(user_id) DO UPDATE SET
+             title = excluded.title,
+             aliases = excluded.aliases,
+             body = excluded.body,
+             impact = excluded.impact,
+             insignia = excluded.insignia,
+             visits = excluded.visits,
+             recency = excluded.recency",
+        named_params! {
+            ":user_id": user_id,
+            ":title": ranking.title,
+            ":aliases": ranking.aliases,
+            ":body": ranking.body,
+            ":impact": ranking.impact,
+            ":insignia": ranking.insignia,
+            ":visits": ranking.visits,
+            ":recency": ranking.recency,
+        },
+    )?;
+
+    if before != *ranking {
+        audit_db::record(
+            conn,
+            user_id,
+            AuditAction::Edit,
+            AuditEntity::User,
+            Some(user_id),
+            Payload::before(&serde_json::json!({ "searchRanking": before }))
+                .with_after(&serde_json::json!({ "searchRanking": ranking })),
+        )?;
+    }
+
+    Ok(ranking.clone())
+}
+
+// the argument given to fts5 as "rank MATCH" so that the rank column of each
+// full text table uses the ranking's column weights
+//
+pub(crate) fn decks_fts_rank(ranking: &Ranking) -> String {
+    format!("bm25({}, {})", ranking.title, ranking.aliases)
+}
+
+pub(crate) fn points_fts_rank(ranking: &Ranking) -> String {
+    // title, location_textual, date_textual
+    format!(
+        "bm25({}, {}, {})",
+        ranking.title, ranking.body, ranking.body
+    )
+}
+
+pub(crate) fn body_fts_rank(ranking: &Ranking, num_columns: usize) -> String {
+    let weights = vec![ranking.body.to_string(); num_columns];
+    format!("bm25({})", weights.join(", "))
+}
+
+// replaces the bm25 rank of each search deck with a score that blends its
+// relevance with the deck's impact, insignia, visits and recency. Visits after
+// searched_at are ignored so that later pages of a search are scored the same.
+// Returns the decks with the highest scores first
+//
+pub(crate) fn rank_search_decks(
+    conn: &rusqlite::Connection,
+    ranking: &Ranking,
+    mut search_decks: Vec<SearchDeck>,
+    searched_at: i64,
+) -> Result<Vec<SearchDeck>, DbError> {
+    if search_decks.is_empty() {
+        return Ok(search_decks);
+    }
+
+    let ids: Vec<String> = search_decks
+        .iter()
+        .map(|search_deck| search_deck.deck.id.to_string())
+        .collect();
+    let stmt = format!(
+        "SELECT deck_id, count(*) AS count, max(created_at) AS latest
+         FROM hits
+         WHERE deck_id IN ({}) AND created_at <= datetime(:searched_at, 'unixepoch')
+         GROUP BY deck_id",
+        ids.join(", ")
+    );
+    let visits: Vec<Visits> =
+        sqlite::many(conn, &stmt, named_params! {":searched_at": searched_at})?;
+
+    let now = chrono::DateTime::from_timestamp(searched_at, 0)
+        .unwrap_or_default()
+        .naive_utc();
+
+    // bm25 ranks are negative with the best match being the lowest
+    let best_rank = search_decks
+        .iter()
+        .map(|search_deck| search_deck.rank)
+        .fold(0.0f32, f32::min) as f64;
+    let most_visits = visits.iter().map(|v| v.count).max().unwrap_or(0) as f64;
+
+    for search_deck in &mut search_decks {
+        let deck = &search_deck.deck;
+
+        let relevance = if best_rank < 0.0 {
+            search_deck.rank as f64 / best_rank
+        } else {
+            0.0
+        };
+        let impact = (deck.impact as f64 / MAX_IMPACT).clamp(0.0, 1.0);
+        let insignia = if deck.insignia != 0 { 1.0 } else { 0.0 };
+
+        let visited = visits.iter().find(|v| v.deck_id == deck.id);
+        let visit_score = match visited {
+            Some(v) if most_visits > 0.0 => (1.0 + v.count as f64).ln() / (1.0 + most_visits).ln(),
+            _ => 0.0,
+        };
+        let last_seen = visited.map_or(deck.created_at, |v| v.latest.max(deck.created_at));
+        let days = ((now - last_seen).num_seconds() as f64 / 86400.0).max(0.0);
+        let recency = 1.0 / (1.0 + days / RECENCY_DAYS);
+
+        let score = relevance
+            + ranking.impact * impact
+            + ranking.insignia * insignia
+            + ranking.visits * visit_score
+            + ranking.recency * recency;
+        search_deck.rank = score as f32;
+    }
+
+    search_decks.sort_by(|a, b| {
+        b.rank
+            .total_cmp(&a.rank)
+            .then(a.deck.id.0.cmp(&b.deck.id.0))
+    });
+
+    Ok(search_decks)
+}
+
+// the page of ranked search decks that follows the cursor (or the first page if
+// there isn't one) along with the cursor for the page after it
+//
+pub(crate) fn page_of_search_decks(
+    ranked: Vec<SearchDeck>,
+    after: Option<&Cursor>,
+    searched_at: i64,
+) -> (Vec<SearchDeck>, Option<Cursor>) {
+    let mut remaining: Vec<SearchDeck> = match after {
+        Some(cursor) => ranked
+            .into_iter()
+            .filter(|search_deck| {
+                search_deck.rank < cursor.score
+                    || (search_deck.rank == cursor.score
+                        && search_deck.deck.id.0 > cursor.deck_id.0)
+            })
+            .collect(),
+        None => ranked,
+    };
+
+    if remaining.len() <= PAGE_SIZE {
+        return (remaining, None);
+    }
+
+    remaining.truncate(PAGE_SIZE);
+    let next = remaining.last().map(|last| Cursor {
+        score: last.rank,
+        deck_id: last.deck.id,
+        searched_at,
+    });
+
+    (remaining, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{create_deck, test_db};
+    use crate::interop::decks::{DeckKind, SlimDeck};
+    use crate::interop::font::Font;
+    use std::collections::HashSet;
+
+    const SEARCHED_AT: i64 = 1_800_000_000;
+
+    fn search_deck(id: Key, rank: f32, impact: i32) -> SearchDeck {
+        SearchDeck {
+            rank,
+            deck: SlimDeck {
+                id,
+                title: format!("deck {id}"),
+                deck_kind: DeckKind::Idea,
+                created_at: chrono::DateTime::from_timestamp(SEARCHED_AT - 86400, 0)
+                    .unwrap()
+                    .naive_utc(),
+                graph_terminator: false,
+                insignia: 0,
+                font: Font::Serif,
+                impact,
+            },
+            notes: vec![],
+            foreign: None,
+            snippet: None,
+            source: None,
+        }
+    }
+
+    fn ids(search_decks: &[SearchDeck]) -> Vec<i64> {
+        search_decks.iter().map(|s| s.deck.id.0).collect()
+    }
+
+    #[test]
+    fn test_pages_are_disjoint_and_complete() {
+        let db = test_db("ranking-pages");
+        let ranking = Ranking::default();
+
+        // plenty of ties, with ids that aren't in rank order
+        let num_decks = PAGE_SIZE * 2 + 5;
+        let ranked = || {
+            let search_decks = (1..=num_decks as i64)
+                .rev()
+                .map(|id| search_deck(Key(id), -((id % 7) as f32), 0))
+                .collect();
+            rank_search_decks(&db.conn, &ranking, search_decks, SEARCHED_AT).unwrap()
+        };
+        let all = ids(&ranked());
+
+        let mut seen: Vec<i64> = vec![];
+        let mut cursor: Option<Cursor> = None;
+        let mut pages = 0;
+        loop {
+            let (page, next) = page_of_search_decks(ranked(), cursor.as_ref(), SEARCHED_AT);
+            pages += 1;
+            assert!(page.len() <= PAGE_SIZE);
+            seen.extend(ids(&page));
+
+            let Some(next) = next else {
+                break;
+            };
+            // the cursor makes a round trip through the client
+            let parsed: Cursor = next.to_string().parse().unwrap();
+            assert_eq!(parsed, next);
+            cursor = Some(parsed);
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(seen, all);
+        assert_eq!(seen.iter().collect::<HashSet<_>>().len(), num_decks);
+    }
+
+    #[test]
+    fn test_invalid_cursors() {
+        assert!("".parse::<Cursor>().is_err());
+        assert!("1.5_3".parse::<Cursor>().is_err());
+        assert!("1.5_3_100_4".parse::<Cursor>().is_err());
+        assert!("high_3_100".parse::<Cursor>().is_err());
+
+        let cursor: Cursor = "-0.25_3_100".parse().unwrap();
+        assert_eq!(cursor.score, -0.25);
+        assert_eq!(cursor.deck_id, Key(3));
+        assert_eq!(cursor.searched_at, 100);
+    }
+
+    #[test]
+    fn test_ties_break_by_deck_id() {
+        let db = test_db("ranking-ties");
+        let search_decks = vec![
+            search_deck(Key(3), -1.0, 0),
+            search_deck(Key(1), -1.0, 0),
+            search_deck(Key(2), -1.0, 0),
+        ];
+        let ranked =
+            rank_search_decks(&db.conn, &Ranking::default(), search_decks, SEARCHED_AT).unwrap();
+        assert_eq!(ids(&ranked), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_impact_and_visits_change_the_order() {
+        let db = test_db("ranking-impact-visits");
+        let plain = create_deck(&db.conn, "idea", "Plain");
+        let visited = create_deck(&db.conn, "idea", "Visited");
+        let ranking = Ranking::default();
+        let searched_at = chrono::Utc::now().timestamp() + 60;
+
+        // before any visits the deck with the greater impact comes first
+        let ranked = rank_search_decks(
+            &db.conn,
+            &ranking,
+            vec![search_deck(plain, -1.0, 0), search_deck(visited, -1.0, 4)],
+            searched_at,
+        )
+        .unwrap();
+        assert_eq!(ids(&ranked), [visited.0, plain.0]);
+
+        // visits only count once the ranking weighs them
+        db.conn
+            .execute(
+                "INSERT INTO hits(deck_id) VALUES (?1), (?1), (?1)",
+                [visited],
+            )
+            .unwrap();
+        let search_decks = || vec![search_deck(plain, -1.0, 0), search_deck(visited, -1.0, 0)];
+
+        let unweighted = Ranking {
+            visits: 0.0,
+            recency: 0.0,
+            ..ranking.clone()
+        };
+        let ranked = rank_search_decks(&db.conn, &unweighted, search_decks(), searched_at).unwrap();
+        assert_eq!(ids(&ranked), [plain.0, visited.0]);
+
+        let ranked = rank_search_decks(&db.conn, &ranking, search_decks(), searched_at).unwrap();
+        assert_eq!(ids(&ranked), [visited.0, plain.0]);
+
+        // visits after the first page was searched for are ignored
+        let ranked =
+            rank_search_decks(&db.conn, &ranking, search_decks(), searched_at - 3600).unwrap();
+        assert_eq!(ids(&ranked), [plain.0, visited.0]);
+    }
+}
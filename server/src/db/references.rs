@@ -16,9 +16,11 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::decks::deckbase_get_or_create;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
 use crate::interop::decks::Ref;
 use crate::interop::decks::{DeckKind, SlimDeck};
 use crate::interop::font::Font;
@@ -53,15 +55,30 @@ pub(crate) fn update_references(
     user_id: Key,
     note_id: Key,
 ) -> Result<ReferencesApplied, DbError> {
-    info!("update_references");
     let tx = conn.transaction()?;
+    let applied = update_references_in_tx(&tx, diff, user_id, note_id)?;
+    tx.commit()?;
+
+    Ok(applied)
+}
+
+// note: this should be part of a transaction
+//
+pub(crate) fn update_references_in_tx(
+    tx: &Connection,
+    diff: ReferencesDiff,
+    user_id: Key,
+    note_id: Key,
+) -> Result<ReferencesApplied, DbError> {
+    info!("update_references");
+    let before = audit_db::note_snapshot(tx, user_id, note_id)?;
 
     let stmt_refs_removed = "DELETE FROM refs WHERE note_id = :note_id AND deck_id = :removed_id";
     for removed in &diff.references_removed {
         // this deck has been removed from the note by the user
         info!("deleting {}, {}", &note_id, &removed.id);
         sqlite::zero(
-            &tx,
+            tx,
             stmt_refs_removed,
             named_params! {":note_id": note_id, ":removed_id": removed.id},
         )?;
@@ -77,7 +94,7 @@ pub(crate) fn update_references(
         );
 
         sqlite::zero(
-            &tx,
+            tx,
             stmt_refs_changed,
             named_params! {
                 ":changed_id": changed.id,
@@ -96,7 +113,7 @@ pub(crate) fn update_references(
             &note_id, &added.id
         );
         sqlite::zero(
-            &tx,
+            tx,
             stmt_refs_added,
             named_params! {
                 ":note_id": note_id,
@@ -112,7 +129,7 @@ pub(crate) fn update_references(
     for created in &diff.references_created {
         info!("create new idea: {} and a new edge", created.title);
         let (deck, _created) = deckbase_get_or_create(
-            &tx,
+            tx,
             user_id,
             DeckKind::Idea,
             &created.title,
@@ -122,7 +139,7 @@ pub(crate) fn update_references(
             1,
         )?;
         sqlite::zero(
-            &tx,
+            tx,
             stmt_refs_added,
             named_params! {
                 ":note_id": note_id,
@@ -141,11 +158,19 @@ pub(crate) fn update_references(
                           d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact
          FROM refs r, decks d
          WHERE r.note_id = :note_id AND d.id = r.deck_id";
-    let refs: Vec<Ref> = sqlite::many(&tx, stmt_all_decks, named_params! {":note_id": note_id})?;
-
-    let recents = decks_recently_referenced(&tx, user_id)?;
-
-    tx.commit()?;
+    let refs: Vec<Ref> = sqlite::many(tx, stmt_all_decks, named_params! {":note_id": note_id})?;
+
+    let recents = decks_recently_referenced(tx, user_id)?;
+
+    let after = audit_db::note_snapshot(tx, user_id, note_id)?;
+    audit_db::record(
+        tx,
+        user_id,
+        AuditAction::Edit,
+        AuditEntity::Ref,
+        Some(note_id),
+        Payload::before(&before).with_after(&after),
+    )?;
 
     Ok(ReferencesApplied { refs, recents })
 }
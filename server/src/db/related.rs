@@ -0,0 +1,345 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::decks::SlimDeck;
+use crate::interop::related::RelatedDeck;
+use rusqlite::{Connection, Row, named_params};
+use std::collections::{HashMap, HashSet};
+
+#[allow(unused_imports)]
+use tracing::info;
+
+// only the most frequent terms of a deck are kept
+const TERMS_PER_DECK: usize = 200;
+
+const MAX_RELATED_DECKS: usize = 10;
+const MIN_SIMILARITY: f32 = 0.05;
+const SHARED_TERMS_SHOWN: usize = 5;
+
+const STOP_WORDS: [&str; 60] = [
+    "about", "after", "again", "all", "also", "and", "any", "are", "because", "been", "before",
+    "being", "but", "can", "could", "did", "does", "for", "from", "had", "has", "have", "her",
+    "his", "how", "into", "its", "just", "more", "most", "not", "now", "one", "only", "other",
+    "our", "out", "over", "same", "she", "should", "some", "such", "than", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "those", "was", "were", "what", "which",
+    "who", "with",
+];
+
+struct DeckTerm {
+    deck_id: Key,
+    term: String,
+    count: i32,
+    decks_with_term: i32,
+}
+
+impl FromRow for DeckTerm {
+    fn from_row(row: &Row) -> rusqlite::Result<DeckTerm> {
+        Ok(DeckTerm {
+            deck_id: row.get("deck_id")?,
+            term: row.get("term")?,
+            count: row.get("count")?,
+            decks_with_term: row.get("decks_with_term")?,
+        })
+    }
+}
+
+// decks whose notes are similar to the given deck's notes but which aren't
+// connected to it by a ref in either direction. Returns None if the user doesn't
+// own the deck
+//
+pub(crate) fn related_decks(
+    conn: &mut Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Option<Vec<RelatedDeck>>, DbError> {
+    let owned: Option<Key> = sqlite::one_optional(
+        conn,
+        "SELECT id FROM decks WHERE id = :deck_id AND user_id = :user_id",
+        named_params! {":deck_id": deck_id, ":user_id": user_id},
+    )?;
+    if owned.is_none() {
+        return Ok(None);
+    }
+
+    refresh_stale_terms(conn, user_id)?;
+
+    let num_decks: i32 = sqlite::one(
+        conn,
+        "SELECT count(DISTINCT dt.deck_id)
+         FROM deck_terms dt JOIN decks d ON d.id = dt.deck_id
+         WHERE d.user_id = :user_id",
+        named_params! {":user_id": user_id},
+    )?;
+
+    // every term of every deck that shares a term with deck_id, including deck_id itself
+    let deck_terms: Vec<DeckTerm> = sqlite::many(
+        conn,
+        "WITH df AS (SELECT dt.term AS term, count(*) AS decks_with_term
+                     FROM deck_terms dt JOIN decks d ON d.id = dt.deck_id
+                     WHERE d.user_id = :user_id
+                     GROUP BY dt.term)
+         SELECT dt.deck_id AS deck_id, dt.term AS term, dt.count AS count, df.decks_with_term AS decks_with_term
+         FROM deck_terms dt JOIN df ON df.term = dt.term
+         WHERE dt.deck_id IN (SELECT DISTINCT c.deck_id
+                              FROM deck_terms c JOIN decks cd ON cd.id = c.deck_id
+                              WHERE cd.user_id = :user_id
+                                    AND c.term IN (SELECT term FROM deck_terms WHERE deck_id = :deck_id))",
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+
+    let mut vectors: HashMap<Key, HashMap<String, f32>> = HashMap::new();
+    for dt in deck_terms {
+        let tf = 1.0 + (dt.count as f32).ln();
+        let idf = ((1.0 + num_decks as f32) / (1.0 + dt.decks_with_term as f32)).ln() + 1.0;
+        vectors
+            .entry(dt.deck_id)
+            .or_default()
+            .insert(dt.term, tf * idf);
+    }
+
+    let Some(target) = vectors.remove(&deck_id) else {
+        return Ok(Some(vec![]));
+    };
+    let target_norm = norm(&target);
+
+    let linked: HashSet<Key> = sqlite::many::<Key, _>(
+        conn,
+        "SELECT r.deck_id FROM refs r JOIN notes n ON n.id = r.note_id WHERE n.deck_id = :deck_id
+         UNION
+         SELECT n.deck_id FROM refs r JOIN notes n ON n.id = r.note_id WHERE r.deck_id = :deck_id",
+        named_params! {":deck_id": deck_id},
+    )?
+    .into_iter()
+    .collect();
+
+    let mut scored: Vec<(Key, f32, Vec<String>)> = vectors
+        .into_iter()
+        .filter(|(id, _)| !linked.contains(id))
+        .filter_map(|(id, vector)| {
+            let mut shared: Vec<(&String, f32)> = target
+                .iter()
+                .filter_map(|(term, w)| vector.get(term).map(|v| (term, w * v)))
+                .collect();
+            let dot: f32 = shared.iter().map(|(_, product)| product).sum();
+            let similarity = dot / (target_norm * norm(&vector));
+            if similarity < MIN_SIMILARITY {
+                return None;
+            }
+
+            shared.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let terms = shared
+                .into_iter()
+                .take(SHARED_TERMS_SHOWN)
+                .map(|(term, _)| term.clone())
+                .collect();
+            Some((id, similarity, terms))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(MAX_RELATED_DECKS);
+
+    let mut related = vec![];
+    for (id, similarity, shared_terms) in scored {
+        let deck: SlimDeck = sqlite::one(
+            conn,
+            "SELECT id, name, kind, created_at, graph_terminator, insignia, font, impact
+             FROM decks
+             WHERE id = :id",
+            named_params! {":id": id},
+        )?;
+        related.push(RelatedDeck {
+            deck,
+            similarity,
+            shared_terms,
+        });
+    }
+
+    Ok(Some(related))
+}
+
+fn norm(vector: &HashMap<String, f32>) -> f32 {
+    vector.values().map(|w| w * w).sum::<f32>().sqrt()
+}
+
+// recount the terms of the user's decks that have changed since they were last counted
+//
+fn refresh_stale_terms(conn: &mut Connection, user_id: Key) -> Result<(), DbError> {
+    let tx = conn.transaction()?;
+
+    // a deck that no longer exists only needs to leave the stale list
+    sqlite::zero(
+        &tx,
+        "DELETE FROM deck_terms_stale WHERE deck_id NOT IN (SELECT id FROM decks)",
+        named_params! {},
+    )?;
+
+    let stale: Vec<Key> = sqlite::many(
+        &tx,
+        "SELECT s.deck_id
+         FROM deck_terms_stale s JOIN decks d ON d.id = s.deck_id
+         WHERE d.user_id = :user_id",
+        named_params! {":user_id": user_id},
+    )?;
+
+    for deck_id in stale {
+        let contents: Vec<String> = sqlite::many(
+            &tx,
+            "SELECT content FROM notes WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+
+        let mut counts: HashMap<String, i32> = HashMap::new();
+        for content in contents {
+            let text = civil_shared::markup_as_simplified_text(&content).unwrap_or(content);
+            for term in terms(&text) {
+                *counts.entry(term).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(String, i32)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(TERMS_PER_DECK);
+
+        sqlite::zero(
+            &tx,
+            "DELETE FROM deck_terms WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+        for (term, count) in counts {
+            sqlite::zero(
+                &tx,
+                "INSERT INTO deck_terms(deck_id, term, count) VALUES (:deck_id, :term, :count)",
+                named_params! {":deck_id": deck_id, ":term": term, ":count": count},
+            )?;
+        }
+        sqlite::zero(
+            &tx,
+            "DELETE FROM deck_terms_stale WHERE deck_id = :deck_id",
+            named_params! {":deck_id": deck_id},
+        )?;
+    }
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+// the lowercased words of some text, ignoring short words, numbers and stop words
+//
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 2 && !word.chars().all(|c| c.is_numeric()))
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::notes as notes_db;
+    use crate::db::test_util::{OWNER, RECIPIENT, add_note, add_ref, create_deck, test_db};
+
+    fn related_ids(conn: &mut Connection, deck_id: Key) -> Vec<Key> {
+        related_decks(conn, OWNER, deck_id)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|r| r.deck.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_terms() {
+        let found: Vec<String> =
+            terms("The Stoics, in 2024, read Épictète's ideas of virtue").collect();
+        assert_eq!(found, ["stoics", "read", "épictète", "ideas", "virtue"]);
+
+        assert_eq!(terms("it is of the 42 and").count(), 0);
+    }
+
+    #[test]
+    fn test_related_decks_are_scored_by_tf_idf() {
+        let mut db = test_db("related-tf-idf");
+        let target = create_deck(&db.conn, "idea", "Stoicism");
+        let close = create_deck(&db.conn, "idea", "Virtue");
+        let distant = create_deck(&db.conn, "idea", "Cooking");
+        let unrelated = create_deck(&db.conn, "idea", "Gardening");
+
+        add_note(&mut db.conn, target, "stoicism virtue virtue");
+        add_note(&mut db.conn, close, "stoicism virtue");
+        add_note(&mut db.conn, distant, "stoicism cooking");
+        add_note(&mut db.conn, unrelated, "gardening compost");
+
+        let related = related_decks(&mut db.conn, OWNER, target).unwrap().unwrap();
+        assert_eq!(related.len(), 2);
+        assert_eq!(related[0].deck.id, close);
+        assert_eq!(related[0].shared_terms, ["virtue", "stoicism"]);
+        assert_eq!(related[1].deck.id, distant);
+        assert_eq!(related[1].shared_terms, ["stoicism"]);
+        assert!(related[0].similarity > related[1].similarity);
+        assert!(related[0].similarity <= 1.0);
+
+        assert!(
+            related_decks(&mut db.conn, RECIPIENT, target)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_ref_linked_decks_are_excluded() {
+        let mut db = test_db("related-linked");
+        let target = create_deck(&db.conn, "idea", "Stoicism");
+        let referenced = create_deck(&db.conn, "idea", "Virtue");
+        let referencing = create_deck(&db.conn, "idea", "Ethics");
+        let unlinked = create_deck(&db.conn, "idea", "Cynicism");
+
+        let note_id = add_note(&mut db.conn, target, "stoicism virtue");
+        add_ref(&db.conn, note_id, referenced);
+        add_note(&mut db.conn, referenced, "stoicism virtue");
+        let note_id = add_note(&mut db.conn, referencing, "stoicism virtue");
+        add_ref(&db.conn, note_id, target);
+        add_note(&mut db.conn, unlinked, "stoicism virtue");
+
+        assert_eq!(related_ids(&mut db.conn, target), [unlinked]);
+    }
+
+    #[test]
+    fn test_edited_notes_refresh_terms() {
+        let mut db = test_db("related-stale");
+        let target = create_deck(&db.conn, "idea", "Stoicism");
+        let other = create_deck(&db.conn, "idea", "Gardening");
+
+        add_note(&mut db.conn, target, "stoicism virtue");
+        let note_id = add_note(&mut db.conn, other, "gardening compost");
+        assert!(related_ids(&mut db.conn, target).is_empty());
+
+        let mut note = notes_db::get_note(&db.conn, OWNER, note_id)
+            .unwrap()
+            .unwrap();
+        note.content = "the virtue of gardening".to_string();
+        notes_db::edit_note(&db.conn, OWNER, note, note_id, None).unwrap();
+
+        assert_eq!(related_ids(&mut db.conn, target), [other]);
+        let stale: i32 =
+            sqlite::one(&db.conn, "SELECT count(*) FROM deck_terms_stale", []).unwrap();
+        assert_eq!(stale, 0);
+    }
+}
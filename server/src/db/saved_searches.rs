@@ -0,0 +1,216 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::saved_searches as interop;
+use rusqlite::{Row, named_params};
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+impl FromRow for interop::SavedSearch {
+    fn from_row(row: &Row) -> rusqlite::Result<interop::SavedSearch> {
+        Ok(interop::SavedSearch {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            query: row.get("query")?,
+            pinned: row.get("pinned")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+pub(crate) fn get_saved_searches(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+) -> Result<Vec<interop::SavedSearch>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id, name, query, pinned, created_at
+         FROM saved_searches
+         WHERE user_id = :user_id
+         ORDER BY name",
+        named_params! {":user_id": user_id},
+    )
+}
+
+pub(crate) fn get_pinned_saved_searches(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+) -> Result<Vec<interop::SavedSearch>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id, name, query, pinned, created_at
+         FROM saved_searches
+         WHERE user_id = :user_id AND pinned
+         ORDER BY name",
+        named_params! {":user_id": user_id},
+    )
+}
+
+pub(crate) fn get_saved_search(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    saved_search_id: Key,
+) -> Result<Option<interop::SavedSearch>, DbError> {
+    sqlite::one_optional(
+        conn,
+        "SELECT id, name, query, pinned, created_at
+         FROM saved_searches
+         WHERE user_id = :user_id AND id = :saved_search_id",
+        named_params! {":user_id": user_id, ":saved_search_id": saved_search_id},
+    )
+}
+
+pub(crate) fn create_saved_search(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    proto: &interop::ProtoSavedSearch,
+) -> Result<interop::SavedSearch, DbError> {
+    let saved_search: interop::SavedSearch = sqlite::one(
+        conn,
+        "INSERT INTO saved_searches(user_id, name, query, pinned)
+         VALUES (:user_id, :name, :query, :pinned)
+         RETURNING id, name, query, pinned, created_at",
+        named_params! {
+            ":user_id": user_id,
+            ":name": proto.name,
+            ":query": proto.query,
+            ":pinned": proto.pinned,
+        },
+    )?;
+
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::SavedSearch,
+        Some(saved_search.id),
+        Payload::after(&saved_search),
+    )?;
+
+    Ok(saved_search)
+}
+
+pub(crate) fn edit_saved_search(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    saved_search_id: Key,
+    proto: &interop::ProtoSavedSearch,
+) -> Result<Option<interop::SavedSearch>, DbError> {
+    let before = get_saved_search(conn, user_id, saved_search_id)?;
+
+    sqlite::zero(
+        conn,
+        "UPDATE saved_searches
+         SET name = :name, query = :query, pinned = :pinned
+         WHERE user_id = :user_id AND id = :saved_search_id",
+        named_params! {
+            ":user_id": user_id,
+            ":saved_search_id": saved_search_id,
+            ":name": proto.name,
+            ":query": proto.query,
+            ":pinned": proto.pinned,
+        },
+    )?;
+    let changed = conn.changes() > 0;
+
+    let after = get_saved_search(conn, user_id, saved_search_id)?;
+
+    if changed {
+        audit_db::record(
+            conn,
+            user_id,
+            AuditAction::Edit,
+            AuditEntity::SavedSearch,
+            Some(saved_search_id),
+            Payload::before(&before).with_after(&after),
+        )?;
+    }
+
+    Ok(after)
+}
+
+pub(crate) fn delete_saved_search(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    saved_search_id: Key,
+) -> Result<(), DbError> {
+    let before = get_saved_search(conn, user_id, saved_search_id)?;
+
+    sqlite::zero(
+        conn,
+        "DELETE FROM saved_searches WHERE user_id = :user_id AND id = :saved_search_id",
+        named_params! {":user_id": user_id, ":saved_search_id": saved_search_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::SavedSearch,
+        Some(saved_search_id),
+        Payload::before(&before),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, RECIPIENT, test_db};
+    use crate::error::Error;
+    use actix_web::{ResponseError, http::StatusCode};
+
+    fn proto(name: &str, query: &str) -> interop::ProtoSavedSearch {
+        interop::ProtoSavedSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_name_is_a_conflict() {
+        let test = test_db("saved-search-duplicate");
+        create_saved_search(&test.conn, OWNER, &proto("Stoics", "stoic")).unwrap();
+
+        let err: Error = create_saved_search(&test.conn, OWNER, &proto("Stoics", "virtue"))
+            .unwrap_err()
+            .into();
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+        assert_eq!(get_saved_searches(&test.conn, OWNER).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_names_are_per_user() {
+        let test = test_db("saved-search-per-user");
+        create_saved_search(&test.conn, OWNER, &proto("Stoics", "stoic")).unwrap();
+        create_saved_search(&test.conn, RECIPIENT, &proto("Stoics", "stoic")).unwrap();
+
+        let saved = get_saved_searches(&test.conn, OWNER).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(
+            get_saved_search(&test.conn, RECIPIENT, saved[0].id)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
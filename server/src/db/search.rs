@@ -18,17 +18,28 @@
 use crate::db::DbError;
 use crate::db::memorise as db_memorise;
 use crate::db::notes as db_notes;
+use crate::db::points::year_as_date_string;
+use crate::db::ranking as db_ranking;
 use crate::db::sanitize_for_sqlite_match;
 use crate::db::sqlite::{self, FromRow};
+use crate::db::tags::DECK_TAG_FILTER;
+use crate::db::users as db_users;
 use crate::interop::Key;
 use crate::interop::decks::{Arrival, DeckKind, Ref, SlimDeck};
+use crate::interop::memorise::FlashCard;
 use crate::interop::notes::Note;
-use crate::interop::search::{SearchDeck, SearchResults};
+use crate::interop::search::{
+    Cursor, Language, Ranking, SearchDeck, SearchResults, SearchSource, SearchTerms,
+};
+use rusqlite::types::ToSql;
 use rusqlite::{Connection, Row, named_params};
 
 #[allow(unused_imports)]
 use tracing::{info, warn};
 
+// the number of decks taken from each of the deck level queries before they're ranked
+const CANDIDATES: usize = 100;
+
 impl FromRow for SearchDeck {
     fn from_row(row: &Row) -> rusqlite::Result<SearchDeck> {
         Ok(SearchDeck {
@@ -44,6 +55,9 @@ impl FromRow for SearchDeck {
                 impact: row.get("impact")?,
             },
             notes: vec![],
+            foreign: None,
+            snippet: None,
+            source: None,
         })
     }
 }
@@ -100,21 +114,305 @@ impl FromRow for SearchDeckNoteRef {
                 content: row.get("content")?,
                 point_id: row.get("point_id")?,
                 font: row.get("note_font")?,
+                private: row.get("note_private")?,
+                version: row.get("note_version")?,
                 refs: vec![],
                 flashcards: vec![],
+                snippet: renderable_snippet(row.get("snippet")?, row.get("content")?),
             },
             reference_maybe,
         })
     }
 }
 
+// fts5 fragments are cut at token boundaries and can split a note's markup, so
+// only keep a snippet that still parses and is shorter than the whole note
+//
+fn renderable_snippet(snippet: Option<String>, content: String) -> Option<String> {
+    snippet.filter(|snippet| {
+        snippet.len() < content.len() && civil_shared::markup_as_ast(snippet).is_ok()
+    })
+}
+
+// a deck found via one of its points along with a fragment of the best matching point
+//
+struct PointHit {
+    search_deck: SearchDeck,
+    snippet: Option<String>,
+}
+
+impl FromRow for PointHit {
+    fn from_row(row: &Row) -> rusqlite::Result<PointHit> {
+        Ok(PointHit {
+            search_deck: SearchDeck::from_row(row)?,
+            snippet: row.get("snippet")?,
+        })
+    }
+}
+
+// a note found via a ref annotation or dialogue message along with a fragment of
+// the text that matched
+//
+struct SourceHit {
+    search_deck_note_ref: SearchDeckNoteRef,
+    snippet: Option<String>,
+}
+
+impl FromRow for SourceHit {
+    fn from_row(row: &Row) -> rusqlite::Result<SourceHit> {
+        let snippet: Option<String> = row.get("source_snippet")?;
+        Ok(SourceHit {
+            search_deck_note_ref: SearchDeckNoteRef::from_row(row)?,
+            snippet: snippet.filter(|snippet| civil_shared::markup_as_ast(snippet).is_ok()),
+        })
+    }
+}
+
+// the filters of a search as sql constraints on the deck 'd' and the note 'n'.
+// refs are resolved to deck ids first so that everything in here is either a
+// number or comes from an enum and can be formatted straight into a statement,
+// the only text from the user is in the LIKE patterns which are bound as parameters
+//
+#[derive(Default)]
+struct Constraints {
+    deck: String,
+    note: String,
+    // the tables that the text is matched against
+    index: Index,
+    // the parameter names and LIKE patterns of a substring search
+    likes: Vec<(String, String)>,
+}
+
+// which of the full text indexes of decks and notes a search goes through
+//
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Index {
+    #[default]
+    Stemmed,
+    Trigram,
+    // CJK terms that are too short for the trigram indexes (e.g. 東京), these are
+    // matched with LIKE as part of the constraints rather than through an index
+    Substring,
+}
+
+impl Index {
+    // the stemmed indexes only work with English and with text that has spaces
+    // between its words, anything else is searched by substring: through the
+    // trigram indexes when every term is long enough to be split into trigrams
+    fn for_search(language: Language, terms: &SearchTerms) -> Index {
+        if !language.is_stemmed() || terms.has_cjk() {
+            if terms.suits_trigrams() {
+                return Index::Trigram;
+            }
+            if terms.has_cjk() {
+                return Index::Substring;
+            }
+        }
+        Index::Stemmed
+    }
+
+    fn decks(self) -> &'static str {
+        match self {
+            Index::Stemmed => "decks_fts",
+            Index::Trigram | Index::Substring => "decks_trigram",
+        }
+    }
+
+    fn notes(self) -> &'static str {
+        match self {
+            Index::Stemmed => "notes_fts",
+            Index::Trigram | Index::Substring => "notes_trigram",
+        }
+    }
+}
+
+impl Constraints {
+    // the text has already been matched by the constraints, so there's nothing
+    // left to give to an index
+    fn matches_text(&self) -> bool {
+        self.index == Index::Substring
+    }
+
+    // the parameters of a statement that includes the deck or note constraints
+    fn params<'a>(&'a self, params: &[(&'a str, &'a dyn ToSql)]) -> Vec<(&'a str, &'a dyn ToSql)> {
+        let mut params = params.to_vec();
+        for (name, pattern) in &self.likes {
+            params.push((name.as_str(), pattern as &dyn ToSql));
+        }
+        params
+    }
+}
+
+fn constraints(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    terms: &SearchTerms,
+) -> Result<Constraints, DbError> {
+    let mut shared = String::new();
+
+    if !terms.kinds.is_empty() {
+        let kinds: Vec<String> = terms
+            .kinds
+            .iter()
+            .map(|kind| format!("'{}'", kind.singular()))
+            .collect();
+        shared += &format!(" AND d.kind IN ({})", kinds.join(", "));
+    }
+    if let Some(year) = terms.before {
+        shared += &format!(
+            " AND EXISTS (SELECT 1 FROM points fp
+                          WHERE fp.deck_id = d.id
+                                AND coalesce(fp.exact_realdate, fp.lower_realdate) < julianday('{}'))",
+            year_as_date_string(year)
+        );
+    }
+    if let Some(year) = terms.after {
+        shared += &format!(
+            " AND EXISTS (SELECT 1 FROM points fp
+                          WHERE fp.deck_id = d.id
+                                AND coalesce(fp.exact_realdate, fp.upper_realdate) >= julianday('{}'))",
+            year_as_date_string(year)
+        );
+    }
+    if let Some(insignia) = terms.insignia {
+        shared += &format!(" AND (d.insignia & {insignia}) = {insignia}");
+    }
+
+    let mut deck = shared.clone();
+    let mut note = shared;
+
+    // a note is in a tag's results if either it or its deck has been tagged
+    if let Some(tag_id) = terms.tag {
+        deck += &format!(
+            " AND EXISTS (SELECT 1 FROM deck_tags ft WHERE ft.deck_id = d.id AND ft.tag_id = {tag_id})"
+        );
+        note += &format!(
+            " AND (EXISTS (SELECT 1 FROM deck_tags ft WHERE ft.deck_id = n.deck_id AND ft.tag_id = {tag_id})
+                   OR EXISTS (SELECT 1 FROM note_tags ft WHERE ft.note_id = n.id AND ft.tag_id = {tag_id}))"
+        );
+    }
+
+    if let Some(font) = terms.font {
+        deck += &format!(" AND d.font = {}", i32::from(font));
+        note += &format!(" AND n.font = {}", i32::from(font));
+    }
+
+    for name in &terms.refs {
+        let ids: Vec<String> = deck_ids_named(conn, user_id, name)?
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let ids = ids.join(", ");
+
+        deck += &format!(
+            " AND EXISTS (SELECT 1 FROM notes fn JOIN refs fr ON fr.note_id = fn.id
+                          WHERE (fn.deck_id = d.id AND fr.deck_id IN ({ids}))
+                                OR (fr.deck_id = d.id AND fn.deck_id IN ({ids})))"
+        );
+        note += &format!(
+            " AND (n.deck_id IN ({ids})
+                   OR EXISTS (SELECT 1 FROM refs fr WHERE fr.note_id = n.id AND fr.deck_id IN ({ids})))"
+        );
+    }
+
+    let language = db_users::get_search_language(conn, user_id)?;
+    let index = Index::for_search(language, terms);
+
+    let mut likes = vec![];
+    if index == Index::Substring {
+        for (term, excluded) in terms
+            .text
+            .iter()
+            .map(|t| (t, false))
+            .chain(terms.excluded.iter().map(|t| (t, true)))
+        {
+            let Some(pattern) = like_pattern(term) else {
+                continue;
+            };
+            let name = format!(":like{}", likes.len());
+            let not = if excluded { "NOT " } else { "" };
+            deck += &format!(
+                " AND {not}(d.name LIKE {name} ESCAPE '\\'
+                           OR EXISTS (SELECT 1 FROM deck_aliases fa WHERE fa.deck_id = d.id AND fa.name LIKE {name} ESCAPE '\\')
+                           OR EXISTS (SELECT 1 FROM notes fn WHERE fn.deck_id = d.id AND fn.content LIKE {name} ESCAPE '\\'))"
+            );
+            note += &format!(" AND {not}(n.content LIKE {name} ESCAPE '\\')");
+            likes.push((name, pattern));
+        }
+    }
+
+    Ok(Constraints {
+        deck,
+        note,
+        index,
+        likes,
+    })
+}
+
+// a term as a LIKE pattern that matches it anywhere in the text,
+// None for the fts5 operators which have no meaning here
+//
+fn like_pattern(term: &str) -> Option<String> {
+    let term = term.trim_matches(|c| c == '"' || c == '*');
+    if term.is_empty() || matches!(term, "AND" | "OR" | "NOT") {
+        return None;
+    }
+
+    let escaped = term
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    Some(format!("%{escaped}%"))
+}
+
+// the user's decks with the given name or alias
+//
+fn deck_ids_named(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    name: &str,
+) -> Result<Vec<Key>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id
+         FROM decks
+         WHERE user_id = :user_id
+               AND (name = :name COLLATE NOCASE
+                    OR id IN (SELECT deck_id FROM deck_aliases WHERE name = :name COLLATE NOCASE))",
+        named_params! {":user_id": user_id, ":name": name},
+    )
+}
+
+// the note level results are only given with the first page of deck level results
+//
 pub(crate) fn search_at_all_levels(
     conn: &rusqlite::Connection,
     user_id: Key,
     query: String,
+    terms: &SearchTerms,
+    after: Option<&Cursor>,
 ) -> Result<SearchResults, DbError> {
-    let deck_level_results = search_at_deck_level(conn, user_id, query.clone())?;
-    let note_level_results = search_at_note_level(conn, user_id, query.clone())?;
+    let ranking = db_ranking::get_ranking(conn, user_id)?;
+    let searched_at = searched_at(after);
+
+    let deck_level_results = ranked_at_deck_level(conn, user_id, terms, &ranking, searched_at)?;
+    let note_level_results = search_at_note_level(conn, user_id, terms)?;
+    let mut note_level_results =
+        db_ranking::rank_search_decks(conn, &ranking, note_level_results, searched_at)?;
+
+    let fts = terms.fts();
+    if !fts.is_empty() {
+        let constraints = constraints(conn, user_id, terms)?;
+
+        let hits = search_annotations(conn, user_id, fts.clone(), &constraints)?;
+        let mut sourced =
+            sourced_search_decks(hits, SearchSource::Annotation, &note_level_results)?;
+        note_level_results.append(&mut sourced);
+
+        let hits = search_dialogue_messages(conn, user_id, fts, &constraints)?;
+        let mut sourced = sourced_search_decks(hits, SearchSource::Dialogue, &note_level_results)?;
+        note_level_results.append(&mut sourced);
+    }
 
     // dedupe deck_level against note_level
     //
@@ -123,10 +421,25 @@ pub(crate) fn search_at_all_levels(
         .filter(|search_deck| !in_searchdecks(search_deck, &note_level_results))
         .collect();
 
+    let (deck_level, next_cursor) =
+        db_ranking::page_of_search_decks(deduped_deck_level_results, after, searched_at);
+    if after.is_some() {
+        note_level_results = vec![];
+    }
+
+    let did_you_mean = if after.is_none() && deck_level.is_empty() && note_level_results.is_empty()
+    {
+        did_you_mean(conn, user_id, &terms.text.join(" "))?
+    } else {
+        None
+    };
+
     let res = SearchResults {
-        search_text: String::from(query),
-        deck_level: deduped_deck_level_results,
+        search_text: query,
+        deck_level,
         note_level: note_level_results,
+        did_you_mean,
+        next_cursor: next_cursor.map(|cursor| cursor.to_string()),
     };
 
     Ok(res)
@@ -136,84 +449,216 @@ pub(crate) fn search_quotes(
     conn: &rusqlite::Connection,
     user_id: Key,
     query: String,
+    terms: &SearchTerms,
 ) -> Result<SearchResults, DbError> {
-    let q: String = query.clone();
-    let q2: String = q.clone();
-    let note_level_results = search_quotes_at_note_level(conn, user_id, q)?;
+    // re-use the note level search, restricted to quotes
+    //
+    let terms = SearchTerms {
+        kinds: vec![DeckKind::Quote],
+        ..terms.clone()
+    };
+    let note_level_results = search_at_note_level(conn, user_id, &terms)?;
 
     let res = SearchResults {
-        search_text: q2,
+        search_text: query,
         deck_level: vec![],
         note_level: note_level_results,
+        did_you_mean: None,
+        next_cursor: None,
     };
 
     Ok(res)
 }
 
-fn search_quotes_at_note_level(
+// a page of deck level results along with the cursor for the next page
+//
+pub(crate) fn search_at_deck_level(
     conn: &rusqlite::Connection,
     user_id: Key,
-    query: String,
+    terms: &SearchTerms,
+    after: Option<&Cursor>,
+) -> Result<(Vec<SearchDeck>, Option<Cursor>), DbError> {
+    let ranking = db_ranking::get_ranking(conn, user_id)?;
+    let searched_at = searched_at(after);
+
+    let ranked = ranked_at_deck_level(conn, user_id, terms, &ranking, searched_at)?;
+
+    Ok(db_ranking::page_of_search_decks(ranked, after, searched_at))
+}
+
+fn ranked_at_deck_level(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    terms: &SearchTerms,
+    ranking: &Ranking,
+    searched_at: i64,
 ) -> Result<Vec<SearchDeck>, DbError> {
-    // be lazy, re-use the search_query fn and then filter for quotes
-    //
-    let q: String = query.clone();
-    let q2: String = q.clone();
-    let search_deck_note_refs = search_query(conn, user_id, q)?;
+    let constraints = constraints(conn, user_id, terms)?;
+    let fts = terms.fts();
 
-    let mut search_decks = build_search_decks(search_deck_note_refs)?;
+    let results = if fts.is_empty() || constraints.matches_text() {
+        filtered_decks(conn, user_id, terms, &constraints)?
+    } else {
+        search_at_deck_level_base(conn, user_id, fts, false, &constraints, ranking)?
+    };
 
-    let flashcards = db_memorise::all_flashcards_for_search_query(conn, user_id, q2)?;
+    db_ranking::rank_search_decks(conn, ranking, results, searched_at)
+}
 
-    for search_deck in &mut *search_decks {
-        db_notes::assign_flashcards_to_notes(&mut search_deck.notes, &flashcards)?;
+// later pages are ranked as of when the first page was searched for
+//
+fn searched_at(after: Option<&Cursor>) -> i64 {
+    after.map_or_else(
+        || chrono::Utc::now().timestamp(),
+        |cursor| cursor.searched_at,
+    )
+}
+
+// a search that's only made up of filters (and perhaps some excluded text)
+//
+fn filtered_decks(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    terms: &SearchTerms,
+    constraints: &Constraints,
+) -> Result<Vec<SearchDeck>, DbError> {
+    if !terms.has_filters() && !constraints.matches_text() {
+        return Ok(vec![]);
     }
 
-    let search_quotes: Vec<SearchDeck> = search_decks
-        .into_iter()
-        .filter(|search_deck| search_deck.deck.deck_kind == DeckKind::Quote)
-        .collect();
+    let select = "SELECT d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
+                         0 AS rank_sum, 1 AS rank_count
+                  FROM decks d
+                  WHERE d.user_id = :user_id";
+    let order = "ORDER BY d.impact DESC, d.name ASC LIMIT 100";
 
-    Ok(search_quotes)
+    let excluded = if constraints.matches_text() {
+        String::new()
+    } else {
+        terms.excluded_fts()
+    };
+    if excluded.is_empty() {
+        let stmt = format!("{select}{} {order}", constraints.deck);
+        sqlite::many(
+            conn,
+            &stmt,
+            constraints
+                .params(named_params! {":user_id": user_id})
+                .as_slice(),
+        )
+    } else {
+        let stmt = format!(
+            "{select}{} AND d.id NOT IN (SELECT rowid FROM {index} WHERE {index} MATCH :excluded) {order}",
+            constraints.deck,
+            index = constraints.index.decks()
+        );
+        sqlite::many(
+            conn,
+            &stmt,
+            constraints
+                .params(named_params! {":user_id": user_id, ":excluded": excluded})
+                .as_slice(),
+        )
+    }
 }
 
-pub(crate) fn search_at_deck_level(
+// the note level equivalent of filtered_decks
+//
+fn filtered_notes(
     conn: &rusqlite::Connection,
     user_id: Key,
-    query: String,
-) -> Result<Vec<SearchDeck>, DbError> {
-    search_at_deck_level_base(conn, user_id, query, false)
+    terms: &SearchTerms,
+    constraints: &Constraints,
+) -> Result<Vec<SearchDeckNoteRef>, DbError> {
+    if !terms.has_filters() && !constraints.matches_text() {
+        return Ok(vec![]);
+    }
+
+    let select = "SELECT 0 AS rank,
+                       d.id as deck_id, d.name as deck_name, d.kind as deck_kind, d.created_at as deck_created_at, d.graph_terminator as deck_graph_terminator, d.insignia as deck_insignia, d.font as deck_font, d.impact as deck_impact,
+                       n.id as note_id, n.prev_note_id as prev_note_id, n.kind as note_kind,
+                       n.content as content, NULL as snippet,
+                       n.point_id as point_id, n.font as note_font, n.private as note_private, n.version as note_version,
+                       r.deck_id as ref_deck_id, r.kind as ref_kind, r.annotation as ref_annotation,
+
+                       d2.name as d2_name, d2.kind as d2_kind, d2.created_at as d2_created_at,
+                       d2.graph_terminator as d2_graph_terminator, d2.insignia as d2_insignia, d2.font as d2_font, d2.impact as d2_impact
+               FROM notes n
+                    LEFT JOIN decks d ON d.id = n.deck_id
+                    LEFT JOIN dialogue_messages dm ON dm.note_id = n.id
+                    LEFT JOIN refs r on r.note_id = n.id
+                    LEFT JOIN decks d2 on d2.id = r.deck_id
+               WHERE n.id IN (SELECT n.id
+                              FROM notes n LEFT JOIN decks d ON d.id = n.deck_id
+                              WHERE d.user_id = :user_id";
+    let order = "ORDER BY n.id DESC LIMIT 100)
+                     AND (dm.role IS null OR dm.role <> 'system')
+               ORDER BY n.id DESC";
+
+    let excluded = if constraints.matches_text() {
+        String::new()
+    } else {
+        terms.excluded_fts()
+    };
+    if excluded.is_empty() {
+        let stmt = format!("{select}{} {order}", constraints.note);
+        sqlite::many(
+            conn,
+            &stmt,
+            constraints
+                .params(named_params! {":user_id": user_id})
+                .as_slice(),
+        )
+    } else {
+        let stmt = format!(
+            "{select}{} AND n.id NOT IN (SELECT rowid FROM {index} WHERE {index} MATCH :excluded) {order}",
+            constraints.note,
+            index = constraints.index.notes()
+        );
+        sqlite::many(
+            conn,
+            &stmt,
+            constraints
+                .params(named_params! {":user_id": user_id, ":excluded": excluded})
+                .as_slice(),
+        )
+    }
 }
 
 pub(crate) fn search_names_at_deck_level(
     conn: &rusqlite::Connection,
     user_id: Key,
     query: String,
+    tag_id: Option<Key>,
 ) -> Result<Vec<SearchDeck>, DbError> {
-    let stmt = "select d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
+    let stmt = format!("select d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
                        decks_fts.rank AS rank_sum, 1 as rank_count
                 from decks_fts left join decks d on d.id = decks_fts.rowid
                 where decks_fts match :query
                       and d.user_id = :user_id
+                      and {DECK_TAG_FILTER}
                 group by d.id
                 order by rank_sum asc, length(d.name) asc
-                limit 20";
+                limit 20");
     let mut results: Vec<SearchDeck> = sqlite::many(
         &conn,
-        stmt,
-        named_params! {":user_id": user_id, ":query": query},
+        &stmt,
+        named_params! {":user_id": user_id, ":query": query, ":tag_id": tag_id},
     )?;
 
-    let stmt =
-        "select id, name, kind, created_at, graph_terminator, insignia, font, impact, 0 as rank_sum, 1 as rank_count
-                from decks
-                where name like '%' || :query || '%'
-                and user_id = :user_id
-                limit 20";
+    let stmt = format!(
+        "select d.id, d.name, d.kind, d.created_at, d.graph_terminator, d.insignia, d.font, d.impact, 0 as rank_sum, 1 as rank_count
+                from decks d
+                where (d.name like '%' || :query || '%'
+                       or d.id in (select deck_id from deck_aliases where name like '%' || :query || '%'))
+                and d.user_id = :user_id
+                and {DECK_TAG_FILTER}
+                limit 20"
+    );
     let res2: Vec<SearchDeck> = sqlite::many(
         &conn,
-        stmt,
-        named_params! {":user_id": user_id, ":query": query},
+        &stmt,
+        named_params! {":user_id": user_id, ":query": query, ":tag_id": tag_id},
     )?;
 
     for r in res2 {
@@ -225,6 +670,141 @@ pub(crate) fn search_names_at_deck_level(
     Ok(results)
 }
 
+// the smallest similarity between a query and a deck name for the name to be suggested
+//
+const FUZZY_THRESHOLD: f32 = 0.7;
+
+struct FuzzyCandidate {
+    search_deck: SearchDeck,
+    aliases: Option<String>,
+}
+
+impl FromRow for FuzzyCandidate {
+    fn from_row(row: &Row) -> rusqlite::Result<FuzzyCandidate> {
+        Ok(FuzzyCandidate {
+            search_deck: SearchDeck::from_row(row)?,
+            aliases: row.get("aliases")?,
+        })
+    }
+}
+
+// typo tolerant search of deck names and aliases, e.g. "Nietsche" finds "Friedrich Nietzsche".
+// Candidates share at least one trigram with the query, they're then ranked by
+// their edit distance to it. The rank is the negated similarity so that, as with
+// fts5, lower is better
+//
+pub(crate) fn fuzzy_search_names_at_deck_level(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    query: &str,
+    tag_id: Option<Key>,
+) -> Result<Vec<SearchDeck>, DbError> {
+    let query = query.trim().to_lowercase();
+    let chars: Vec<char> = query.chars().collect();
+    if chars.len() < 3 {
+        return Ok(vec![]);
+    }
+
+    let mut trigrams: Vec<String> = chars
+        .windows(3)
+        .map(|w| format!("\"{}\"", String::from_iter(w).replace('"', "\"\"")))
+        .collect();
+    trigrams.sort();
+    trigrams.dedup();
+
+    let stmt = format!("SELECT d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
+                       decks_trigram.rank AS rank_sum, 1 as rank_count, decks_trigram.aliases AS aliases
+                FROM decks_trigram LEFT JOIN decks d ON d.id = decks_trigram.rowid
+                WHERE decks_trigram MATCH :query
+                      AND d.user_id = :user_id
+                      AND {DECK_TAG_FILTER}
+                ORDER BY decks_trigram.rank ASC
+                LIMIT 100");
+    let candidates: Vec<FuzzyCandidate> = sqlite::many(
+        conn,
+        &stmt,
+        named_params! {":user_id": user_id, ":query": trigrams.join(" OR "), ":tag_id": tag_id},
+    )?;
+
+    let mut results: Vec<SearchDeck> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let mut search_deck = candidate.search_deck;
+            let score = similarity(&query, &search_deck.deck.title).max(
+                candidate
+                    .aliases
+                    .map_or(0.0, |aliases| similarity(&query, &aliases)),
+            );
+            search_deck.rank = -score;
+            (score >= FUZZY_THRESHOLD).then_some(search_deck)
+        })
+        .collect();
+
+    results.sort_by(|a, b| {
+        a.rank
+            .total_cmp(&b.rank)
+            .then(a.deck.title.len().cmp(&b.deck.title.len()))
+    });
+    results.truncate(10);
+
+    Ok(results)
+}
+
+// the name of the deck that best matches a misspelt query
+//
+pub(crate) fn did_you_mean(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    query: &str,
+) -> Result<Option<String>, DbError> {
+    let suggestions = fuzzy_search_names_at_deck_level(conn, user_id, query, None)?;
+    Ok(suggestions
+        .into_iter()
+        .map(|search_deck| search_deck.deck.title)
+        .find(|title| !title.eq_ignore_ascii_case(query.trim())))
+}
+
+// 1.0 for an exact match down to 0.0 for nothing in common. The query is compared
+// against the whole of the text and against each run of the text's words that is as
+// long as the query, so a surname can match a full name
+//
+fn similarity(query: &str, text: &str) -> f32 {
+    let text = text.to_lowercase();
+    let query_len = query.split_whitespace().count().max(1);
+    let words: Vec<&str> = text.split_whitespace().collect();
+
+    let score = |candidate: &str| {
+        let longest = query.chars().count().max(candidate.chars().count());
+        if longest == 0 {
+            0.0
+        } else {
+            1.0 - levenshtein(query, candidate) as f32 / longest as f32
+        }
+    };
+
+    words
+        .windows(query_len)
+        .map(|window| score(&window.join(" ")))
+        .fold(score(&text), f32::max)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
 pub(crate) fn additional_search_at_deck_level(
     conn: &rusqlite::Connection,
     user_id: Key,
@@ -240,6 +820,8 @@ pub(crate) fn additional_search_at_deck_level(
             search_text: name,
             deck_level: vec![],
             note_level: vec![],
+            did_you_mean: None,
+            next_cursor: None,
         });
     }
 
@@ -247,7 +829,15 @@ pub(crate) fn additional_search_at_deck_level(
     //
     let arrivals = db_notes::arrivals_for_deck(conn, deck_id)?;
 
-    let deck_level_results = search_at_deck_level_base(conn, user_id, sane_name, true)?;
+    let ranking = db_ranking::get_ranking(conn, user_id)?;
+    let deck_level_results = search_at_deck_level_base(
+        conn,
+        user_id,
+        sane_name,
+        true,
+        &Constraints::default(),
+        &ranking,
+    )?;
 
     // dedupe deck_level_results against the arrivals
     let deck_level_results: Vec<SearchDeck> = deck_level_results
@@ -275,6 +865,8 @@ pub(crate) fn additional_search_at_deck_level(
         search_text: name,
         deck_level: deduped_deck_level_results,
         note_level: note_level_results,
+        did_you_mean: None,
+        next_cursor: None,
     };
 
     Ok(res)
@@ -359,6 +951,9 @@ fn build_search_decks(
                 rank: sdnr.rank,
                 deck: sdnr.deck,
                 notes: vec![],
+                foreign: None,
+                snippet: None,
+                source: None,
             });
         }
 
@@ -386,13 +981,36 @@ fn build_search_decks(
 pub(crate) fn search_at_note_level(
     conn: &rusqlite::Connection,
     user_id: Key,
-    query: String,
+    terms: &SearchTerms,
 ) -> Result<Vec<SearchDeck>, DbError> {
-    let search_deck_note_refs = search_query(conn, user_id, query.clone())?;
+    let constraints = constraints(conn, user_id, terms)?;
+    let fts = terms.fts();
+
+    let (search_deck_note_refs, flashcards) = if fts.is_empty() || constraints.matches_text() {
+        let search_deck_note_refs = filtered_notes(conn, user_id, terms, &constraints)?;
+        let mut flashcards: Vec<FlashCard> = vec![];
+        for sdnr in &search_deck_note_refs {
+            if !flashcards.iter().any(|f| f.note_id == sdnr.note.id) {
+                flashcards.append(&mut db_memorise::all_flashcards_for_note(
+                    conn,
+                    sdnr.note.id,
+                )?);
+            }
+        }
+        (search_deck_note_refs, flashcards)
+    } else {
+        (
+            search_query(conn, user_id, fts.clone(), &constraints)?,
+            db_memorise::all_flashcards_for_search_query(
+                conn,
+                user_id,
+                fts,
+                constraints.index.notes(),
+            )?,
+        )
+    };
     let mut search_decks = build_search_decks(search_deck_note_refs)?;
 
-    let flashcards = db_memorise::all_flashcards_for_search_query(conn, user_id, query.clone())?;
-
     for search_deck in &mut *search_decks {
         db_notes::assign_flashcards_to_notes(&mut search_deck.notes, &flashcards)?;
     }
@@ -416,6 +1034,9 @@ fn remove_notes_with_refs_to_deck_id(search_deck: SearchDeck, deck_id: Key) -> S
             .into_iter()
             .filter(|note| !has_ref_to_deck_id(note, deck_id))
             .collect(),
+        foreign: search_deck.foreign,
+        snippet: search_deck.snippet,
+        source: search_deck.source,
     }
 }
 
@@ -423,34 +1044,162 @@ fn search_query(
     conn: &rusqlite::Connection,
     user_id: Key,
     query: String,
+    constraints: &Constraints,
 ) -> Result<Vec<SearchDeckNoteRef>, DbError> {
-    let stmt = "SELECT notes_fts.rank AS rank,
+    let index = constraints.index.notes();
+    let stmt = format!("SELECT {index}.rank AS rank,
                        d.id as deck_id, d.name as deck_name, d.kind as deck_kind, d.created_at as deck_created_at, d.graph_terminator as deck_graph_terminator, d.insignia as deck_insignia, d.font as deck_font, d.impact as deck_impact,
                        n.id as note_id, n.prev_note_id as prev_note_id, n.kind as note_kind,
-                       highlight(notes_fts, 0, ':searched(', ')') as content,
-                       n.point_id as point_id, n.font as note_font,
+                       highlight({index}, 0, ':searched(', ')') as content,
+                       snippet({index}, 0, ':searched(', ')', '…', 24) as snippet,
+                       n.point_id as point_id, n.font as note_font, n.private as note_private, n.version as note_version,
                        r.deck_id as ref_deck_id, r.kind as ref_kind, r.annotation as ref_annotation,
 
                        d2.name as d2_name, d2.kind as d2_kind, d2.created_at as d2_created_at,
                        d2.graph_terminator as d2_graph_terminator, d2.insignia as d2_insignia, d2.font as d2_font, d2.impact as d2_impact
-               FROM notes_fts
-                    LEFT JOIN notes n ON n.id = notes_fts.rowid
+               FROM {index}
+                    LEFT JOIN notes n ON n.id = {index}.rowid
                     LEFT JOIN decks d ON d.id = n.deck_id
                     LEFT JOIN dialogue_messages dm ON dm.note_id = n.id
                     LEFT JOIN refs r on r.note_id = n.id
                     LEFT JOIN decks d2 on d2.id = r.deck_id
-               WHERE notes_fts match :query
-                     AND d.user_id = :user_id
+               WHERE {index} match :query
+                     AND d.user_id = :user_id{note}
                      AND (dm.role IS null OR dm.role <> 'system')
                ORDER BY rank ASC
-               LIMIT 100";
+               LIMIT 100",
+        note = constraints.note
+    );
     sqlite::many(
-        &conn,
-        stmt,
-        named_params! {":user_id": user_id, ":query": query},
+        conn,
+        &stmt,
+        constraints
+            .params(named_params! {":user_id": user_id, ":query": query})
+            .as_slice(),
+    )
+}
+
+// notes with a ref whose annotation matches the query
+//
+fn search_annotations(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    query: String,
+    constraints: &Constraints,
+) -> Result<Vec<SourceHit>, DbError> {
+    let stmt = format!("SELECT refs_fts.rank AS rank,
+                       d.id as deck_id, d.name as deck_name, d.kind as deck_kind, d.created_at as deck_created_at, d.graph_terminator as deck_graph_terminator, d.insignia as deck_insignia, d.font as deck_font, d.impact as deck_impact,
+                       n.id as note_id, n.prev_note_id as prev_note_id, n.kind as note_kind,
+                       n.content as content, NULL as snippet,
+                       snippet(refs_fts, 0, ':searched(', ')', '…', 24) as source_snippet,
+                       n.point_id as point_id, n.font as note_font, n.private as note_private, n.version as note_version,
+                       r.deck_id as ref_deck_id, r.kind as ref_kind, r.annotation as ref_annotation,
+
+                       d2.name as d2_name, d2.kind as d2_kind, d2.created_at as d2_created_at,
+                       d2.graph_terminator as d2_graph_terminator, d2.insignia as d2_insignia, d2.font as d2_font, d2.impact as d2_impact
+               FROM refs_fts
+                    JOIN refs_fts_keys k ON k.id = refs_fts.rowid
+                    JOIN notes n ON n.id = k.note_id
+                    JOIN decks d ON d.id = n.deck_id
+                    LEFT JOIN refs r on r.note_id = n.id
+                    LEFT JOIN decks d2 on d2.id = r.deck_id
+               WHERE refs_fts match :query
+                     AND d.user_id = :user_id{note}
+               ORDER BY rank ASC
+               LIMIT 100",
+        note = constraints.note
+    );
+    sqlite::many(
+        conn,
+        &stmt,
+        constraints
+            .params(named_params! {":user_id": user_id, ":query": query})
+            .as_slice(),
     )
 }
 
+// notes whose original dialogue message matches the query, the copy in the note
+// may since have been edited
+//
+fn search_dialogue_messages(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    query: String,
+    constraints: &Constraints,
+) -> Result<Vec<SourceHit>, DbError> {
+    let stmt = format!("SELECT dialogue_messages_fts.rank AS rank,
+                       d.id as deck_id, d.name as deck_name, d.kind as deck_kind, d.created_at as deck_created_at, d.graph_terminator as deck_graph_terminator, d.insignia as deck_insignia, d.font as deck_font, d.impact as deck_impact,
+                       n.id as note_id, n.prev_note_id as prev_note_id, n.kind as note_kind,
+                       n.content as content, NULL as snippet,
+                       snippet(dialogue_messages_fts, 0, ':searched(', ')', '…', 24) as source_snippet,
+                       n.point_id as point_id, n.font as note_font, n.private as note_private, n.version as note_version,
+                       r.deck_id as ref_deck_id, r.kind as ref_kind, r.annotation as ref_annotation,
+
+                       d2.name as d2_name, d2.kind as d2_kind, d2.created_at as d2_created_at,
+                       d2.graph_terminator as d2_graph_terminator, d2.insignia as d2_insignia, d2.font as d2_font, d2.impact as d2_impact
+               FROM dialogue_messages_fts
+                    JOIN dialogue_messages dm ON dm.id = dialogue_messages_fts.rowid
+                    JOIN notes n ON n.id = dm.note_id
+                    JOIN decks d ON d.id = n.deck_id
+                    LEFT JOIN refs r on r.note_id = n.id
+                    LEFT JOIN decks d2 on d2.id = r.deck_id
+               WHERE dialogue_messages_fts match :query
+                     AND d.user_id = :user_id{note}
+                     AND dm.role <> 'system'
+               ORDER BY rank ASC
+               LIMIT 100",
+        note = constraints.note
+    );
+    sqlite::many(
+        conn,
+        &stmt,
+        constraints
+            .params(named_params! {":user_id": user_id, ":query": query})
+            .as_slice(),
+    )
+}
+
+// group the hits into decks labelled with where they were found, ignoring any
+// note that's already in the results
+//
+fn sourced_search_decks(
+    hits: Vec<SourceHit>,
+    source: SearchSource,
+    existing: &[SearchDeck],
+) -> Result<Vec<SearchDeck>, DbError> {
+    let already_found = |note_id: Key| {
+        existing
+            .iter()
+            .any(|search_deck| search_deck.notes.iter().any(|note| note.id == note_id))
+    };
+
+    let mut snippets: Vec<(Key, String)> = vec![];
+    let mut search_deck_note_refs: Vec<SearchDeckNoteRef> = vec![];
+    for hit in hits {
+        if already_found(hit.search_deck_note_ref.note.id) {
+            continue;
+        }
+        let deck_id = hit.search_deck_note_ref.deck.id;
+        if let Some(snippet) = hit.snippet
+            && !snippets.iter().any(|(id, _)| *id == deck_id)
+        {
+            snippets.push((deck_id, snippet));
+        }
+        search_deck_note_refs.push(hit.search_deck_note_ref);
+    }
+
+    let mut search_decks = build_search_decks(search_deck_note_refs)?;
+    for search_deck in &mut search_decks {
+        search_deck.source = Some(source);
+        search_deck.snippet = snippets
+            .iter()
+            .find(|(id, _)| *id == search_deck.deck.id)
+            .map(|(_, snippet)| snippet.clone());
+    }
+
+    Ok(search_decks)
+}
+
 // only searching via notes for the moment, will have to add additional search queries that look in points, article_extras etc
 //
 fn search_deck_additional_query(
@@ -470,7 +1219,8 @@ fn search_deck_additional_query(
                        d.id as deck_id, d.name as deck_name, d.kind as deck_kind, d.created_at as deck_created_at, d.graph_terminator as deck_graph_terminator, d.insignia as deck_insignia, d.font as deck_font, d.impact as deck_impact,
                        n.id as note_id, n.prev_note_id as prev_note_id, n.kind as note_kind,
                        highlight(notes_fts, 0, ':searched(', ')') as content,
-                       n.point_id as point_id, n.font as note_font,
+                       snippet(notes_fts, 0, ':searched(', ')', '…', 24) as snippet,
+                       n.point_id as point_id, n.font as note_font, n.private as note_private, n.version as note_version,
                        r.deck_id as ref_deck_id, r.kind as ref_kind, r.annotation as ref_annotation,
 
                        d2.name as d2_name, d2.kind as d2_kind, d2.created_at as d2_created_at,
@@ -500,55 +1250,73 @@ fn search_at_deck_level_base(
     user_id: Key,
     query: String,
     ignore_notes: bool,
+    constraints: &Constraints,
+    ranking: &Ranking,
 ) -> Result<Vec<SearchDeck>, DbError> {
     let q = query;
-
-    let stmt = "SELECT d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
-                decks_fts.rank AS rank_sum, 1 AS rank_count
-         FROM decks_fts LEFT JOIN decks d ON d.id = decks_fts.rowid
-         WHERE decks_fts MATCH :query
-               AND d.user_id = :user_id
+    let decks_index = constraints.index.decks();
+    let notes_index = constraints.index.notes();
+    let deck = &constraints.deck;
+
+    let stmt = format!("SELECT d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
+                {decks_index}.rank AS rank_sum, 1 AS rank_count
+         FROM {decks_index} LEFT JOIN decks d ON d.id = {decks_index}.rowid
+         WHERE {decks_index} MATCH :query
+               AND {decks_index}.rank MATCH :rank
+               AND d.user_id = :user_id{deck}
          GROUP BY d.id
          ORDER BY rank_sum ASC, length(d.name) ASC, d.created_at DESC
-         LIMIT 30";
+         LIMIT {CANDIDATES}");
     let mut results: Vec<SearchDeck> = sqlite::many(
-        &conn,
-        stmt,
-        named_params! {":user_id": user_id, ":query": q},
+        conn,
+        &stmt,
+        constraints
+            .params(named_params! {":user_id": user_id, ":query": q, ":rank": db_ranking::decks_fts_rank(ranking)})
+            .as_slice(),
     )?;
 
-    let stmt = "select d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
+    let stmt = format!("select d.id as id, d.name as name, d.kind as kind, d.created_at as created_at, d.graph_terminator as graph_terminator, d.insignia as insignia, d.font as font, d.impact as impact,
                        article_extras_fts.rank AS rank_sum, 1 as rank_count
                 from article_extras_fts left join decks d on d.id = article_extras_fts.rowid
                 where article_extras_fts match :query
-                      and d.user_id = :user_id
+                      and article_extras_fts.rank match :rank
+                      and d.user_id = :user_id{deck}
                 group by d.id
                 order by rank_sum asc, length(d.name) asc
-                limit 30";
+                limit {CANDIDATES}");
     let results_via_pub_ext: Vec<SearchDeck> = sqlite::many(
-        &conn,
-        stmt,
-        named_params! {":user_id": user_id, ":query": q},
+        conn,
+        &stmt,
+        constraints
+            .params(named_params! {":user_id": user_id, ":query": q, ":rank": db_ranking::body_fts_rank(ranking, 3)})
+            .as_slice(),
     )?;
 
-    let stmt = "select res.id as id, res.name as name, res.kind as kind, res.created_at as created_at, res.graph_terminator as graph_terminator, res.insignia as insignia, res.font as font, res.impact as impact,
-                       sum(res.rank) as rank_sum, count(res.rank) as rank_count
+    // snippet() can't be used in an aggregate query, so the matching points are
+    // materialized first
+    let stmt = format!("with hits as materialized (
+                    select p.deck_id as deck_id, points_fts.rank as rank,
+                           snippet(points_fts, -1, ':searched(', ')', '…', 12) as snippet
+                    from points_fts left join points p on p.id = points_fts.rowid
+                    where points_fts match :query and points_fts.rank match :rank)
+                select res.id as id, res.name as name, res.kind as kind, res.created_at as created_at, res.graph_terminator as graph_terminator, res.insignia as insignia, res.font as font, res.impact as impact,
+                       sum(res.rank) as rank_sum, count(res.rank) as rank_count, res.snippet as snippet
                 from (select d.id, d.name, d.kind, d.created_at, d.graph_terminator, d.insignia, d.font,
-                             d.impact, points_fts.rank AS rank
-                      from points_fts
-                           left join points n on n.id = points_fts.rowid
-                           left join decks d on d.id = n.deck_id
-                      where points_fts match :query
-                            and d.user_id = :user_id
+                             d.impact, min(hits.rank) AS rank, hits.snippet AS snippet
+                      from hits
+                           left join decks d on d.id = hits.deck_id
+                      where d.user_id = :user_id{deck}
                       group by d.id
                       order by rank asc) res
                 group by res.id, res.kind, res.name
                 order by sum(res.rank) asc, length(res.name) asc
-                limit 30";
-    let results_via_points: Vec<SearchDeck> = sqlite::many(
-        &conn,
-        stmt,
-        named_params! {":user_id": user_id, ":query": q},
+                limit {CANDIDATES}");
+    let results_via_points: Vec<PointHit> = sqlite::many(
+        conn,
+        &stmt,
+        constraints
+            .params(named_params! {":user_id": user_id, ":query": q, ":rank": db_ranking::points_fts_rank(ranking)})
+            .as_slice(),
     )?;
 
     for r in results_via_pub_ext {
@@ -558,25 +1326,28 @@ fn search_at_deck_level_base(
     }
 
     if !ignore_notes {
-        let stmt = "select res.id as id, res.name as name, res.kind as kind, res.created_at as created_at, res.graph_terminator as graph_terminator, res.insignia as insignia, res.font as font, res.impact as impact,
+        let stmt = format!("select res.id as id, res.name as name, res.kind as kind, res.created_at as created_at, res.graph_terminator as graph_terminator, res.insignia as insignia, res.font as font, res.impact as impact,
                            sum(res.rank) as rank_sum, count(res.rank) as rank_count
-                from (select d.id, d.name, d.kind, d.created_at, d.graph_terminator, d.insignia, d.font, d.impact, notes_fts.rank AS rank
-                      from notes_fts
-                           left join notes n on n.id = notes_fts.rowid
+                from (select d.id, d.name, d.kind, d.created_at, d.graph_terminator, d.insignia, d.font, d.impact, {notes_index}.rank AS rank
+                      from {notes_index}
+                           left join notes n on n.id = {notes_index}.rowid
                            left join decks d on d.id = n.deck_id
                            left join dialogue_messages dm on dm.note_id = n.id
-                      where notes_fts match :query
-                            and d.user_id = :user_id
+                      where {notes_index} match :query
+                            and {notes_index}.rank match :rank
+                            and d.user_id = :user_id{deck}
                             and (dm.role is null or dm.role <> 'system')
                       group by d.id
                       order by rank asc) res
                 group by res.id, res.kind, res.name
                 order by sum(res.rank) asc, length(res.name) asc
-                limit 30";
+                limit {CANDIDATES}");
         let results_via_notes: Vec<SearchDeck> = sqlite::many(
-            &conn,
-            stmt,
-            named_params! {":user_id": user_id, ":query": q},
+            conn,
+            &stmt,
+            constraints
+                .params(named_params! {":user_id": user_id, ":query": q, ":rank": db_ranking::body_fts_rank(ranking, 1)})
+                .as_slice(),
         )?;
         for r in results_via_notes {
             if !contains(&results, r.deck.id) {
@@ -585,11 +1356,255 @@ fn search_at_deck_level_base(
         }
     }
 
-    for r in results_via_points {
-        if !contains(&results, r.deck.id) {
-            results.push(r);
+    for hit in results_via_points {
+        let snippet = hit
+            .snippet
+            .filter(|snippet| civil_shared::markup_as_ast(snippet).is_ok());
+        match results
+            .iter_mut()
+            .find(|r| r.deck.id == hit.search_deck.deck.id)
+        {
+            Some(existing) => existing.snippet = existing.snippet.take().or(snippet),
+            None => results.push(SearchDeck {
+                snippet,
+                ..hit.search_deck
+            }),
         }
     }
 
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::aliases;
+    use crate::db::test_util::{OWNER, RECIPIENT, add_note, add_ref, create_deck, test_db};
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("kant", ""), 4);
+        assert_eq!(levenshtein("nietsche", "nietzsche"), 1);
+        assert_eq!(levenshtein("dostoevsky", "dostoyevsky"), 1);
+        assert_eq!(levenshtein("hume", "hegel"), 3);
+    }
+
+    #[test]
+    fn test_similarity() {
+        // a misspelt surname matches the surname within the full name
+        assert!(similarity("nietsche", "Friedrich Nietzsche") >= FUZZY_THRESHOLD);
+        assert!(similarity("dostoevsky", "Fyodor Dostoyevsky") >= FUZZY_THRESHOLD);
+        assert!(similarity("dostoyevsky", "Dostoevsky") >= FUZZY_THRESHOLD);
+
+        assert_eq!(similarity("marcus aurelius", "Marcus Aurelius"), 1.0);
+        assert!(similarity("seneca", "Friedrich Nietzsche") < FUZZY_THRESHOLD);
+        assert_eq!(similarity("", ""), 0.0);
+    }
+
+    #[test]
+    fn test_fuzzy_deck_names() {
+        let db = test_db("fuzzy-deck-names");
+        let nietzsche = create_deck(&db.conn, "person", "Friedrich Nietzsche");
+        let dostoevsky = create_deck(&db.conn, "person", "Fyodor Dostoevsky");
+        create_deck(&db.conn, "idea", "Eternal Recurrence");
+        aliases::create_alias(&db.conn, OWNER, dostoevsky, "The Gambler's Author").unwrap();
+
+        let titles = |q: &str| -> Vec<Key> {
+            fuzzy_search_names_at_deck_level(&db.conn, OWNER, q, None)
+                .unwrap()
+                .into_iter()
+                .map(|search_deck| search_deck.deck.id)
+                .collect()
+        };
+
+        assert_eq!(titles("Nietsche"), vec![nietzsche]);
+        assert_eq!(titles("Dostoyevsky"), vec![dostoevsky]);
+        // matched by its alias rather than its name
+        assert_eq!(titles("gamblers author"), vec![dostoevsky]);
+        // too short to have a trigram
+        assert!(titles("Ni").is_empty());
+
+        assert_eq!(
+            did_you_mean(&db.conn, OWNER, "Nietsche").unwrap(),
+            Some("Friedrich Nietzsche".to_string())
+        );
+        // another user's decks are never suggested
+        assert!(
+            fuzzy_search_names_at_deck_level(&db.conn, RECIPIENT, "Nietsche", None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_index_for_search() {
+        let terms = |q: &str| -> SearchTerms { q.parse().unwrap() };
+
+        assert_eq!(
+            Index::for_search(Language::English, &terms("stoicism")),
+            Index::Stemmed
+        );
+        assert_eq!(
+            Index::for_search(Language::German, &terms("Stoizismus")),
+            Index::Trigram
+        );
+        // too short for trigrams, the stemmed index is still better than nothing
+        assert_eq!(
+            Index::for_search(Language::German, &terms("zu")),
+            Index::Stemmed
+        );
+
+        assert_eq!(
+            Index::for_search(Language::English, &terms("東京都")),
+            Index::Trigram
+        );
+        assert_eq!(
+            Index::for_search(Language::Japanese, &terms("東京")),
+            Index::Substring
+        );
+        assert_eq!(
+            Index::for_search(Language::English, &terms("哲学")),
+            Index::Substring
+        );
+        assert_eq!(
+            Index::for_search(Language::Chinese, &terms("日本 -東京")),
+            Index::Substring
+        );
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!(like_pattern("東京").as_deref(), Some("%東京%"));
+        assert_eq!(like_pattern("\"東京\"").as_deref(), Some("%東京%"));
+        assert_eq!(
+            like_pattern("it's 50%_").as_deref(),
+            Some("%it's 50\\%\\_%")
+        );
+        assert_eq!(like_pattern("OR"), None);
+        assert_eq!(like_pattern("\"\""), None);
+    }
+
+    #[test]
+    fn test_short_cjk_search() {
+        let mut db = test_db("short-cjk-search");
+        let tokyo = create_deck(&db.conn, "idea", "東京");
+        let philosophy = create_deck(&db.conn, "idea", "Philosophy");
+        let note_id = add_note(&mut db.conn, philosophy, "日本の哲学について");
+        add_note(&mut db.conn, tokyo, "the capital");
+
+        let search = |conn: &Connection, q: &str| {
+            let terms: SearchTerms = q.parse().unwrap();
+            let (decks, _) = search_at_deck_level(conn, OWNER, &terms, None).unwrap();
+            let notes = search_at_note_level(conn, OWNER, &terms).unwrap();
+            let decks: Vec<Key> = decks.into_iter().map(|sd| sd.deck.id).collect();
+            let notes: Vec<Key> = notes
+                .into_iter()
+                .flat_map(|sd| sd.notes)
+                .map(|n| n.id)
+                .collect();
+            (decks, notes)
+        };
+
+        assert_eq!(search(&db.conn, "東京"), (vec![tokyo], vec![]));
+        assert_eq!(search(&db.conn, "哲学"), (vec![philosophy], vec![note_id]));
+        assert_eq!(search(&db.conn, "日本 -哲学"), (vec![], vec![]));
+
+        // the patterns are bound rather than quoted into the statement
+        let quoted = create_deck(&db.conn, "idea", "東'京");
+        assert_eq!(search(&db.conn, "東'"), (vec![quoted], vec![]));
+
+        // only the user's own notes are searched
+        let terms: SearchTerms = "哲学".parse().unwrap();
+        assert!(
+            search_at_note_level(&db.conn, RECIPIENT, &terms)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_annotations_index() {
+        let mut db = test_db("annotations-index");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let other_id = create_deck(&db.conn, "person", "Seneca");
+        let note_id = add_note(&mut db.conn, deck_id, "on the shortness of life");
+        add_ref(&db.conn, note_id, other_id);
+
+        let matching = |conn: &Connection, q: &str| -> Vec<Key> {
+            sqlite::many(
+                conn,
+                "SELECT k.note_id
+                 FROM refs_fts JOIN refs_fts_keys k ON k.id = refs_fts.rowid
+                 WHERE refs_fts MATCH :q",
+                named_params! {":q": q},
+            )
+            .unwrap()
+        };
+        let integrity = |conn: &Connection| {
+            conn.execute(
+                "INSERT INTO refs_fts(refs_fts, rank) VALUES('integrity-check', 1)",
+                [],
+            )
+            .unwrap();
+        };
+
+        db.conn
+            .execute(
+                "UPDATE refs SET annotation = 'letters to lucilius' WHERE note_id = ?1",
+                [note_id],
+            )
+            .unwrap();
+        assert_eq!(matching(&db.conn, "lucilius"), vec![note_id]);
+        integrity(&db.conn);
+
+        db.conn
+            .execute(
+                "UPDATE refs SET annotation = 'moral essays' WHERE note_id = ?1",
+                [note_id],
+            )
+            .unwrap();
+        assert!(matching(&db.conn, "lucilius").is_empty());
+        assert_eq!(matching(&db.conn, "essays"), vec![note_id]);
+        integrity(&db.conn);
+
+        db.conn
+            .execute("DELETE FROM refs WHERE note_id = ?1", [note_id])
+            .unwrap();
+        assert!(matching(&db.conn, "essays").is_empty());
+        integrity(&db.conn);
+
+        let keys: i64 = sqlite::one(&db.conn, "SELECT count(*) FROM refs_fts_keys", []).unwrap();
+        assert_eq!(keys, 0);
+    }
+
+    #[test]
+    fn test_note_snippets() {
+        let mut db = test_db("note-snippets");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let filler = "a long passage of words that goes on well past the length of a snippet";
+        let long = add_note(
+            &mut db.conn,
+            deck_id,
+            &format!("{filler}, then mentions ataraxia once, and {filler} again"),
+        );
+        let short = add_note(&mut db.conn, deck_id, "ataraxia is tranquillity");
+
+        let terms: SearchTerms = "ataraxia".parse().unwrap();
+        let notes: Vec<Note> = search_at_note_level(&db.conn, OWNER, &terms)
+            .unwrap()
+            .into_iter()
+            .flat_map(|sd| sd.notes)
+            .collect();
+        assert_eq!(notes.len(), 2);
+
+        let long = notes.iter().find(|n| n.id == long).unwrap();
+        let snippet = long.snippet.as_ref().expect("a long note has a snippet");
+        assert!(snippet.contains(":searched(ataraxia)"));
+        assert!(snippet.len() < long.content.len());
+        assert!(civil_shared::markup_as_ast(snippet).is_ok());
+
+        let short = notes.iter().find(|n| n.id == short).unwrap();
+        assert!(short.snippet.is_none());
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
+use crate::db::notes as notes_db;
+use crate::db::points as points_db;
+use crate::db::qry::Qry;
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::decks::SlimDeck;
+use crate::interop::share_links::{ProtoShareLink, PublicDeck, ShareLink};
+use rusqlite::{Row, named_params};
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+impl FromRow for ShareLink {
+    fn from_row(row: &Row) -> rusqlite::Result<ShareLink> {
+        Ok(ShareLink {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            token: row.get("token")?,
+            created_at: row.get("created_at")?,
+            expires_at: row.get("expires_at")?,
+            revoked: row.get("revoked")?,
+            include_arrivals: row.get("include_arrivals")?,
+            view_count: row.get("view_count")?,
+            last_viewed_at: row.get("last_viewed_at")?,
+        })
+    }
+}
+
+struct ValidLink {
+    id: Key,
+    user_id: Key,
+    deck_id: Key,
+    include_arrivals: bool,
+}
+
+impl FromRow for ValidLink {
+    fn from_row(row: &Row) -> rusqlite::Result<ValidLink> {
+        Ok(ValidLink {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            deck_id: row.get("deck_id")?,
+            include_arrivals: row.get("include_arrivals")?,
+        })
+    }
+}
+
+pub(crate) fn get_share_links(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Vec<ShareLink>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id, deck_id, token, created_at, expires_at, revoked, include_arrivals, view_count, last_viewed_at
+         FROM share_links
+         WHERE user_id = :user_id AND deck_id = :deck_id
+         ORDER BY id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )
+}
+
+pub(crate) fn create_share_link(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    proto_share_link: ProtoShareLink,
+    token: String,
+) -> Result<Vec<ShareLink>, DbError> {
+    sqlite::zero(
+        conn,
+        "INSERT INTO share_links(user_id, deck_id, token, expires_at, include_arrivals)
+         SELECT user_id, id, :token, :expires_at, :include_arrivals
+         FROM decks
+         WHERE user_id = :user_id AND id = :deck_id",
+        named_params! {
+            ":user_id": user_id,
+            ":deck_id": deck_id,
+            ":token": token,
+            ":expires_at": proto_share_link.expires_at,
+            ":include_arrivals": proto_share_link.include_arrivals,
+        },
+    )?;
+
+    // the token is left out since anyone with it can view the deck
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::ShareLink,
+        Some(Key(conn.last_insert_rowid())),
+        Payload::after(&serde_json::json!({
+            "deckId": deck_id,
+            "expiresAt": proto_share_link.expires_at,
+            "includeArrivals": proto_share_link.include_arrivals,
+        })),
+    )?;
+
+    get_share_links(conn, user_id, deck_id)
+}
+
+// revoked links are kept so that their view counts remain visible to the owner
+//
+pub(crate) fn revoke_share_link(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    link_id: Key,
+) -> Result<Vec<ShareLink>, DbError> {
+    sqlite::zero(
+        conn,
+        "UPDATE share_links
+         SET revoked = TRUE
+         WHERE id = :link_id AND user_id = :user_id AND deck_id = :deck_id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":link_id": link_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Edit,
+        AuditEntity::ShareLink,
+        Some(link_id),
+        Payload::after(&serde_json::json!({ "deckId": deck_id, "revoked": true })),
+    )?;
+
+    get_share_links(conn, user_id, deck_id)
+}
+
+// returns None if the token is unknown, revoked or has expired,
+// otherwise the view is counted and the deck is returned
+//
+pub(crate) fn view_share_link(
+    conn: &mut rusqlite::Connection,
+    token: &str,
+) -> Result<Option<PublicDeck>, DbError> {
+    let tx = conn.transaction()?;
+
+    let link: Option<ValidLink> = sqlite::one_optional(
+        &tx,
+        "SELECT id, user_id, deck_id, include_arrivals
+         FROM share_links
+         WHERE token = :token AND NOT revoked
+               AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+        named_params! {":token": token},
+    )?;
+    let Some(link) = link else {
+        return Ok(None);
+    };
+
+    sqlite::zero(
+        &tx,
+        "UPDATE share_links
+         SET view_count = view_count + 1, last_viewed_at = CURRENT_TIMESTAMP
+         WHERE id = :link_id",
+        named_params! {":link_id": link.id},
+    )?;
+
+    let stmt = Qry::select_decklike()
+        .from_decklike()
+        .where_clause("d.user_id = :user_id AND d.id = :deck_id");
+    let deck: SlimDeck = sqlite::one(
+        &tx,
+        &stmt,
+        named_params! {":user_id": link.user_id, ":deck_id": link.deck_id},
+    )?;
+
+    // private notes, the owner's flashcards and their refs (which name and annotate
+    // the owner's other decks) are never shown
+    let mut notes = notes_db::without_private_notes(notes_db::notes_for_deck(&tx, link.deck_id)?);
+    for note in &mut notes {
+        note.flashcards.clear();
+        note.refs.clear();
+    }
+
+    let points = points_db::all(&tx, link.user_id, link.deck_id)?;
+
+    let mut arrivals = if link.include_arrivals {
+        notes_db::without_private_arrivals(notes_db::arrivals_for_deck(&tx, link.deck_id)?)
+    } else {
+        vec![]
+    };
+    for arrival in &mut arrivals {
+        for note in &mut arrival.notes {
+            note.flashcards.clear();
+            note.refs.clear();
+        }
+    }
+
+    tx.commit()?;
+
+    Ok(Some(PublicDeck {
+        deck,
+        notes,
+        points,
+        arrivals,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, add_note, add_ref, create_deck, test_db};
+    use chrono::{Duration, Utc};
+
+    fn share(
+        conn: &rusqlite::Connection,
+        deck_id: Key,
+        token: &str,
+        expires_at: Option<chrono::NaiveDateTime>,
+    ) -> ShareLink {
+        let link = ProtoShareLink {
+            expires_at,
+            include_arrivals: true,
+        };
+        create_share_link(conn, OWNER, deck_id, link, token.to_string())
+            .unwrap()
+            .pop()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_expired_links_show_nothing() {
+        let mut db = test_db("share-link-expiry");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        add_note(&mut db.conn, deck_id, "public");
+
+        let now = Utc::now().naive_utc();
+        share(&db.conn, deck_id, "expired", Some(now - Duration::days(1)));
+        share(&db.conn, deck_id, "current", Some(now + Duration::days(1)));
+        share(&db.conn, deck_id, "forever", None);
+
+        assert!(view_share_link(&mut db.conn, "expired").unwrap().is_none());
+        assert!(view_share_link(&mut db.conn, "current").unwrap().is_some());
+        assert!(view_share_link(&mut db.conn, "forever").unwrap().is_some());
+        assert!(view_share_link(&mut db.conn, "unknown").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_revoked_links_show_nothing() {
+        let mut db = test_db("share-link-revoke");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        add_note(&mut db.conn, deck_id, "public");
+
+        let link = share(&db.conn, deck_id, "token", None);
+        assert!(view_share_link(&mut db.conn, "token").unwrap().is_some());
+
+        let links = revoke_share_link(&db.conn, OWNER, deck_id, link.id).unwrap();
+        assert!(links[0].revoked);
+        assert!(view_share_link(&mut db.conn, "token").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_views_are_counted() {
+        let mut db = test_db("share-link-views");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        add_note(&mut db.conn, deck_id, "public");
+
+        let link = share(&db.conn, deck_id, "token", None);
+        assert_eq!(link.view_count, 0);
+        assert!(link.last_viewed_at.is_none());
+
+        view_share_link(&mut db.conn, "token").unwrap();
+        view_share_link(&mut db.conn, "token").unwrap();
+        let links = get_share_links(&db.conn, OWNER, deck_id).unwrap();
+        assert_eq!(links[0].view_count, 2);
+        assert!(links[0].last_viewed_at.is_some());
+
+        // attempts on a revoked link aren't views, but the count stays visible to the owner
+        revoke_share_link(&db.conn, OWNER, deck_id, link.id).unwrap();
+        view_share_link(&mut db.conn, "token").unwrap();
+        let links = get_share_links(&db.conn, OWNER, deck_id).unwrap();
+        assert_eq!(links[0].view_count, 2);
+    }
+
+    #[test]
+    fn test_refs_are_not_shown() {
+        let mut db = test_db("share-link-refs");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let elsewhere = create_deck(&db.conn, "idea", "Private thoughts");
+
+        let note_id = add_note(&mut db.conn, deck_id, "public");
+        add_ref(&db.conn, note_id, elsewhere);
+        let arriving = add_note(&mut db.conn, elsewhere, "arrival");
+        add_ref(&db.conn, arriving, deck_id);
+        add_ref(&db.conn, arriving, elsewhere);
+        db.conn
+            .execute("UPDATE refs SET annotation = 'not for sharing'", [])
+            .unwrap();
+
+        share(&db.conn, deck_id, "token", None);
+        let public_deck = view_share_link(&mut db.conn, "token").unwrap().unwrap();
+
+        assert_eq!(public_deck.notes.len(), 1);
+        assert!(public_deck.notes[0].refs.is_empty());
+        assert_eq!(public_deck.arrivals.len(), 1);
+        assert!(
+            public_deck.arrivals[0]
+                .notes
+                .iter()
+                .all(|n| n.refs.is_empty())
+        );
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
+use crate::db::notes as notes_db;
+use crate::db::qry::Qry;
+use crate::db::sanitize_for_sqlite_match;
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::decks::SlimDeck;
+use crate::interop::search::SearchDeck;
+use crate::interop::shares::{Foreign, ForeignDeck, Share, SharedDeck};
+use rusqlite::{Row, named_params};
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+// the ids of every deck shared with :user_id: the decks that were shared directly along
+// with, for shares that include a subtree, the owner's decks that are reachable through
+// refs from non-private notes. A graph terminator is included but the subtree isn't
+// followed beyond it
+//
+pub(crate) const SHARED_DECKS: &str = "WITH RECURSIVE shared_decks(id, expand) AS (
+                                           SELECT deck_id, include_subtree
+                                           FROM deck_shares
+                                           WHERE recipient_id = :user_id
+                                           UNION
+                                           SELECT d.id, NOT d.graph_terminator
+                                           FROM shared_decks sd, notes n, refs r, decks d
+                                           WHERE sd.expand AND n.deck_id = sd.id AND NOT n.private
+                                                 AND r.note_id = n.id AND d.id = r.deck_id
+                                                 AND d.user_id = n.user_id
+                                       ) ";
+
+impl FromRow for Share {
+    fn from_row(row: &Row) -> rusqlite::Result<Share> {
+        Ok(Share {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            deck_name: row.get("deck_name")?,
+            recipient_email: row.get("recipient_email")?,
+            include_subtree: row.get("include_subtree")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+impl FromRow for ForeignDeck {
+    fn from_row(row: &Row) -> rusqlite::Result<ForeignDeck> {
+        Ok(ForeignDeck {
+            deck: SlimDeck {
+                id: row.get("id")?,
+                title: row.get("name")?,
+                deck_kind: row.get("kind")?,
+                created_at: row.get("created_at")?,
+                graph_terminator: row.get("graph_terminator")?,
+                insignia: row.get("insignia")?,
+                font: row.get("font")?,
+                impact: row.get("impact")?,
+            },
+            foreign: Foreign {
+                owner: row.get("owner")?,
+                read_only: true,
+            },
+        })
+    }
+}
+
+const SELECT_SHARE: &str = "SELECT s.id, s.deck_id, d.name AS deck_name, u.email AS recipient_email,
+                                   s.include_subtree, s.created_at
+                            FROM deck_shares s, decks d, users u
+                            WHERE s.deck_id = d.id AND s.recipient_id = u.id AND s.owner_id = :user_id";
+
+pub(crate) fn get_shares(conn: &rusqlite::Connection, user_id: Key) -> Result<Vec<Share>, DbError> {
+    sqlite::many(
+        conn,
+        &format!("{SELECT_SHARE} ORDER BY s.id"),
+        named_params! {":user_id": user_id},
+    )
+}
+
+pub(crate) fn get_deck_shares(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Vec<Share>, DbError> {
+    sqlite::many(
+        conn,
+        &format!("{SELECT_SHARE} AND s.deck_id = :deck_id ORDER BY s.id"),
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )
+}
+
+pub(crate) fn get_user_id_by_email(
+    conn: &rusqlite::Connection,
+    email: &str,
+) -> Result<Option<Key>, DbError> {
+    sqlite::one_optional(
+        conn,
+        "SELECT id
+         FROM users
+         WHERE email = :email",
+        named_params! {":email": email.trim()},
+    )
+}
+
+// sharing a deck that's already shared with the recipient updates the include_subtree setting.
+// Without a recipient (an unknown email) nothing is shared but the response is the same, so
+// that sharing can't be used to find out who has an account
+//
+pub(crate) fn create_share(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    recipient_id: Option<Key>,
+    include_subtree: bool,
+) -> Result<Vec<Share>, DbError> {
+    let owned: Option<Key> = sqlite::one_optional(
+        conn,
+        "SELECT id
+         FROM decks
+         WHERE user_id = :user_id AND id = :deck_id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )?;
+    if owned.is_none() {
+        return Err(DbError::NotFound);
+    }
+    let Some(recipient_id) = recipient_id else {
+        return get_deck_shares(conn, user_id, deck_id);
+    };
+
+    sqlite::zero(
+        conn,
+        "INSERT INTO deck_shares(owner_id, recipient_id, deck_id, include_subtree)
+         SELECT user_id, :recipient_id, id, :include_subtree
+         FROM decks
+         WHERE user_id = :user_id AND id = :deck_id
+         ON CONFLICT(deck_id, recipient_id) DO UPDATE SET include_subtree = excluded.include_subtree",
+        named_params! {
+            ":user_id": user_id,
+            ":deck_id": deck_id,
+            ":recipient_id": recipient_id,
+            ":include_subtree": include_subtree,
+        },
+    )?;
+
+    if conn.changes() > 0 {
+        let share_id: Key = sqlite::one(
+            conn,
+            "SELECT id FROM deck_shares WHERE deck_id = :deck_id AND recipient_id = :recipient_id",
+            named_params! {":deck_id": deck_id, ":recipient_id": recipient_id},
+        )?;
+        audit_db::record(
+            conn,
+            user_id,
+            AuditAction::Create,
+            AuditEntity::Share,
+            Some(share_id),
+            Payload::after(&serde_json::json!({
+                "deckId": deck_id,
+                "recipientId": recipient_id,
+                "includeSubtree": include_subtree,
+            })),
+        )?;
+    }
+
+    get_deck_shares(conn, user_id, deck_id)
+}
+
+pub(crate) fn delete_share(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    share_id: Key,
+) -> Result<Vec<Share>, DbError> {
+    sqlite::zero(
+        conn,
+        "DELETE FROM deck_shares
+         WHERE id = :share_id AND owner_id = :user_id",
+        named_params! {":user_id": user_id, ":share_id": share_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Share,
+        Some(share_id),
+        Payload::none(),
+    )?;
+
+    get_shares(conn, user_id)
+}
+
+pub(crate) fn get_deck_owner_id(conn: &rusqlite::Connection, deck_id: Key) -> Result<Key, DbError> {
+    sqlite::one(
+        conn,
+        "SELECT user_id
+         FROM decks
+         WHERE id = :deck_id",
+        named_params! {":deck_id": deck_id},
+    )
+}
+
+pub(crate) fn shared_deck_ids(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+) -> Result<HashSet<Key>, DbError> {
+    let ids: Vec<Key> = sqlite::many(
+        conn,
+        &format!("{SHARED_DECKS} SELECT DISTINCT id FROM shared_decks"),
+        named_params! {":user_id": user_id},
+    )?;
+
+    Ok(ids.into_iter().collect())
+}
+
+fn select_foreign_decks() -> Qry {
+    Qry::new(SHARED_DECKS)
+        .select_decklike_inline()
+        .comma("u.username AS owner")
+        .from("decks d, users u")
+        .where_clause("d.id IN (SELECT id FROM shared_decks)")
+        .and("u.id = d.user_id")
+}
+
+// every deck that's visible to the user through a share
+//
+pub(crate) fn get_received(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+) -> Result<Vec<ForeignDeck>, DbError> {
+    let stmt = select_foreign_decks().order_by("u.username, d.name");
+    sqlite::many(conn, &stmt, named_params! {":user_id": user_id})
+}
+
+pub(crate) fn get_foreign_deck(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Option<ForeignDeck>, DbError> {
+    let stmt = select_foreign_decks().and("d.id = :deck_id");
+    sqlite::one_optional(
+        conn,
+        &stmt,
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )
+}
+
+pub(crate) fn get_shared_deck(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Option<SharedDeck>, DbError> {
+    let Some(foreign_deck) = get_foreign_deck(conn, user_id, deck_id)? else {
+        return Ok(None);
+    };
+    let shared = shared_deck_ids(conn, user_id)?;
+
+    // private notes are never shared, refs and arrivals are limited to
+    // the decks that have been shared and the owner's flashcards are left out
+    let mut notes = notes_db::without_private_notes(notes_db::notes_for_deck(conn, deck_id)?);
+    for note in &mut notes {
+        note.refs.retain(|r| shared.contains(&r.id));
+        note.flashcards.clear();
+    }
+
+    let mut arrivals =
+        notes_db::without_private_arrivals(notes_db::arrivals_for_deck(conn, deck_id)?);
+    arrivals.retain(|arrival| shared.contains(&arrival.deck.id));
+    for arrival in &mut arrivals {
+        for note in &mut arrival.notes {
+            note.refs.retain(|r| shared.contains(&r.id));
+            note.flashcards.clear();
+        }
+    }
+
+    Ok(Some(SharedDeck {
+        deck: foreign_deck.deck,
+        foreign: foreign_deck.foreign,
+        notes,
+        arrivals,
+    }))
+}
+
+// decks shared with the user whose names match the query, marked as foreign so
+// that they can be shown alongside (but distinct from) the user's own results
+//
+pub(crate) fn search_shared_decks(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    query: String,
+) -> Result<Vec<SearchDeck>, DbError> {
+    let query = sanitize_for_sqlite_match(query)?;
+    if query.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let stmt = Qry::new(SHARED_DECKS)
+        .select_decklike_inline()
+        .comma("u.username AS owner, decks_fts.rank AS rank")
+        .from("decks_fts, decks d, users u")
+        .where_clause("decks_fts MATCH :query")
+        .and("d.id = decks_fts.rowid")
+        .and("d.id IN (SELECT id FROM shared_decks)")
+        .and("u.id = d.user_id")
+        .order_by("decks_fts.rank")
+        .limit();
+
+    let ranked: Vec<RankedForeignDeck> = sqlite::many(
+        conn,
+        &stmt,
+        named_params! {":user_id": user_id, ":query": query, ":limit": 20},
+    )?;
+
+    Ok(ranked
+        .into_iter()
+        .map(|r| SearchDeck {
+            rank: r.rank,
+            deck: r.foreign_deck.deck,
+            notes: vec![],
+            foreign: Some(r.foreign_deck.foreign),
+            snippet: None,
+            source: None,
+        })
+        .collect())
+}
+
+struct RankedForeignDeck {
+    rank: f32,
+    foreign_deck: ForeignDeck,
+}
+
+impl FromRow for RankedForeignDeck {
+    fn from_row(row: &Row) -> rusqlite::Result<RankedForeignDeck> {
+        Ok(RankedForeignDeck {
+            rank: row.get("rank")?,
+            foreign_deck: ForeignDeck::from_row(row)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, RECIPIENT, add_note, add_ref, create_deck, test_db};
+
+    #[test]
+    fn test_subtree_stops_at_graph_terminator() {
+        let mut db = test_db("share-terminator");
+        let shared = create_deck(&db.conn, "idea", "Shared");
+        let terminator = create_deck(&db.conn, "idea", "Terminator");
+        let beyond = create_deck(&db.conn, "idea", "Beyond");
+        db.conn
+            .execute(
+                "UPDATE decks SET graph_terminator = TRUE WHERE id = ?1",
+                [terminator],
+            )
+            .unwrap();
+
+        let note_id = add_note(&mut db.conn, shared, "to the terminator");
+        add_ref(&db.conn, note_id, terminator);
+        let note_id = add_note(&mut db.conn, terminator, "and beyond");
+        add_ref(&db.conn, note_id, beyond);
+
+        create_share(&db.conn, OWNER, shared, Some(RECIPIENT), false).unwrap();
+        let ids = shared_deck_ids(&db.conn, RECIPIENT).unwrap();
+        assert_eq!(ids, HashSet::from([shared]));
+
+        // the terminator is shared but its own refs aren't followed
+        create_share(&db.conn, OWNER, shared, Some(RECIPIENT), true).unwrap();
+        let ids = shared_deck_ids(&db.conn, RECIPIENT).unwrap();
+        assert_eq!(ids, HashSet::from([shared, terminator]));
+    }
+
+    #[test]
+    fn test_unknown_recipient_gets_the_same_response() {
+        let db = test_db("share-unknown-recipient");
+        let deck_id = create_deck(&db.conn, "idea", "Shared");
+
+        let shares = create_share(&db.conn, OWNER, deck_id, Some(RECIPIENT), false).unwrap();
+        assert_eq!(shares.len(), 1);
+
+        let unknown = create_share(&db.conn, OWNER, deck_id, None, false).unwrap();
+        assert_eq!(
+            serde_json::to_value(&unknown).unwrap(),
+            serde_json::to_value(&shares).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sharing_another_users_deck_is_not_found() {
+        let db = test_db("share-not-owner");
+        let deck_id = create_deck(&db.conn, "idea", "Not yours");
+
+        let r = create_share(&db.conn, RECIPIENT, deck_id, Some(OWNER), true);
+        assert!(matches!(r, Err(DbError::NotFound)));
+        let r = create_share(&db.conn, RECIPIENT, deck_id, None, true);
+        assert!(matches!(r, Err(DbError::NotFound)));
+
+        assert!(get_shares(&db.conn, OWNER).unwrap().is_empty());
+        assert!(get_received(&db.conn, OWNER).unwrap().is_empty());
+    }
+}
@@ -32,6 +32,12 @@ impl FromRow for i32 {
     }
 }
 
+impl FromRow for i64 {
+    fn from_row(row: &Row) -> rusqlite::Result<i64> {
+        row.get(0)
+    }
+}
+
 impl FromRow for Key {
     fn from_row(row: &Row) -> rusqlite::Result<Key> {
         Ok(row.get(0)?)
@@ -50,6 +56,12 @@ impl FromRow for String {
     }
 }
 
+impl FromRow for (String, String) {
+    fn from_row(row: &Row) -> rusqlite::Result<(String, String)> {
+        Ok((row.get(0)?, row.get(1)?))
+    }
+}
+
 impl FromRow for chrono::NaiveDateTime {
     fn from_row(row: &Row) -> rusqlite::Result<chrono::NaiveDateTime> {
         Ok(row.get(0)?)
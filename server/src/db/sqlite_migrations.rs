@@ -30,6 +30,7 @@ CREATE TABLE IF NOT EXISTS users (
 
        image_count INTEGER DEFAULT 0,
        ui_config_json TEXT NOT NULL DEFAULT '{}', -- an opaque json string used exclusively by the client
+       search_language TEXT NOT NULL DEFAULT 'en', -- any language other than English is searched with the trigram indexes
 
        password TEXT NOT NULL
 );
@@ -58,6 +59,10 @@ CREATE TABLE IF NOT EXISTS decks (
 
        impact INTEGER DEFAULT 0,
 
+       private_notes_default BOOLEAN DEFAULT FALSE, -- the private setting given to new notes
+
+       version INTEGER NOT NULL DEFAULT 1, -- bumped on every edit, guards against clobbering a concurrent edit
+
        FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
 );
 
@@ -100,6 +105,10 @@ CREATE TABLE IF NOT EXISTS notes (
        content TEXT NOT NULL,
        font INTEGER NOT NULL DEFAULT 1,
 
+       private BOOLEAN DEFAULT FALSE, -- never shared, published, exported or sent to an AI
+
+       version INTEGER NOT NULL DEFAULT 1, -- bumped on every edit, guards against clobbering a concurrent edit
+
        FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
        FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
        FOREIGN KEY (point_id) REFERENCES points (id) ON DELETE CASCADE ON UPDATE NO ACTION
@@ -275,10 +284,184 @@ CREATE TABLE IF NOT EXISTS stats_num_refs (
        FOREIGN KEY (stats_id) REFERENCES stats (id) ON DELETE CASCADE ON UPDATE NO ACTION
 );
 
-CREATE VIRTUAL TABLE decks_fts USING fts5(name, content='decks', content_rowid='id' tokenize='porter unicode61', prefix='2 3 4 5 6');
-CREATE VIRTUAL TABLE points_fts USING fts5(title, location_textual, date_textual, content='points', content_rowid='id' tokenize='porter unicode61', prefix='2 3 4 5 6');
-CREATE VIRTUAL TABLE notes_fts USING fts5(content, content='notes', content_rowid='id', tokenize='porter unicode61', prefix='2 3 4 5 6');
-CREATE VIRTUAL TABLE article_extras_fts USING fts5(source, author, short_description, content='article_extras', content_rowid='deck_id', tokenize='porter unicode61', prefix='2 3 4 5 6');
+CREATE TABLE IF NOT EXISTS deck_aliases (
+       id INTEGER PRIMARY KEY,
+       created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+       deck_id INTEGER NOT NULL,
+       name TEXT NOT NULL,
+
+       FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       UNIQUE(deck_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS tags (
+       id INTEGER PRIMARY KEY,
+       created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+       user_id INTEGER NOT NULL,
+       name TEXT NOT NULL,
+
+       FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       UNIQUE(user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS deck_tags (
+       deck_id INTEGER NOT NULL,
+       tag_id INTEGER NOT NULL,
+
+       PRIMARY KEY (deck_id, tag_id),
+       FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+CREATE TABLE IF NOT EXISTS note_tags (
+       note_id INTEGER NOT NULL,
+       tag_id INTEGER NOT NULL,
+
+       PRIMARY KEY (note_id, tag_id),
+       FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+-- a read-only grant of a deck (and optionally the decks it refers to) to another user
+CREATE TABLE IF NOT EXISTS deck_shares (
+       id INTEGER PRIMARY KEY,
+       created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+       owner_id INTEGER NOT NULL,
+       recipient_id INTEGER NOT NULL,
+       deck_id INTEGER NOT NULL,
+
+       include_subtree BOOLEAN DEFAULT FALSE,
+
+       FOREIGN KEY (owner_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       FOREIGN KEY (recipient_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       UNIQUE(deck_id, recipient_id)
+);
+
+-- a public link to a single deck, viewable without logging in
+CREATE TABLE IF NOT EXISTS share_links (
+       id INTEGER PRIMARY KEY,
+       created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+       user_id INTEGER NOT NULL,
+       deck_id INTEGER NOT NULL,
+
+       token TEXT NOT NULL UNIQUE,
+       expires_at DATETIME,            -- never expires if NULL
+       revoked BOOLEAN DEFAULT FALSE,
+       include_arrivals BOOLEAN DEFAULT FALSE,
+
+       view_count INTEGER DEFAULT 0,
+       last_viewed_at DATETIME,
+
+       FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+
+-- append-only record of every change made through the api, along with migrations.
+-- Deliberately has no foreign keys so that events outlive the things they describe
+CREATE TABLE IF NOT EXISTS audit_events (
+       id INTEGER PRIMARY KEY,
+       created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+       user_id INTEGER,          -- NULL for events that weren't caused by a user, e.g. migrations
+       entity TEXT NOT NULL,     -- 'deck', 'note', 'ref', 'point', 'card', 'tag', 'user', 'database', etc
+       entity_id INTEGER,
+       action TEXT NOT NULL,     -- 'create', 'edit', 'delete', 'summarize', 'login', 'migrate', etc
+       payload TEXT              -- json of the form {"before": ..., "after": ...}
+);
+
+-- the most recent change to each deck, note, point, card and set of note refs, maintained by triggers.
+-- seq only ever increases so clients can ask for everything that changed after the last seq they saw
+CREATE TABLE IF NOT EXISTS sync_changes (
+       seq INTEGER PRIMARY KEY AUTOINCREMENT,
+
+       user_id INTEGER NOT NULL,
+       entity TEXT NOT NULL,     -- 'deck', 'note', 'ref', 'point', 'card'
+       entity_id INTEGER NOT NULL, -- for 'ref' this is the note_id
+
+       FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       UNIQUE(entity, entity_id)
+);
+
+-- a deck's name along with all of its aliases, used as the content of decks_fts
+CREATE VIEW IF NOT EXISTS decks_fts_content AS
+       SELECT d.id AS id,
+              d.name AS name,
+              (SELECT group_concat(a.name, ' ')
+               FROM (SELECT name FROM deck_aliases WHERE deck_id = d.id ORDER BY id) a) AS aliases
+       FROM decks d;
+
+CREATE VIRTUAL TABLE decks_fts USING fts5(name, aliases, content='decks_fts_content', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+-- the same content split into trigrams, finds candidates for misspelt deck names
+CREATE VIRTUAL TABLE decks_trigram USING fts5(name, aliases, content='decks_fts_content', content_rowid='id', tokenize='trigram remove_diacritics 1');
+
+-- how often each term appears in a deck's notes, used to find related decks
+CREATE TABLE IF NOT EXISTS deck_terms (
+       deck_id INTEGER NOT NULL,
+       term TEXT NOT NULL,
+       count INTEGER NOT NULL,
+
+       FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       PRIMARY KEY (deck_id, term)
+);
+CREATE INDEX IF NOT EXISTS deck_terms_term ON deck_terms(term);
+
+-- decks whose deck_terms need to be recounted, filled by triggers on notes
+CREATE TABLE IF NOT EXISTS deck_terms_stale (
+       deck_id INTEGER PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS saved_searches (
+       id INTEGER PRIMARY KEY,
+       user_id INTEGER NOT NULL,
+       created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+       name TEXT NOT NULL,
+       query TEXT NOT NULL,                   -- in the search query language, e.g. kind:person ref:"Stoicism"
+       pinned BOOLEAN NOT NULL DEFAULT FALSE, -- pinned searches are listed with the bookmarks
+
+       FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+       UNIQUE(user_id, name)
+);
+
+-- the weights used to order a user's search results, users without a row get the defaults
+CREATE TABLE IF NOT EXISTS search_rankings (
+       user_id INTEGER PRIMARY KEY,
+
+       title REAL NOT NULL,    -- bm25 weights of the fts5 columns
+       aliases REAL NOT NULL,
+       body REAL NOT NULL,
+       impact REAL NOT NULL,   -- how much each of a deck's signals adds to its text relevance
+       insignia REAL NOT NULL,
+       visits REAL NOT NULL,
+       recency REAL NOT NULL,
+
+       FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
+CREATE VIRTUAL TABLE points_fts USING fts5(title, location_textual, date_textual, content='points', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+CREATE VIRTUAL TABLE notes_fts USING fts5(content, content='notes', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+-- unstemmed substring matches of notes, for languages other than English and scripts without spaces between words
+CREATE VIRTUAL TABLE notes_trigram USING fts5(content, content='notes', content_rowid='id', tokenize='trigram remove_diacritics 1');
+CREATE VIRTUAL TABLE article_extras_fts USING fts5(source, author, short_description, content='article_extras', content_rowid='deck_id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+-- refs has no rowid of its own, so each annotated ref is given one here for refs_fts to be keyed by
+CREATE TABLE IF NOT EXISTS refs_fts_keys (
+       id INTEGER PRIMARY KEY,
+       note_id INTEGER NOT NULL,
+       deck_id INTEGER NOT NULL,
+
+       UNIQUE(note_id, deck_id)
+);
+CREATE VIEW IF NOT EXISTS refs_fts_content AS
+       SELECT k.id AS id,
+              r.annotation AS annotation
+       FROM refs_fts_keys k, refs r
+       WHERE r.note_id = k.note_id AND r.deck_id = k.deck_id;
+CREATE VIRTUAL TABLE refs_fts USING fts5(annotation, content='refs_fts_content', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+CREATE VIRTUAL TABLE dialogue_messages_fts USING fts5(content, content='dialogue_messages', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
  */
 
 // the following sqlite command:
@@ -905,6 +1088,517 @@ pub fn migration_check(db_name: &str) -> crate::Result<()> {
                    FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
                    FOREIGN KEY (parent_deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION
                );"),
+
+        ///////////////////
+        // user_version 30: deck_aliases, indexed alongside deck names in decks_fts
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS deck_aliases (
+                   id INTEGER PRIMARY KEY,
+                   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                   deck_id INTEGER NOT NULL,
+                   name TEXT NOT NULL,
+
+                   FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   UNIQUE(deck_id, name)
+               );
+               CREATE INDEX IF NOT EXISTS deck_aliases_name ON deck_aliases(name);
+
+               CREATE VIEW IF NOT EXISTS decks_fts_content AS
+                   SELECT d.id AS id,
+                          d.name AS name,
+                          (SELECT group_concat(a.name, ' ')
+                           FROM (SELECT name FROM deck_aliases WHERE deck_id = d.id ORDER BY id) a) AS aliases
+                   FROM decks d;
+
+               DROP TRIGGER IF EXISTS decks_fts_ai;
+               DROP TRIGGER IF EXISTS decks_fts_ad;
+               DROP TRIGGER IF EXISTS decks_fts_au;
+               DROP TABLE IF EXISTS decks_fts;
+
+               CREATE VIRTUAL TABLE decks_fts USING fts5(name, aliases, content='decks_fts_content', content_rowid='id', tokenize='porter unicode61', prefix='2 3 4 5 6');
+               INSERT INTO decks_fts(decks_fts) VALUES('rebuild');
+
+               -- the indexed row of a deck is read from decks_fts_content, so stale rows
+               -- are removed before a change and fresh rows are added after it
+               CREATE TRIGGER decks_fts_ai AFTER INSERT ON decks BEGIN
+                   INSERT INTO decks_fts(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = new.id;
+               END;
+               CREATE TRIGGER decks_fts_bd BEFORE DELETE ON decks BEGIN
+                   INSERT INTO decks_fts(decks_fts, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = old.id;
+               END;
+               CREATE TRIGGER decks_fts_bu BEFORE UPDATE OF name ON decks BEGIN
+                   INSERT INTO decks_fts(decks_fts, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = old.id;
+               END;
+               CREATE TRIGGER decks_fts_au AFTER UPDATE OF name ON decks BEGIN
+                   INSERT INTO decks_fts(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = new.id;
+               END;
+
+               CREATE TRIGGER deck_aliases_fts_bi BEFORE INSERT ON deck_aliases BEGIN
+                   INSERT INTO decks_fts(decks_fts, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = new.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_fts_ai AFTER INSERT ON deck_aliases BEGIN
+                   INSERT INTO decks_fts(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = new.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_fts_bd BEFORE DELETE ON deck_aliases BEGIN
+                   INSERT INTO decks_fts(decks_fts, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = old.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_fts_ad AFTER DELETE ON deck_aliases BEGIN
+                   INSERT INTO decks_fts(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = old.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_fts_bu BEFORE UPDATE ON deck_aliases BEGIN
+                   INSERT INTO decks_fts(decks_fts, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id IN (old.deck_id, new.deck_id);
+               END;
+               CREATE TRIGGER deck_aliases_fts_au AFTER UPDATE ON deck_aliases BEGIN
+                   INSERT INTO decks_fts(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id IN (old.deck_id, new.deck_id);
+               END;"),
+
+        ///////////////////
+        // user_version 31: tags for decks and notes
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS tags (
+                   id INTEGER PRIMARY KEY,
+                   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                   user_id INTEGER NOT NULL,
+                   name TEXT NOT NULL,
+
+                   FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   UNIQUE(user_id, name)
+               );
+
+               CREATE TABLE IF NOT EXISTS deck_tags (
+                   deck_id INTEGER NOT NULL,
+                   tag_id INTEGER NOT NULL,
+
+                   PRIMARY KEY (deck_id, tag_id),
+                   FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE ON UPDATE NO ACTION
+               );
+               CREATE INDEX IF NOT EXISTS deck_tags_tag_id ON deck_tags(tag_id);
+
+               CREATE TABLE IF NOT EXISTS note_tags (
+                   note_id INTEGER NOT NULL,
+                   tag_id INTEGER NOT NULL,
+
+                   PRIMARY KEY (note_id, tag_id),
+                   FOREIGN KEY (note_id) REFERENCES notes (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE ON UPDATE NO ACTION
+               );
+               CREATE INDEX IF NOT EXISTS note_tags_tag_id ON note_tags(tag_id);"),
+
+        ///////////////////
+        // user_version 32: read-only deck shares between users
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS deck_shares (
+                   id INTEGER PRIMARY KEY,
+                   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                   owner_id INTEGER NOT NULL,
+                   recipient_id INTEGER NOT NULL,
+                   deck_id INTEGER NOT NULL,
+
+                   include_subtree BOOLEAN DEFAULT FALSE,
+
+                   FOREIGN KEY (owner_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   FOREIGN KEY (recipient_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   UNIQUE(deck_id, recipient_id)
+               );
+               CREATE INDEX IF NOT EXISTS deck_shares_recipient_id ON deck_shares(recipient_id);"),
+
+        ///////////////////
+        // user_version 33: public, unauthenticated links to a single deck
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS share_links (
+                   id INTEGER PRIMARY KEY,
+                   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                   user_id INTEGER NOT NULL,
+                   deck_id INTEGER NOT NULL,
+
+                   token TEXT NOT NULL UNIQUE,
+                   expires_at DATETIME,
+                   revoked BOOLEAN DEFAULT FALSE,
+                   include_arrivals BOOLEAN DEFAULT FALSE,
+
+                   view_count INTEGER DEFAULT 0,
+                   last_viewed_at DATETIME,
+
+                   FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION
+               );
+               CREATE INDEX IF NOT EXISTS share_links_deck_id ON share_links(deck_id);"),
+
+        ///////////////////
+        // user_version 34: private notes, which never leave the user's own view
+        ///////////////////
+        M::up("ALTER TABLE notes ADD COLUMN private BOOLEAN DEFAULT FALSE;
+               ALTER TABLE decks ADD COLUMN private_notes_default BOOLEAN DEFAULT FALSE;"),
+
+        ///////////////////
+        // user_version 35: append-only audit log
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS audit_events (
+                   id INTEGER PRIMARY KEY,
+                   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                   user_id INTEGER,
+                   entity TEXT NOT NULL,
+                   entity_id INTEGER,
+                   action TEXT NOT NULL,
+                   payload TEXT
+               );
+               CREATE INDEX IF NOT EXISTS audit_events_user_id ON audit_events(user_id);
+               CREATE INDEX IF NOT EXISTS audit_events_entity ON audit_events(entity, entity_id);
+
+               CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON audit_events BEGIN
+                   SELECT RAISE(ABORT, 'audit_events is append-only');
+               END;
+               CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON audit_events BEGIN
+                   SELECT RAISE(ABORT, 'audit_events is append-only');
+               END;"),
+
+        ///////////////////
+        // user_version 36: change sequence for offline sync
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS sync_changes (
+                   seq INTEGER PRIMARY KEY AUTOINCREMENT,
+
+                   user_id INTEGER NOT NULL,
+                   entity TEXT NOT NULL,
+                   entity_id INTEGER NOT NULL,
+
+                   FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   UNIQUE(entity, entity_id)
+               );
+               CREATE INDEX IF NOT EXISTS sync_changes_user_id ON sync_changes(user_id, seq);
+
+               INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'deck', id FROM decks;
+               INSERT INTO sync_changes(user_id, entity, entity_id) SELECT d.user_id, 'point', p.id FROM points p JOIN decks d ON d.id = p.deck_id;
+               INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'note', id FROM notes;
+               INSERT INTO sync_changes(user_id, entity, entity_id) SELECT DISTINCT n.user_id, 'ref', n.id FROM refs r JOIN notes n ON n.id = r.note_id;
+               INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'card', id FROM cards;
+
+               -- a change deletes the entity's previous row rather than using INSERT OR REPLACE,
+               -- since an UPDATE OR IGNORE that fires the trigger would override the REPLACE.
+               -- points and refs get their user from their parent, which has already gone when
+               -- they're deleted by a cascade, in that case the parent's BEFORE DELETE trigger
+               -- has recorded the change so it's left alone
+               --
+               CREATE TRIGGER sync_decks_ai AFTER INSERT ON decks BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'deck' AND entity_id = new.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (new.user_id, 'deck', new.id);
+               END;
+               CREATE TRIGGER sync_decks_au AFTER UPDATE ON decks BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'deck' AND entity_id = new.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (new.user_id, 'deck', new.id);
+               END;
+               CREATE TRIGGER sync_decks_ad AFTER DELETE ON decks BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'deck' AND entity_id = old.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (old.user_id, 'deck', old.id);
+               END;
+               CREATE TRIGGER sync_decks_bd BEFORE DELETE ON decks BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'point' AND entity_id IN (SELECT id FROM points WHERE deck_id = old.id);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT old.user_id, 'point', id FROM points WHERE deck_id = old.id;
+               END;
+
+               CREATE TRIGGER sync_points_ai AFTER INSERT ON points BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'point' AND entity_id = new.id AND EXISTS (SELECT 1 FROM decks WHERE id = new.deck_id);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'point', new.id FROM decks WHERE id = new.deck_id;
+               END;
+               CREATE TRIGGER sync_points_au AFTER UPDATE ON points BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'point' AND entity_id = new.id AND EXISTS (SELECT 1 FROM decks WHERE id = new.deck_id);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'point', new.id FROM decks WHERE id = new.deck_id;
+               END;
+               CREATE TRIGGER sync_points_ad AFTER DELETE ON points BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'point' AND entity_id = old.id AND EXISTS (SELECT 1 FROM decks WHERE id = old.deck_id);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'point', old.id FROM decks WHERE id = old.deck_id;
+               END;
+
+               CREATE TRIGGER sync_notes_ai AFTER INSERT ON notes BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'note' AND entity_id = new.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (new.user_id, 'note', new.id);
+               END;
+               CREATE TRIGGER sync_notes_au AFTER UPDATE ON notes BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'note' AND entity_id = new.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (new.user_id, 'note', new.id);
+               END;
+               CREATE TRIGGER sync_notes_ad AFTER DELETE ON notes BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'note' AND entity_id = old.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (old.user_id, 'note', old.id);
+               END;
+               CREATE TRIGGER sync_notes_bd BEFORE DELETE ON notes WHEN EXISTS (SELECT 1 FROM refs WHERE note_id = old.id) BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'ref' AND entity_id = old.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (old.user_id, 'ref', old.id);
+               END;
+
+               CREATE TRIGGER sync_refs_ai AFTER INSERT ON refs BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'ref' AND entity_id = new.note_id AND EXISTS (SELECT 1 FROM notes WHERE id = new.note_id);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'ref', new.note_id FROM notes WHERE id = new.note_id;
+               END;
+               CREATE TRIGGER sync_refs_au AFTER UPDATE ON refs BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'ref' AND entity_id IN (old.note_id, new.note_id) AND entity_id IN (SELECT id FROM notes);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'ref', old.note_id FROM notes WHERE id = old.note_id AND old.note_id <> new.note_id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'ref', new.note_id FROM notes WHERE id = new.note_id;
+               END;
+               CREATE TRIGGER sync_refs_ad AFTER DELETE ON refs BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'ref' AND entity_id = old.note_id AND EXISTS (SELECT 1 FROM notes WHERE id = old.note_id);
+                   INSERT INTO sync_changes(user_id, entity, entity_id) SELECT user_id, 'ref', old.note_id FROM notes WHERE id = old.note_id;
+               END;
+
+               CREATE TRIGGER sync_cards_ai AFTER INSERT ON cards BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'card' AND entity_id = new.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (new.user_id, 'card', new.id);
+               END;
+               CREATE TRIGGER sync_cards_au AFTER UPDATE ON cards BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'card' AND entity_id = new.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (new.user_id, 'card', new.id);
+               END;
+               CREATE TRIGGER sync_cards_ad AFTER DELETE ON cards BEGIN
+                   DELETE FROM sync_changes WHERE entity = 'card' AND entity_id = old.id;
+                   INSERT INTO sync_changes(user_id, entity, entity_id) VALUES (old.user_id, 'card', old.id);
+               END;"),
+
+        ///////////////////
+        // user_version 37: versions for optimistic concurrency on note and deck edits
+        ///////////////////
+        M::up("ALTER TABLE notes ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+               ALTER TABLE decks ADD COLUMN version INTEGER NOT NULL DEFAULT 1;"),
+
+        ///////////////////
+        // user_version 38: trigram index of deck names and aliases for typo tolerant searches
+        ///////////////////
+        M::up("CREATE VIRTUAL TABLE decks_trigram USING fts5(name, aliases, content='decks_fts_content', content_rowid='id', tokenize='trigram');
+               INSERT INTO decks_trigram(decks_trigram) VALUES('rebuild');
+
+               -- kept in step with decks_fts, see user_version 30
+               CREATE TRIGGER decks_trigram_ai AFTER INSERT ON decks BEGIN
+                   INSERT INTO decks_trigram(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = new.id;
+               END;
+               CREATE TRIGGER decks_trigram_bd BEFORE DELETE ON decks BEGIN
+                   INSERT INTO decks_trigram(decks_trigram, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = old.id;
+               END;
+               CREATE TRIGGER decks_trigram_bu BEFORE UPDATE OF name ON decks BEGIN
+                   INSERT INTO decks_trigram(decks_trigram, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = old.id;
+               END;
+               CREATE TRIGGER decks_trigram_au AFTER UPDATE OF name ON decks BEGIN
+                   INSERT INTO decks_trigram(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = new.id;
+               END;
+
+               CREATE TRIGGER deck_aliases_trigram_bi BEFORE INSERT ON deck_aliases BEGIN
+                   INSERT INTO decks_trigram(decks_trigram, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = new.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_trigram_ai AFTER INSERT ON deck_aliases BEGIN
+                   INSERT INTO decks_trigram(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = new.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_trigram_bd BEFORE DELETE ON deck_aliases BEGIN
+                   INSERT INTO decks_trigram(decks_trigram, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id = old.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_trigram_ad AFTER DELETE ON deck_aliases BEGIN
+                   INSERT INTO decks_trigram(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id = old.deck_id;
+               END;
+               CREATE TRIGGER deck_aliases_trigram_bu BEFORE UPDATE ON deck_aliases BEGIN
+                   INSERT INTO decks_trigram(decks_trigram, rowid, name, aliases) SELECT 'delete', id, name, aliases FROM decks_fts_content WHERE id IN (old.deck_id, new.deck_id);
+               END;
+               CREATE TRIGGER deck_aliases_trigram_au AFTER UPDATE ON deck_aliases BEGIN
+                   INSERT INTO decks_trigram(rowid, name, aliases) SELECT id, name, aliases FROM decks_fts_content WHERE id IN (old.deck_id, new.deck_id);
+               END;"),
+
+        ///////////////////
+        // user_version 39: cached term counts of each deck's notes for finding related decks
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS deck_terms (
+                   deck_id INTEGER NOT NULL,
+                   term TEXT NOT NULL,
+                   count INTEGER NOT NULL,
+
+                   FOREIGN KEY (deck_id) REFERENCES decks (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   PRIMARY KEY (deck_id, term)
+               );
+               CREATE INDEX IF NOT EXISTS deck_terms_term ON deck_terms(term);
+
+               -- decks whose notes have changed since their terms were counted. No foreign
+               -- key since the notes of a deleted deck mark it as stale while it's being deleted
+               CREATE TABLE IF NOT EXISTS deck_terms_stale (
+                   deck_id INTEGER PRIMARY KEY
+               );
+               INSERT INTO deck_terms_stale(deck_id) SELECT id FROM decks;
+
+               CREATE TRIGGER deck_terms_notes_ai AFTER INSERT ON notes BEGIN
+                   INSERT OR IGNORE INTO deck_terms_stale(deck_id) VALUES (new.deck_id);
+               END;
+               CREATE TRIGGER deck_terms_notes_au AFTER UPDATE OF content, deck_id ON notes BEGIN
+                   INSERT OR IGNORE INTO deck_terms_stale(deck_id) VALUES (old.deck_id);
+                   INSERT OR IGNORE INTO deck_terms_stale(deck_id) VALUES (new.deck_id);
+               END;
+               CREATE TRIGGER deck_terms_notes_ad AFTER DELETE ON notes BEGIN
+                   INSERT OR IGNORE INTO deck_terms_stale(deck_id) VALUES (old.deck_id);
+               END;"),
+
+        ///////////////////
+        // user_version 40: saved searches, shown as smart decks when pinned
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS saved_searches (
+                   id INTEGER PRIMARY KEY,
+                   user_id INTEGER NOT NULL,
+                   created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+                   name TEXT NOT NULL,
+                   query TEXT NOT NULL,
+                   pinned BOOLEAN NOT NULL DEFAULT FALSE,
+
+                   FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION,
+                   UNIQUE(user_id, name)
+               );"),
+
+        ///////////////////
+        // user_version 41: full text search of ref annotations and dialogue messages
+        ///////////////////
+        M::up("CREATE VIRTUAL TABLE refs_fts USING fts5(annotation, note_id UNINDEXED, deck_id UNINDEXED, tokenize='porter unicode61', prefix='2 3 4 5 6');
+               INSERT INTO refs_fts(annotation, note_id, deck_id)
+                   SELECT annotation, note_id, deck_id FROM refs WHERE annotation IS NOT NULL;
+
+               CREATE TRIGGER refs_fts_ai AFTER INSERT ON refs WHEN new.annotation IS NOT NULL BEGIN
+                   INSERT INTO refs_fts(annotation, note_id, deck_id) VALUES (new.annotation, new.note_id, new.deck_id);
+               END;
+               CREATE TRIGGER refs_fts_ad AFTER DELETE ON refs WHEN old.annotation IS NOT NULL BEGIN
+                   DELETE FROM refs_fts WHERE note_id = old.note_id AND deck_id = old.deck_id;
+               END;
+               CREATE TRIGGER refs_fts_au AFTER UPDATE OF annotation, note_id, deck_id ON refs BEGIN
+                   DELETE FROM refs_fts WHERE note_id = old.note_id AND deck_id = old.deck_id;
+                   INSERT INTO refs_fts(annotation, note_id, deck_id)
+                       SELECT new.annotation, new.note_id, new.deck_id WHERE new.annotation IS NOT NULL;
+               END;
+
+               CREATE VIRTUAL TABLE dialogue_messages_fts USING fts5(content, content='dialogue_messages', content_rowid='id', tokenize='porter unicode61', prefix='2 3 4 5 6');
+               INSERT INTO dialogue_messages_fts(dialogue_messages_fts) VALUES('rebuild');
+
+               CREATE TRIGGER dialogue_messages_fts_ai AFTER INSERT ON dialogue_messages BEGIN
+                   INSERT INTO dialogue_messages_fts(rowid, content) VALUES (new.id, new.content);
+               END;
+               CREATE TRIGGER dialogue_messages_fts_ad AFTER DELETE ON dialogue_messages BEGIN
+                   INSERT INTO dialogue_messages_fts(dialogue_messages_fts, rowid, content) VALUES('delete', old.id, old.content);
+               END;
+               CREATE TRIGGER dialogue_messages_fts_au AFTER UPDATE ON dialogue_messages BEGIN
+                   INSERT INTO dialogue_messages_fts(dialogue_messages_fts, rowid, content) VALUES('delete', old.id, old.content);
+                   INSERT INTO dialogue_messages_fts(rowid, content) VALUES (new.id, new.content);
+               END;"),
+
+        ///////////////////
+        // user_version 42: configurable ranking of search results
+        ///////////////////
+        M::up("CREATE TABLE IF NOT EXISTS search_rankings (
+                   user_id INTEGER PRIMARY KEY,
+
+                   title REAL NOT NULL,
+                   aliases REAL NOT NULL,
+                   body REAL NOT NULL,
+                   impact REAL NOT NULL,
+                   insignia REAL NOT NULL,
+                   visits REAL NOT NULL,
+                   recency REAL NOT NULL,
+
+                   FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE ON UPDATE NO ACTION
+               );"),
+
+        ///////////////////
+        // user_version 43: full text indexes ignore diacritics, a trigram index of notes
+        //                  and the language that each user searches in
+        ///////////////////
+        M::up("ALTER TABLE users ADD COLUMN search_language TEXT NOT NULL DEFAULT 'en';
+
+               -- the triggers that keep these indexes up to date refer to them by name
+               -- so they carry on working once the tables are re-created
+               DROP TABLE IF EXISTS decks_fts;
+               CREATE VIRTUAL TABLE decks_fts USING fts5(name, aliases, content='decks_fts_content', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO decks_fts(decks_fts) VALUES('rebuild');
+
+               DROP TABLE IF EXISTS decks_trigram;
+               CREATE VIRTUAL TABLE decks_trigram USING fts5(name, aliases, content='decks_fts_content', content_rowid='id', tokenize='trigram remove_diacritics 1');
+               INSERT INTO decks_trigram(decks_trigram) VALUES('rebuild');
+
+               DROP TABLE IF EXISTS points_fts;
+               CREATE VIRTUAL TABLE points_fts USING fts5(title, location_textual, date_textual, content='points', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO points_fts(points_fts) VALUES('rebuild');
+
+               DROP TABLE IF EXISTS notes_fts;
+               CREATE VIRTUAL TABLE notes_fts USING fts5(content, content='notes', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO notes_fts(notes_fts) VALUES('rebuild');
+
+               DROP TABLE IF EXISTS article_extras_fts;
+               CREATE VIRTUAL TABLE article_extras_fts USING fts5(source, author, short_description, content='article_extras', content_rowid='deck_id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO article_extras_fts(article_extras_fts) VALUES('rebuild');
+
+               DROP TABLE IF EXISTS refs_fts;
+               CREATE VIRTUAL TABLE refs_fts USING fts5(annotation, note_id UNINDEXED, deck_id UNINDEXED, tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO refs_fts(annotation, note_id, deck_id)
+                   SELECT annotation, note_id, deck_id FROM refs WHERE annotation IS NOT NULL;
+
+               DROP TABLE IF EXISTS dialogue_messages_fts;
+               CREATE VIRTUAL TABLE dialogue_messages_fts USING fts5(content, content='dialogue_messages', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO dialogue_messages_fts(dialogue_messages_fts) VALUES('rebuild');
+
+               CREATE VIRTUAL TABLE notes_trigram USING fts5(content, content='notes', content_rowid='id', tokenize='trigram remove_diacritics 1');
+               INSERT INTO notes_trigram(notes_trigram) VALUES('rebuild');
+
+               CREATE TRIGGER notes_trigram_ai AFTER INSERT ON notes BEGIN
+                   INSERT INTO notes_trigram(rowid, content) VALUES (new.id, new.content);
+               END;
+               CREATE TRIGGER notes_trigram_ad AFTER DELETE ON notes BEGIN
+                   INSERT INTO notes_trigram(notes_trigram, rowid, content) VALUES('delete', old.id, old.content);
+               END;
+               CREATE TRIGGER notes_trigram_au AFTER UPDATE ON notes BEGIN
+                   INSERT INTO notes_trigram(notes_trigram, rowid, content) VALUES('delete', old.id, old.content);
+                   INSERT INTO notes_trigram(rowid, content) VALUES (new.id, new.content);
+               END;"),
+
+        ///////////////////
+        // user_version 44: key refs_fts by a rowid so that its triggers don't scan the whole index
+        ///////////////////
+        M::up("DROP TRIGGER IF EXISTS refs_fts_ai;
+               DROP TRIGGER IF EXISTS refs_fts_ad;
+               DROP TRIGGER IF EXISTS refs_fts_au;
+               DROP TABLE IF EXISTS refs_fts;
+
+               CREATE TABLE IF NOT EXISTS refs_fts_keys (
+                   id INTEGER PRIMARY KEY,
+                   note_id INTEGER NOT NULL,
+                   deck_id INTEGER NOT NULL,
+
+                   UNIQUE(note_id, deck_id)
+               );
+               INSERT INTO refs_fts_keys(note_id, deck_id)
+                   SELECT note_id, deck_id FROM refs WHERE annotation IS NOT NULL;
+
+               CREATE VIEW IF NOT EXISTS refs_fts_content AS
+                   SELECT k.id AS id,
+                          r.annotation AS annotation
+                   FROM refs_fts_keys k, refs r
+                   WHERE r.note_id = k.note_id AND r.deck_id = k.deck_id;
+
+               CREATE VIRTUAL TABLE refs_fts USING fts5(annotation, content='refs_fts_content', content_rowid='id', tokenize='porter unicode61 remove_diacritics 2', prefix='2 3 4 5 6');
+               INSERT INTO refs_fts(refs_fts) VALUES('rebuild');
+
+               CREATE TRIGGER refs_fts_ai AFTER INSERT ON refs WHEN new.annotation IS NOT NULL BEGIN
+                   INSERT INTO refs_fts_keys(note_id, deck_id) VALUES (new.note_id, new.deck_id);
+                   INSERT INTO refs_fts(rowid, annotation)
+                       SELECT id, new.annotation FROM refs_fts_keys WHERE note_id = new.note_id AND deck_id = new.deck_id;
+               END;
+               CREATE TRIGGER refs_fts_ad AFTER DELETE ON refs WHEN old.annotation IS NOT NULL BEGIN
+                   INSERT INTO refs_fts(refs_fts, rowid, annotation)
+                       SELECT 'delete', id, old.annotation FROM refs_fts_keys WHERE note_id = old.note_id AND deck_id = old.deck_id;
+                   DELETE FROM refs_fts_keys WHERE note_id = old.note_id AND deck_id = old.deck_id;
+               END;
+               CREATE TRIGGER refs_fts_au AFTER UPDATE OF annotation, note_id, deck_id ON refs BEGIN
+                   INSERT INTO refs_fts(refs_fts, rowid, annotation)
+                       SELECT 'delete', id, old.annotation FROM refs_fts_keys WHERE note_id = old.note_id AND deck_id = old.deck_id;
+                   DELETE FROM refs_fts_keys WHERE note_id = old.note_id AND deck_id = old.deck_id;
+                   INSERT INTO refs_fts_keys(note_id, deck_id)
+                       SELECT new.note_id, new.deck_id WHERE new.annotation IS NOT NULL;
+                   INSERT INTO refs_fts(rowid, annotation)
+                       SELECT id, new.annotation FROM refs_fts_keys WHERE new.annotation IS NOT NULL AND note_id = new.note_id AND deck_id = new.deck_id;
+               END;"),
     ]);
 
     let mut conn = Connection::open(db_name)?;
@@ -912,8 +1606,15 @@ pub fn migration_check(db_name: &str) -> crate::Result<()> {
     // Apply some PRAGMA, often better to do it outside of migrations
     conn.pragma_update(None, "journal_mode", "WAL")?;
 
+    let from_version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
     // Update the database schema, atomically
     migrations.to_latest(&mut conn)?;
 
+    let to_version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if to_version != from_version {
+        crate::db::audit::record_migration(&conn, from_version, to_version)?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,242 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::decks::SlimDeck;
+use crate::interop::memorise::FlashCard;
+use crate::interop::sync::{
+    SyncChange, SyncData, SyncDelta, SyncEntity, SyncNote, SyncPoint, SyncRef,
+};
+use rusqlite::{Connection, Row, named_params};
+
+// the most changes that are returned by a single delta, clients
+// keep asking until has_more is false
+//
+const MAX_CHANGES: usize = 500;
+
+struct Changed {
+    seq: i64,
+    entity: SyncEntity,
+    id: Key,
+}
+
+impl FromRow for Changed {
+    fn from_row(row: &Row) -> rusqlite::Result<Changed> {
+        Ok(Changed {
+            seq: row.get("seq")?,
+            entity: row.get("entity")?,
+            id: row.get("entity_id")?,
+        })
+    }
+}
+
+impl FromRow for SyncNote {
+    fn from_row(row: &Row) -> rusqlite::Result<SyncNote> {
+        Ok(SyncNote {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            prev_note_id: row.get("prev_note_id")?,
+            point_id: row.get("point_id")?,
+            kind: row.get("kind")?,
+            content: row.get("content")?,
+            font: row.get("font")?,
+            private: row.get("private")?,
+        })
+    }
+}
+
+impl FromRow for SyncRef {
+    fn from_row(row: &Row) -> rusqlite::Result<SyncRef> {
+        Ok(SyncRef {
+            deck_id: row.get("deck_id")?,
+            ref_kind: row.get("kind")?,
+            annotation: row.get("annotation")?,
+        })
+    }
+}
+
+impl FromRow for SyncPoint {
+    fn from_row(row: &Row) -> rusqlite::Result<SyncPoint> {
+        Ok(SyncPoint {
+            id: row.get("id")?,
+            deck_id: row.get("deck_id")?,
+            kind: row.get("kind")?,
+            title: row.get("title")?,
+            font: row.get("font")?,
+
+            location_textual: row.get("location_textual")?,
+            longitude: row.get("longitude")?,
+            latitude: row.get("latitude")?,
+            location_fuzz: row.get("location_fuzz")?,
+
+            date_textual: row.get("date_textual")?,
+            exact_date: row.get("exact_date")?,
+            lower_date: row.get("lower_date")?,
+            upper_date: row.get("upper_date")?,
+            date_fuzz: row.get("date_fuzz")?,
+        })
+    }
+}
+
+pub(crate) fn last_seq(conn: &Connection, user_id: Key) -> Result<i64, DbError> {
+    sqlite::one(
+        conn,
+        "SELECT coalesce(max(seq), 0) FROM sync_changes WHERE user_id = :user_id",
+        named_params! {":user_id": user_id},
+    )
+}
+
+pub(crate) fn get_delta(conn: &Connection, user_id: Key, since: i64) -> Result<SyncDelta, DbError> {
+    let mut changed: Vec<Changed> = sqlite::many(
+        conn,
+        "SELECT seq, entity, entity_id
+         FROM sync_changes
+         WHERE user_id = :user_id AND seq > :since
+         ORDER BY seq
+         LIMIT :limit",
+        named_params! {":user_id": user_id, ":since": since, ":limit": MAX_CHANGES + 1},
+    )?;
+
+    let has_more = changed.len() > MAX_CHANGES;
+    changed.truncate(MAX_CHANGES);
+
+    let seq = changed.last().map_or(since, |c| c.seq);
+
+    let mut changes = Vec::with_capacity(changed.len());
+    for c in changed {
+        changes.push(SyncChange {
+            seq: c.seq,
+            entity: c.entity,
+            id: c.id,
+            data: current_data(conn, user_id, c.entity, c.id)?,
+        });
+    }
+
+    Ok(SyncDelta {
+        seq,
+        changes,
+        has_more,
+    })
+}
+
+// the latest change to a single entity, None if the user has never had it
+//
+pub(crate) fn get_change(
+    conn: &Connection,
+    user_id: Key,
+    entity: SyncEntity,
+    id: Key,
+) -> Result<Option<SyncChange>, DbError> {
+    let seq: Option<i64> = sqlite::one_optional(
+        conn,
+        "SELECT seq
+         FROM sync_changes
+         WHERE user_id = :user_id AND entity = :entity AND entity_id = :entity_id",
+        named_params! {":user_id": user_id, ":entity": entity, ":entity_id": id},
+    )?;
+
+    match seq {
+        Some(seq) => Ok(Some(SyncChange {
+            seq,
+            entity,
+            id,
+            data: current_data(conn, user_id, entity, id)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn note_ids_in_deck(conn: &Connection, deck_id: Key) -> Result<Vec<Key>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id FROM notes WHERE deck_id = :deck_id",
+        named_params! {":deck_id": deck_id},
+    )
+}
+
+// None when the entity no longer exists
+//
+fn current_data(
+    conn: &Connection,
+    user_id: Key,
+    entity: SyncEntity,
+    id: Key,
+) -> Result<Option<SyncData>, DbError> {
+    let params = named_params! {":user_id": user_id, ":id": id};
+
+    Ok(match entity {
+        SyncEntity::Deck => sqlite::one_optional::<SlimDeck, _>(
+            conn,
+            "SELECT id, name, kind, created_at, graph_terminator, insignia, font, impact
+             FROM decks
+             WHERE id = :id AND user_id = :user_id",
+            params,
+        )?
+        .map(SyncData::Deck),
+        SyncEntity::Note => sqlite::one_optional::<SyncNote, _>(
+            conn,
+            "SELECT id, deck_id, prev_note_id, point_id, kind, content, font, private
+             FROM notes
+             WHERE id = :id AND user_id = :user_id",
+            params,
+        )?
+        .map(SyncData::Note),
+        SyncEntity::Ref => {
+            let note_id: Option<Key> = sqlite::one_optional(
+                conn,
+                "SELECT id FROM notes WHERE id = :id AND user_id = :user_id",
+                params,
+            )?;
+            if note_id.is_some() {
+                let refs: Vec<SyncRef> = sqlite::many(
+                    conn,
+                    "SELECT deck_id, kind, annotation
+                     FROM refs
+                     WHERE note_id = :id
+                     ORDER BY deck_id",
+                    named_params! {":id": id},
+                )?;
+                Some(SyncData::Refs(refs))
+            } else {
+                None
+            }
+        }
+        SyncEntity::Point => sqlite::one_optional::<SyncPoint, _>(
+            conn,
+            "SELECT p.id, p.deck_id, p.kind, p.title, p.font,
+                    p.location_textual, p.longitude, p.latitude, p.location_fuzz,
+                    p.date_textual, date(p.exact_realdate) AS exact_date,
+                    date(p.lower_realdate) AS lower_date, date(p.upper_realdate) AS upper_date,
+                    p.date_fuzz
+             FROM points p
+                  INNER JOIN decks d ON d.id = p.deck_id
+             WHERE p.id = :id AND d.user_id = :user_id",
+            params,
+        )?
+        .map(SyncData::Point),
+        SyncEntity::Card => sqlite::one_optional::<FlashCard, _>(
+            conn,
+            "SELECT id, note_id, prompt, next_test_date, easiness_factor, interval, repetition
+             FROM cards
+             WHERE id = :id AND user_id = :user_id",
+            params,
+        )?
+        .map(SyncData::Card),
+    })
+}
@@ -0,0 +1,455 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::DbError;
+use crate::db::audit::{self as audit_db, Payload};
+use crate::db::sqlite::{self, FromRow};
+use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::tags as interop;
+use rusqlite::{Row, named_params};
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+// restricts a decklike query (decks aliased as d) to decks tagged with :tag_id,
+// a NULL :tag_id leaves the query unfiltered
+//
+pub(crate) const DECK_TAG_FILTER: &str =
+    "(:tag_id IS NULL OR d.id IN (SELECT deck_id FROM deck_tags WHERE tag_id = :tag_id))";
+
+impl FromRow for interop::Tag {
+    fn from_row(row: &Row) -> rusqlite::Result<interop::Tag> {
+        Ok(interop::Tag {
+            id: row.get("id")?,
+            name: row.get("name")?,
+        })
+    }
+}
+
+pub(crate) fn get_tags(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+) -> Result<Vec<interop::Tag>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT id, name
+         FROM tags
+         WHERE user_id = :user_id
+         ORDER BY name",
+        named_params! {":user_id": user_id},
+    )
+}
+
+// returns the existing tag if the user already has one with the given name
+//
+pub(crate) fn get_or_create_tag(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    name: &str,
+) -> Result<interop::Tag, DbError> {
+    sqlite::zero(
+        conn,
+        "INSERT OR IGNORE INTO tags(user_id, name) VALUES (:user_id, :name)",
+        named_params! {":user_id": user_id, ":name": name},
+    )?;
+    let created = conn.changes() > 0;
+
+    let tag: interop::Tag = sqlite::one(
+        conn,
+        "SELECT id, name FROM tags WHERE user_id = :user_id AND name = :name",
+        named_params! {":user_id": user_id, ":name": name},
+    )?;
+
+    if created {
+        audit_db::record(
+            conn,
+            user_id,
+            AuditAction::Create,
+            AuditEntity::Tag,
+            Some(tag.id),
+            Payload::after(&tag),
+        )?;
+    }
+
+    Ok(tag)
+}
+
+// renaming a tag to the name of another of the user's tags is ignored
+//
+pub(crate) fn edit_tag(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    tag_id: Key,
+    name: &str,
+) -> Result<Option<interop::Tag>, DbError> {
+    let before = get_tag(conn, user_id, tag_id)?;
+
+    sqlite::zero(
+        conn,
+        "UPDATE OR IGNORE tags
+         SET name = :name
+         WHERE user_id = :user_id AND id = :tag_id",
+        named_params! {":user_id": user_id, ":tag_id": tag_id, ":name": name},
+    )?;
+    let changed = conn.changes() > 0;
+
+    let after = get_tag(conn, user_id, tag_id)?;
+
+    if changed {
+        audit_db::record(
+            conn,
+            user_id,
+            AuditAction::Edit,
+            AuditEntity::Tag,
+            Some(tag_id),
+            Payload::before(&before).with_after(&after),
+        )?;
+    }
+
+    Ok(after)
+}
+
+fn get_tag(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    tag_id: Key,
+) -> Result<Option<interop::Tag>, DbError> {
+    sqlite::one_optional(
+        conn,
+        "SELECT id, name FROM tags WHERE user_id = :user_id AND id = :tag_id",
+        named_params! {":user_id": user_id, ":tag_id": tag_id},
+    )
+}
+
+pub(crate) fn delete_tag(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    tag_id: Key,
+) -> Result<(), DbError> {
+    let before = get_tag(conn, user_id, tag_id)?;
+
+    sqlite::zero(
+        conn,
+        "DELETE FROM tags WHERE user_id = :user_id AND id = :tag_id",
+        named_params! {":user_id": user_id, ":tag_id": tag_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::Tag,
+        Some(tag_id),
+        Payload::before(&before),
+    )
+}
+
+pub(crate) fn get_deck_tags(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+) -> Result<Vec<interop::Tag>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT t.id, t.name
+         FROM tags t, deck_tags dt
+         WHERE dt.tag_id = t.id AND dt.deck_id = :deck_id AND t.user_id = :user_id
+         ORDER BY t.name",
+        named_params! {":user_id": user_id, ":deck_id": deck_id},
+    )
+}
+
+pub(crate) fn add_deck_tag(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    name: &str,
+) -> Result<(), DbError> {
+    let tx = conn.transaction()?;
+
+    let tag = get_or_create_tag(&tx, user_id, name)?;
+    sqlite::zero(
+        &tx,
+        "INSERT OR IGNORE INTO deck_tags(deck_id, tag_id)
+         SELECT id, :tag_id FROM decks WHERE user_id = :user_id AND id = :deck_id",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":tag_id": tag.id},
+    )?;
+
+    audit_db::record_if_changed(
+        &tx,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::DeckTag,
+        Some(deck_id),
+        Payload::after(&tag),
+    )?;
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+pub(crate) fn remove_deck_tag(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    deck_id: Key,
+    tag_id: Key,
+) -> Result<(), DbError> {
+    sqlite::zero(
+        conn,
+        "DELETE FROM deck_tags
+         WHERE deck_id = :deck_id
+               AND tag_id = (SELECT id FROM tags WHERE user_id = :user_id AND id = :tag_id)",
+        named_params! {":user_id": user_id, ":deck_id": deck_id, ":tag_id": tag_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::DeckTag,
+        Some(deck_id),
+        Payload::before(&get_tag(conn, user_id, tag_id)?),
+    )
+}
+
+pub(crate) fn get_note_tags(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+) -> Result<Vec<interop::Tag>, DbError> {
+    sqlite::many(
+        conn,
+        "SELECT t.id, t.name
+         FROM tags t, note_tags nt
+         WHERE nt.tag_id = t.id AND nt.note_id = :note_id AND t.user_id = :user_id
+         ORDER BY t.name",
+        named_params! {":user_id": user_id, ":note_id": note_id},
+    )
+}
+
+pub(crate) fn add_note_tag(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+    name: &str,
+) -> Result<(), DbError> {
+    let tx = conn.transaction()?;
+
+    let tag = get_or_create_tag(&tx, user_id, name)?;
+    sqlite::zero(
+        &tx,
+        "INSERT OR IGNORE INTO note_tags(note_id, tag_id)
+         SELECT id, :tag_id FROM notes WHERE user_id = :user_id AND id = :note_id",
+        named_params! {":user_id": user_id, ":note_id": note_id, ":tag_id": tag.id},
+    )?;
+
+    audit_db::record_if_changed(
+        &tx,
+        user_id,
+        AuditAction::Create,
+        AuditEntity::NoteTag,
+        Some(note_id),
+        Payload::after(&tag),
+    )?;
+
+    tx.commit()?;
+
+    Ok(())
+}
+
+pub(crate) fn remove_note_tag(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    note_id: Key,
+    tag_id: Key,
+) -> Result<(), DbError> {
+    sqlite::zero(
+        conn,
+        "DELETE FROM note_tags
+         WHERE note_id = :note_id
+               AND tag_id = (SELECT id FROM tags WHERE user_id = :user_id AND id = :tag_id)",
+        named_params! {":user_id": user_id, ":note_id": note_id, ":tag_id": tag_id},
+    )?;
+
+    audit_db::record_if_changed(
+        conn,
+        user_id,
+        AuditAction::Delete,
+        AuditEntity::NoteTag,
+        Some(note_id),
+        Payload::before(&get_tag(conn, user_id, tag_id)?),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, RECIPIENT, add_note, add_ref, create_deck, test_db};
+    use crate::db::{decks, people};
+    use crate::interop::decks::{DeckKind, Pagination, SlimDeck};
+
+    fn names(tags: &[interop::Tag]) -> Vec<&str> {
+        tags.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    // decks created in the same second have no order, so the ids are sorted
+    fn ids(page: Pagination<SlimDeck>) -> Vec<Key> {
+        let mut ids: Vec<Key> = page.items.into_iter().map(|d| d.id).collect();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    #[test]
+    fn test_tag_crud() {
+        let mut db = test_db("tag-crud");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "the dichotomy of control");
+
+        let hellenistic = get_or_create_tag(&db.conn, OWNER, "hellenistic").unwrap();
+        assert_eq!(
+            get_or_create_tag(&db.conn, OWNER, "hellenistic")
+                .unwrap()
+                .id,
+            hellenistic.id
+        );
+        let ethics = get_or_create_tag(&db.conn, OWNER, "ethics").unwrap();
+        assert_eq!(
+            names(&get_tags(&db.conn, OWNER).unwrap()),
+            vec!["ethics", "hellenistic"]
+        );
+        assert!(get_tags(&db.conn, RECIPIENT).unwrap().is_empty());
+
+        let renamed = edit_tag(&db.conn, OWNER, hellenistic.id, "ancient")
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed.name, "ancient");
+        // a rename onto another tag's name is ignored
+        let unchanged = edit_tag(&db.conn, OWNER, hellenistic.id, "ethics")
+            .unwrap()
+            .unwrap();
+        assert_eq!(unchanged.name, "ancient");
+        assert!(
+            edit_tag(&db.conn, RECIPIENT, hellenistic.id, "mine")
+                .unwrap()
+                .is_none()
+        );
+
+        add_deck_tag(&mut db.conn, OWNER, deck_id, "ancient").unwrap();
+        add_deck_tag(&mut db.conn, OWNER, deck_id, "ancient").unwrap();
+        add_note_tag(&mut db.conn, OWNER, note_id, "ethics").unwrap();
+        assert_eq!(
+            names(&get_deck_tags(&db.conn, OWNER, deck_id).unwrap()),
+            vec!["ancient"]
+        );
+        assert_eq!(
+            names(&get_note_tags(&db.conn, OWNER, note_id).unwrap()),
+            vec!["ethics"]
+        );
+
+        // another user can't tag the owner's decks or notes
+        add_deck_tag(&mut db.conn, RECIPIENT, deck_id, "stolen").unwrap();
+        add_note_tag(&mut db.conn, RECIPIENT, note_id, "stolen").unwrap();
+        assert_eq!(get_deck_tags(&db.conn, OWNER, deck_id).unwrap().len(), 1);
+        assert_eq!(get_note_tags(&db.conn, OWNER, note_id).unwrap().len(), 1);
+
+        remove_note_tag(&db.conn, OWNER, note_id, ethics.id).unwrap();
+        assert!(get_note_tags(&db.conn, OWNER, note_id).unwrap().is_empty());
+
+        // deleting a tag removes it from its decks
+        delete_tag(&db.conn, OWNER, hellenistic.id).unwrap();
+        assert!(get_deck_tags(&db.conn, OWNER, deck_id).unwrap().is_empty());
+        assert_eq!(names(&get_tags(&db.conn, OWNER).unwrap()), vec!["ethics"]);
+    }
+
+    #[test]
+    fn test_deck_tag_filter() {
+        let mut db = test_db("deck-tag-filter");
+        let tagged = create_deck(&db.conn, "idea", "Stoicism");
+        let untagged = create_deck(&db.conn, "idea", "Epicureanism");
+        let seneca = create_deck(&db.conn, "person", "Seneca");
+        let cicero = create_deck(&db.conn, "person", "Cicero");
+        db.conn
+            .execute_batch(&format!(
+                "UPDATE decks SET impact = 1, insignia = 2;
+                 INSERT INTO points(deck_id, title, kind, exact_realdate)
+                 VALUES ({seneca}, 'Born', 'point_begin', julianday('-0003-01-01')),
+                        ({cicero}, 'Born', 'point_begin', julianday('-0105-01-03'));"
+            ))
+            .unwrap();
+        add_deck_tag(&mut db.conn, OWNER, tagged, "stoa").unwrap();
+        add_deck_tag(&mut db.conn, OWNER, seneca, "stoa").unwrap();
+        let tag = Some(get_or_create_tag(&db.conn, OWNER, "stoa").unwrap().id);
+
+        let idea = DeckKind::Idea;
+        assert_eq!(
+            ids(decks::pagination(&db.conn, OWNER, idea, 0, 10, tag).unwrap()),
+            vec![tagged]
+        );
+        assert_eq!(
+            ids(decks::paginated_recents(&db.conn, OWNER, idea, 0, 10, tag).unwrap()),
+            vec![tagged]
+        );
+        assert_eq!(
+            ids(decks::paginated_orphans(&db.conn, OWNER, idea, 0, 10, tag).unwrap()),
+            vec![tagged]
+        );
+        assert_eq!(
+            ids(decks::paginated_rated(&db.conn, OWNER, idea, 0, 10, tag).unwrap()),
+            vec![tagged]
+        );
+        assert_eq!(
+            ids(decks::paginated_unnoted(&db.conn, OWNER, idea, 0, 10, tag).unwrap()),
+            vec![tagged]
+        );
+        assert_eq!(
+            ids(decks::insignia_filter(&db.conn, OWNER, idea, 2, 0, 10, tag).unwrap()),
+            vec![tagged]
+        );
+        assert_eq!(
+            ids(decks::insignia_filter_any(&db.conn, OWNER, 2, 0, 10, tag).unwrap()),
+            vec![tagged, seneca]
+        );
+        assert_eq!(
+            ids(people::paginated_ancient(&db.conn, OWNER, 0, 10, tag).unwrap()),
+            vec![seneca]
+        );
+
+        // the total is of the tagged decks
+        let page = decks::paginated_recents(&db.conn, OWNER, idea, 0, 10, tag).unwrap();
+        assert_eq!(page.total_items, 1);
+
+        // without a tag every deck is listed
+        let page = decks::paginated_recents(&db.conn, OWNER, idea, 0, 10, None).unwrap();
+        assert_eq!(ids(page), vec![tagged, untagged]);
+        assert_eq!(
+            ids(people::paginated_ancient(&db.conn, OWNER, 0, 10, None).unwrap()),
+            vec![seneca, cicero]
+        );
+
+        // a deck that's referenced is no longer an orphan
+        let note_id = add_note(&mut db.conn, untagged, "a rival school");
+        add_ref(&db.conn, note_id, tagged);
+        assert!(
+            decks::paginated_orphans(&db.conn, OWNER, idea, 0, 10, tag)
+                .unwrap()
+                .items
+                .is_empty()
+        );
+    }
+}
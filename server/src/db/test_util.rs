@@ -0,0 +1,132 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// fixtures shared by the db tests: a migrated database with two users and
+// helpers for building decks, notes and refs
+
+use crate::db::SqlitePool;
+use crate::db::notes::{create_notes, notes_for_deck};
+use crate::db::sqlite;
+use crate::db::sqlite_migrations;
+use crate::interop::Key;
+use crate::interop::font::Font;
+use crate::interop::notes::{NoteKind, ProtoNote};
+use rusqlite::{Connection, named_params};
+
+pub(crate) const OWNER: Key = Key(1);
+pub(crate) const RECIPIENT: Key = Key(2);
+
+pub(crate) struct TestDb {
+    path: std::path::PathBuf,
+    pub(crate) conn: Connection,
+}
+
+impl TestDb {
+    // a pool over the same file, for code that runs through db_thread
+    pub(crate) fn pool(&self) -> SqlitePool {
+        r2d2::Pool::new(r2d2_sqlite::SqliteConnectionManager::file(&self.path)).unwrap()
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub(crate) fn test_db(name: &str) -> TestDb {
+    let path = std::env::temp_dir().join(format!("civil-{}-{}.db", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    sqlite_migrations::migration_check(path.to_str().unwrap()).unwrap();
+
+    let conn = Connection::open(&path).unwrap();
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         INSERT INTO users(email, username, password) VALUES ('owner@example.com', 'owner', 'x');
+         INSERT INTO users(email, username, password) VALUES ('friend@example.com', 'friend', 'x');",
+    )
+    .unwrap();
+
+    TestDb { path, conn }
+}
+
+pub(crate) fn create_deck(conn: &Connection, kind: &str, title: &str) -> Key {
+    sqlite::one(
+        conn,
+        "INSERT INTO decks(user_id, kind, name, graph_terminator)
+         VALUES (:user_id, :kind, :name, FALSE)
+         RETURNING id",
+        named_params! {":user_id": OWNER, ":kind": kind, ":name": title},
+    )
+    .unwrap()
+}
+
+// appends a note to the deck's passage, returning its id
+pub(crate) fn add_note(conn: &mut Connection, deck_id: Key, content: &str) -> Key {
+    let prev_note_id = notes_for_deck(conn, deck_id)
+        .unwrap()
+        .into_iter()
+        .filter(|n| n.kind == NoteKind::Note && n.point_id.is_none())
+        .max_by_key(|n| n.id.0)
+        .map(|n| n.id);
+    let notes = create_notes(
+        conn,
+        OWNER,
+        ProtoNote {
+            kind: NoteKind::Note,
+            font: Font::Serif,
+            content: vec![content.to_string()],
+            deck_id,
+            point_id: None,
+            prev_note_id,
+            next_note_id: None,
+        },
+    )
+    .unwrap();
+    notes.last().unwrap().id
+}
+
+pub(crate) fn add_ref(conn: &Connection, note_id: Key, deck_id: Key) {
+    conn.execute(
+        "INSERT INTO refs(note_id, deck_id, kind) VALUES (?1, ?2, 'ref')",
+        rusqlite::params![note_id, deck_id],
+    )
+    .unwrap();
+}
+
+// the contents of a passage in the order given by its prev_note_id links,
+// panics if the passage doesn't form a single chain
+pub(crate) fn passage(conn: &Connection, deck_id: Key, point_id: Option<Key>) -> Vec<String> {
+    let notes: Vec<_> = notes_for_deck(conn, deck_id)
+        .unwrap()
+        .into_iter()
+        .filter(|n| n.kind == NoteKind::Note && n.point_id == point_id)
+        .collect();
+
+    let heads = notes.iter().filter(|n| n.prev_note_id.is_none()).count();
+    assert!(heads <= 1, "passage has {heads} heads");
+
+    let mut contents = vec![];
+    let mut prev_note_id = None;
+    while let Some(note) = notes.iter().find(|n| n.prev_note_id == prev_note_id) {
+        contents.push(note.content.clone());
+        prev_note_id = Some(note.id);
+    }
+    assert_eq!(contents.len(), notes.len(), "passage isn't a single chain");
+
+    contents
+}
@@ -16,12 +16,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::db::DbError;
+use crate::db::audit as audit_db;
 use crate::db::decks;
 use crate::db::notes as notes_db;
 use crate::db::points as points_db;
 use crate::db::qry::Qry;
 use crate::db::sqlite::{self, FromRow};
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::decks::{DeckKind, ProtoSlimDeck};
 use crate::interop::font::Font;
 use crate::interop::timelines::Timeline;
@@ -38,6 +40,7 @@ impl FromRow for Timeline {
             insignia: row.get("insignia")?,
             font: row.get("font")?,
             impact: row.get("impact")?,
+            version: row.get("version")?,
 
             points: vec![],
             notes: vec![],
@@ -111,9 +114,12 @@ pub(crate) fn edit(
     user_id: Key,
     timeline: ProtoSlimDeck,
     timeline_id: Key,
+    version: i32,
 ) -> Result<Timeline, DbError> {
     let tx = conn.transaction()?;
 
+    let before = audit_db::deck_snapshot(&tx, user_id, timeline_id)?;
+
     let deck = decks::deckbase_edit(
         &tx,
         user_id,
@@ -124,8 +130,11 @@ pub(crate) fn edit(
         timeline.insignia,
         timeline.font,
         timeline.impact,
+        version,
     )?;
 
+    audit_db::record_deck_change(&tx, user_id, AuditAction::Edit, timeline_id, &before)?;
+
     tx.commit()?;
 
     let mut timeline: Timeline = deck.into();
@@ -15,9 +15,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::sqlite::{self, FromRow};
 use crate::db::{DbError, SqlitePool};
 use crate::interop::Key;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::search::Language;
 use crate::interop::users::{LoginCredentials, Registration, User, UserId};
 use rusqlite::{Row, named_params};
 use tracing::info;
@@ -92,7 +95,7 @@ pub(crate) fn create(
 ) -> Result<(Key, User), DbError> {
     info!("create");
 
-    sqlite::one(
+    let (id, user): (Key, User) = sqlite::one(
         &conn,
         r#"
            insert into users (email, username, password, ui_config_json)
@@ -105,6 +108,34 @@ pub(crate) fn create(
             ":password": hash,
             ":ui_config_json": registration.ui_config
         },
+    )?;
+
+    audit_db::record(
+        conn,
+        id,
+        AuditAction::Create,
+        AuditEntity::User,
+        Some(id),
+        Payload::after(&serde_json::json!({ "username": user.username, "email": user.email })),
+    )?;
+
+    Ok((id, user))
+}
+
+// logging in and out
+//
+pub(crate) fn record_session_event(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    action: AuditAction,
+) -> Result<(), DbError> {
+    audit_db::record(
+        conn,
+        user_id,
+        action,
+        AuditEntity::User,
+        Some(user_id),
+        Payload::none(),
     )
 }
 
@@ -132,9 +163,65 @@ pub(crate) fn edit_ui_config(
         named_params! {":user_id": user_id, ":config": ui_config_json},
     )?;
 
+    audit_db::record(
+        conn,
+        user_id,
+        AuditAction::Edit,
+        AuditEntity::User,
+        Some(user_id),
+        Payload::after(&serde_json::json!({ "uiConfig": ui_config_json })),
+    )?;
+
     Ok(true)
 }
 
+impl FromRow for Language {
+    fn from_row(row: &Row) -> rusqlite::Result<Language> {
+        row.get("search_language")
+    }
+}
+
+pub(crate) fn get_search_language(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+) -> Result<Language, DbError> {
+    let language: Option<Language> = sqlite::one_optional(
+        conn,
+        "SELECT search_language FROM users WHERE id = :user_id",
+        named_params! {":user_id": user_id},
+    )?;
+
+    Ok(language.unwrap_or_default())
+}
+
+pub(crate) fn edit_search_language(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    language: Language,
+) -> Result<Language, DbError> {
+    let before = get_search_language(conn, user_id)?;
+
+    sqlite::zero(
+        conn,
+        "UPDATE users SET search_language = :language WHERE id = :user_id",
+        named_params! {":user_id": user_id, ":language": language},
+    )?;
+
+    if before != language {
+        audit_db::record(
+            conn,
+            user_id,
+            AuditAction::Edit,
+            AuditEntity::User,
+            Some(user_id),
+            Payload::before(&serde_json::json!({ "searchLanguage": before }))
+                .with_after(&serde_json::json!({ "searchLanguage": language })),
+        )?;
+    }
+
+    Ok(language)
+}
+
 impl FromRow for UserId {
     fn from_row(row: &Row) -> rusqlite::Result<UserId> {
         Ok(UserId { id: row.get("id")? })
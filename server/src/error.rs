@@ -15,7 +15,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::DbError;
 use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use tracing::error;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -35,6 +37,27 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error("not found")]
     NotFound,
+    #[error("a deck cannot be merged into itself")]
+    MergeIntoSelf,
+    #[error("a tag requires a name")]
+    EmptyTagName,
+    #[error("there are no notes that can be summarized")]
+    NothingToSummarize,
+    #[error("a deck cannot be shared with its owner")]
+    ShareWithSelf,
+    #[error("admin access required")]
+    NotAdmin,
+    #[error("the item has been changed since it was last read")]
+    VersionConflict(serde_json::Value),
+    #[error("an If-Match version is required")]
+    PreconditionRequired,
+    #[error("{message}")]
+    InvalidRequest {
+        message: String,
+        field: Option<String>,
+    },
+    #[error("unknown fts command")]
+    InvalidFtsCommand,
     #[error("invalid string to DeckKind conversion")]
     InvalidStringToDeckKindConversion,
     #[error(transparent)]
@@ -76,22 +99,148 @@ pub enum Error {
     #[error("sqlite string conversion error")]
     SqliteStringConversion,
     #[error(transparent)]
-    Db(#[from] crate::db::DbError),
+    Db(#[from] DbError),
 }
 
-impl ResponseError for Error {
-    fn status_code(&self) -> StatusCode {
+// the json body of every error returned by the api
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorBody {
+    pub code: &'static str, // stable and machine readable, e.g. "version_conflict"
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<serde_json::Value>, // the server's copy after a version conflict
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ErrorBody {
+    // for errors that didn't come from a handler, e.g. a route that doesn't exist
+    pub fn for_status(status: StatusCode) -> ErrorBody {
+        let code = match status {
+            StatusCode::BAD_REQUEST => "invalid_request",
+            StatusCode::UNAUTHORIZED => "unauthenticated",
+            StatusCode::FORBIDDEN => "forbidden",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+            StatusCode::CONFLICT => "conflict",
+            StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+            _ if status.is_server_error() => "internal",
+            _ => "error",
+        };
+
+        ErrorBody {
+            code,
+            message: status.canonical_reason().unwrap_or("error").to_lowercase(),
+            fields: vec![],
+            current: None,
+        }
+    }
+}
+
+impl Error {
+    fn classify(&self) -> (StatusCode, &'static str) {
         match self {
-            Error::NotFound => StatusCode::NOT_FOUND,
-            Error::Authenticating => StatusCode::UNAUTHORIZED,
-            Error::Registration | Error::BadUpload => StatusCode::BAD_REQUEST,
-            Error::TooManyFound => StatusCode::CONFLICT,
-            Error::ExternalServerError => StatusCode::BAD_GATEWAY,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::NotFound
+            | Error::Db(DbError::NotFound)
+            | Error::Sqlite(rusqlite::Error::QueryReturnedNoRows)
+            | Error::Db(DbError::Sqlite(rusqlite::Error::QueryReturnedNoRows)) => {
+                (StatusCode::NOT_FOUND, "not_found")
+            }
+            Error::Authenticating | Error::SessionGetError(_) => {
+                (StatusCode::UNAUTHORIZED, "unauthenticated")
+            }
+            Error::NotAdmin => (StatusCode::FORBIDDEN, "forbidden"),
+            Error::Registration => (StatusCode::BAD_REQUEST, "registration_failed"),
+            Error::BadUpload | Error::MultipartError(_) => (StatusCode::BAD_REQUEST, "bad_upload"),
+            Error::MergeIntoSelf => (StatusCode::BAD_REQUEST, "merge_into_self"),
+            Error::EmptyTagName => (StatusCode::BAD_REQUEST, "empty_tag_name"),
+            Error::ShareWithSelf => (StatusCode::BAD_REQUEST, "share_with_self"),
+            Error::NothingToSummarize => (StatusCode::BAD_REQUEST, "nothing_to_summarize"),
+            Error::InvalidFtsCommand => (StatusCode::BAD_REQUEST, "invalid_fts_command"),
+            Error::InvalidRequest { .. } => (StatusCode::BAD_REQUEST, "invalid_request"),
+            Error::InvalidStringToDeckKindConversion
+            | Error::StringConversionToEnum
+            | Error::IntConversionToEnum
+            | Error::ParseInt(_) => (StatusCode::BAD_REQUEST, "invalid_value"),
+            Error::TooManyFound | Error::Db(DbError::TooManyFound) => {
+                (StatusCode::CONFLICT, "too_many_found")
+            }
+            Error::VersionConflict(_) | Error::Db(DbError::VersionConflict) => {
+                (StatusCode::CONFLICT, "version_conflict")
+            }
+            Error::Sqlite(e) | Error::Db(DbError::Sqlite(e))
+                if e.sqlite_error_code() == Some(rusqlite::ErrorCode::ConstraintViolation) =>
+            {
+                (StatusCode::CONFLICT, "constraint_violation")
+            }
+            Error::PreconditionRequired => {
+                (StatusCode::PRECONDITION_REQUIRED, "precondition_required")
+            }
+            Error::ExternalServerError | Error::ChatGPTError(_) => {
+                (StatusCode::BAD_GATEWAY, "upstream_error")
+            }
+            Error::Actix(e) => (e.as_response_error().status_code(), "invalid_request"),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        let (status, code) = self.classify();
+
+        // don't leak the details of internal failures
+        let message = if status.is_server_error() && status != StatusCode::BAD_GATEWAY {
+            "internal server error".to_string()
+        } else {
+            self.to_string()
+        };
+
+        let fields = match self {
+            Error::EmptyTagName => vec![FieldError {
+                field: "name".to_string(),
+                message: self.to_string(),
+            }],
+            Error::InvalidRequest {
+                message,
+                field: Some(field),
+            } => vec![FieldError {
+                field: field.clone(),
+                message: message.clone(),
+            }],
+            _ => vec![],
+        };
+
+        let current = match self {
+            Error::VersionConflict(current) => Some(current.clone()),
+            _ => None,
+        };
+
+        ErrorBody {
+            code,
+            message,
+            fields,
+            current,
         }
     }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        self.classify().0
+    }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).finish()
+        if self.status_code().is_server_error() {
+            error!("{}", self);
+        }
+        HttpResponse::build(self.status_code()).json(self.body())
     }
 }
@@ -0,0 +1,155 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::db::SqlitePool;
+use crate::db::fsck as fsck_db;
+use crate::db::uploader as uploader_db;
+use crate::interop::Key;
+use crate::interop::fsck::{Finding, FtsCommand, FtsReport};
+
+use rusqlite::Connection;
+
+// checks the database (and the user uploaded images) for structural problems,
+// if repair is true then each problem is also fixed
+//
+pub fn fsck(
+    sqlite_pool: &SqlitePool,
+    user_content_path: &str,
+    repair: bool,
+) -> crate::Result<Vec<Finding>> {
+    let mut conn = sqlite_pool.get()?;
+    let tx = conn.transaction()?;
+
+    let mut findings = vec![];
+
+    // dangling point_ids are cleared before the chains are checked since
+    // detaching a note from its point moves it into a different passage
+    findings.append(&mut fsck_db::check_dangling_point_ids(&tx, repair)?);
+    findings.append(&mut fsck_db::check_note_chains(&tx, repair)?);
+    findings.append(&mut fsck_db::check_prediction_points(&tx, repair)?);
+    findings.append(&mut check_images(&tx, user_content_path, repair)?);
+    findings.append(&mut fsck_db::check_fts_tables(&tx, repair)?);
+
+    tx.commit()?;
+
+    Ok(findings)
+}
+
+// runs the fts5 command on each of the full text search tables
+//
+pub fn fts_maintenance(
+    sqlite_pool: &SqlitePool,
+    command: FtsCommand,
+) -> crate::Result<Vec<FtsReport>> {
+    let conn = sqlite_pool.get()?;
+    Ok(fsck_db::fts_maintenance(&conn, command)?)
+}
+
+// images rows without a file, files without an images row
+// and users whose image_count would cause an existing file to be overwritten
+//
+fn check_images(
+    conn: &Connection,
+    user_content_path: &str,
+    repair: bool,
+) -> crate::Result<Vec<Finding>> {
+    let mut findings = vec![];
+
+    let mut filenames: HashMap<Key, HashSet<String>> = HashMap::new();
+    for image in fsck_db::get_all_images(conn)? {
+        let path = format!("{}/{}/{}", user_content_path, image.user_id, image.filename);
+        if Path::new(&path).is_file() {
+            filenames
+                .entry(image.user_id)
+                .or_default()
+                .insert(image.filename);
+        } else {
+            if repair {
+                fsck_db::delete_image_entry(conn, image.id)?;
+            }
+            findings.push(Finding {
+                check: "image_row",
+                description: format!("image {} has no file at {}", image.id, path),
+                repaired: repair,
+            });
+        }
+    }
+
+    for user in fsck_db::get_image_counts(conn)? {
+        let known = filenames.entry(user.user_id).or_default();
+
+        let user_dir = format!("{}/{}", user_content_path, user.user_id);
+        if Path::new(&user_dir).is_dir() {
+            let mut on_disk: Vec<String> = vec![];
+            for entry in fs::read_dir(&user_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_file()
+                    && let Some(filename) = entry.file_name().to_str()
+                {
+                    on_disk.push(filename.to_string());
+                }
+            }
+            on_disk.sort();
+
+            for filename in on_disk {
+                if known.contains(&filename) {
+                    continue;
+                }
+                if repair {
+                    uploader_db::add_image_entry(conn, user.user_id, filename.clone())?;
+                }
+                findings.push(Finding {
+                    check: "image_file",
+                    description: format!("{}/{} has no images row", user_dir, filename),
+                    repaired: repair,
+                });
+                known.insert(filename);
+            }
+        }
+
+        // filenames are derived from image_count so it has to be past the highest
+        // numbered file, otherwise the next upload would overwrite an existing image
+        let highest = known.iter().filter_map(|f| filename_index(f)).max();
+        let expected = highest.map_or(0, |h| h + 1).max(known.len() as i32);
+        if user.image_count != expected {
+            if repair {
+                uploader_db::set_image_count(conn, user.user_id, expected)?;
+            }
+            findings.push(Finding {
+                check: "image_count",
+                description: format!(
+                    "user {} has an image_count of {} but should be {}",
+                    user.user_id, user.image_count, expected
+                ),
+                repaired: repair,
+            });
+        }
+    }
+
+    Ok(findings)
+}
+
+// uploaded images are named after the user's image_count in base 36, e.g. "00a.png"
+//
+fn filename_index(filename: &str) -> Option<i32> {
+    let stem = Path::new(filename).file_stem()?.to_str()?;
+    i32::from_str_radix(stem, 36).ok()
+}
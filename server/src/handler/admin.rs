@@ -0,0 +1,58 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::fsck as db;
+use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
+use crate::handler::AuthUser;
+use crate::interop::Key;
+use crate::interop::fsck::FtsCommand;
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Path};
+use serde::Deserialize;
+
+// the first user is the admin (see handler/users.rs)
+//
+pub(crate) fn is_admin(user_id: Key) -> bool {
+    user_id == Key(1)
+}
+
+fn require_admin(user_id: Key) -> crate::Result<()> {
+    if is_admin(user_id) {
+        Ok(())
+    } else {
+        Err(Error::NotAdmin)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FtsParam {
+    command: String,
+}
+
+pub async fn fts(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<FtsParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    require_admin(user_id)?;
+    let command: FtsCommand = params.command.parse()?;
+
+    let reports = db_thread(&sqlite_pool, move |conn| db::fts_maintenance(conn, command)).await?;
+
+    Ok(Json(reports))
+}
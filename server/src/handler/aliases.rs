@@ -0,0 +1,94 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::aliases as db;
+use crate::db::{SqlitePool, db_thread};
+use crate::handler::AuthUser;
+use crate::interop::aliases::ProtoDeckAlias;
+use crate::interop::{IdParam, Key};
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Path};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct AliasParam {
+    id: Key,
+    alias_id: Key,
+}
+
+pub async fn get_aliases(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let aliases = db_thread(&sqlite_pool, move |conn| {
+        db::get_aliases(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(aliases))
+}
+
+pub async fn create_alias(
+    Json(proto_alias): Json<ProtoDeckAlias>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let aliases = db_thread(&sqlite_pool, move |conn| {
+        db::create_alias(conn, user_id, params.id, proto_alias.name.trim())?;
+        db::get_aliases(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(aliases))
+}
+
+pub async fn edit_alias(
+    Json(proto_alias): Json<ProtoDeckAlias>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<AliasParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let aliases = db_thread(&sqlite_pool, move |conn| {
+        db::edit_alias(
+            conn,
+            user_id,
+            params.id,
+            params.alias_id,
+            proto_alias.name.trim(),
+        )?;
+        db::get_aliases(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(aliases))
+}
+
+pub async fn delete_alias(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<AliasParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let aliases = db_thread(&sqlite_pool, move |conn| {
+        db::delete_alias(conn, user_id, params.id, params.alias_id)?;
+        db::get_aliases(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(aliases))
+}
@@ -17,7 +17,8 @@
 
 use crate::db::articles as db;
 use crate::db::{SqlitePool, db_thread};
-use crate::handler::{AuthUser, PaginationQuery, decks};
+use crate::error::Error;
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, decks, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::articles as interop;
 use crate::interop::decks::{DeckKind, ProtoDeck};
@@ -59,25 +60,61 @@ pub async fn pagination(
 pub async fn recent(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
-    decks::paginated_recents(sqlite_pool, user_id, DeckKind::Article, offset, num_items).await
+    decks::paginated_recents(
+        sqlite_pool,
+        user_id,
+        DeckKind::Article,
+        offset,
+        num_items,
+        tag,
+    )
+    .await
 }
 
 pub async fn orphans(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
-    decks::paginated_orphans(sqlite_pool, user_id, DeckKind::Article, offset, num_items).await
+    decks::paginated_orphans(
+        sqlite_pool,
+        user_id,
+        DeckKind::Article,
+        offset,
+        num_items,
+        tag,
+    )
+    .await
 }
 
 pub async fn rated(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
-    decks::paginated_rated(sqlite_pool, user_id, DeckKind::Article, offset, num_items).await
+    decks::paginated_rated(
+        sqlite_pool,
+        user_id,
+        DeckKind::Article,
+        offset,
+        num_items,
+        tag,
+    )
+    .await
 }
 
 pub async fn get(
@@ -97,10 +134,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let article = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, article, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let article_id = params.id;
+    let article = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, article, article_id, version),
+        move |conn| db::get(conn, user_id, article_id),
+    )
     .await?;
 
     Ok(Json(article))
@@ -0,0 +1,57 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::audit as db;
+use crate::db::{SqlitePool, db_thread};
+use crate::handler::{AuthUser, admin};
+use crate::interop::Key;
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Query};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditQuery {
+    offset: i32,
+    num_items: i32,
+    entity: Option<String>,
+    entity_id: Option<Key>,
+}
+
+// the admin can also see the events that weren't caused by any user, e.g. migrations
+//
+pub async fn get_events(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<AuditQuery>,
+) -> crate::Result<impl Responder> {
+    let include_system = admin::is_admin(user_id);
+    let events = db_thread(&sqlite_pool, move |conn| {
+        db::get_events(
+            conn,
+            user_id,
+            include_system,
+            query.entity,
+            query.entity_id,
+            query.offset,
+            query.num_items,
+        )
+    })
+    .await?;
+
+    Ok(Json(events))
+}
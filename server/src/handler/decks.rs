@@ -18,11 +18,12 @@
 use crate::ai::{AI, openai_interface};
 use crate::db::decks as db;
 use crate::db::notes as db_notes;
+use crate::db::related as db_related;
 use crate::db::{SqlitePool, db_thread};
 use crate::error::Error;
 use crate::handler::{AuthUser, PaginationQuery};
 use crate::interop::decks::{
-    DeckKind, Pagination, SlimDeck, SlimResults, resource_string_to_deck_kind,
+    DeckKind, Pagination, ProtoMerge, SlimDeck, SlimResults, resource_string_to_deck_kind,
 };
 use crate::interop::dialogues::AiKind;
 use crate::interop::{IdParam, Key};
@@ -43,6 +44,22 @@ pub async fn hits(
     Ok(Json(hits))
 }
 
+// decks with notes similar to this deck's that it isn't yet connected to
+//
+pub async fn related(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let related = db_thread(&sqlite_pool, move |conn| {
+        db_related::related_decks(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(related))
+}
+
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InsigniasArgs {
@@ -50,6 +67,7 @@ pub struct InsigniasArgs {
     insignia: i32,
     offset: i32,    // for pagination
     num_items: i32, // for pagination
+    tag: Option<Key>,
 }
 
 pub async fn insignias(
@@ -67,12 +85,20 @@ pub async fn insignias(
                 query.insignia,
                 query.offset,
                 query.num_items,
+                query.tag,
             )
         })
         .await?
     } else {
         db_thread(&sqlite_pool, move |conn| {
-            db::insignia_filter_any(conn, user_id, query.insignia, query.offset, query.num_items)
+            db::insignia_filter_any(
+                conn,
+                user_id,
+                query.insignia,
+                query.offset,
+                query.num_items,
+                query.tag,
+            )
         })
         .await?
     };
@@ -130,11 +156,70 @@ pub async fn recently_visited(
     Ok(Json(res))
 }
 
+#[derive(Deserialize)]
+pub struct MergeParam {
+    id: Key,
+    target: Key,
+}
+
+pub async fn merge_into(
+    Json(proto_merge): Json<ProtoMerge>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<MergeParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    if params.id == params.target {
+        return Err(Error::MergeIntoSelf);
+    }
+
+    let deck = db_thread(&sqlite_pool, move |conn| {
+        db::merge_into(
+            conn,
+            user_id,
+            params.id,
+            params.target,
+            proto_merge.extras_strategy,
+        )
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(deck))
+}
+
+#[derive(Deserialize)]
+pub struct ConvertParam {
+    id: Key,
+    kind: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConvertQuery {
+    force: Option<bool>,
+}
+
+pub async fn convert(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<ConvertParam>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<ConvertQuery>,
+) -> crate::Result<impl Responder> {
+    let deck_kind = resource_string_to_deck_kind(&params.kind)?;
+    let force = query.force.unwrap_or(false);
+
+    let conversion = db_thread(&sqlite_pool, move |conn| {
+        db::convert(conn, user_id, params.id, deck_kind, force)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(conversion))
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SummarizeStruct {
     pub prev_id: Option<Key>,
-    pub content: String,
 }
 
 pub async fn summarize(
@@ -144,6 +229,21 @@ pub async fn summarize(
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
 ) -> crate::Result<impl Responder> {
+    // the text is built here rather than by the client so that private notes are never sent
+    let deck_id = params.id;
+    let contents = db_thread(&sqlite_pool, move |conn| {
+        db_notes::non_private_passage_contents(conn, user_id, deck_id)
+    })
+    .await?;
+    if contents.is_empty() {
+        return Err(Error::NothingToSummarize);
+    }
+    let text = contents
+        .iter()
+        .map(|content| civil_shared::markup_as_simplified_text(content))
+        .collect::<Result<Vec<String>, _>>()?
+        .join("\n");
+
     let mut messages: Vec<openai_interface::ChatMessage> = vec![];
 
     messages.push(openai_interface::ChatMessage {
@@ -155,7 +255,7 @@ pub async fn summarize(
     messages.push(openai_interface::ChatMessage {
         note_id: Key(0),
         role: openai_interface::Role::User,
-        content: "Summarize the following text: ".to_string() + &summarize_struct.content,
+        content: "Summarize the following text: ".to_string() + &text,
     });
     // dbg!(&messages);
 
@@ -315,9 +415,17 @@ pub(crate) async fn pagination(
                 deck_kind,
                 query.offset,
                 query.num_items,
+                query.tag,
             )
         } else {
-            db::pagination(conn, user_id, deck_kind, query.offset, query.num_items)
+            db::pagination(
+                conn,
+                user_id,
+                deck_kind,
+                query.offset,
+                query.num_items,
+                query.tag,
+            )
         }
     })
     .await?;
@@ -331,9 +439,10 @@ pub async fn paginated_unnoted(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> crate::Result<impl Responder> {
     let slimdecks = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_unnoted(conn, user_id, deck_kind, offset, num_items)
+        db::paginated_unnoted(conn, user_id, deck_kind, offset, num_items, tag_id)
     })
     .await?;
 
@@ -346,9 +455,10 @@ pub async fn paginated_recents(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> crate::Result<impl Responder> {
     let slimdecks = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_recents(conn, user_id, deck_kind, offset, num_items)
+        db::paginated_recents(conn, user_id, deck_kind, offset, num_items, tag_id)
     })
     .await?;
 
@@ -361,9 +471,10 @@ pub async fn paginated_orphans(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> crate::Result<impl Responder> {
     let slimdecks = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_orphans(conn, user_id, deck_kind, offset, num_items)
+        db::paginated_orphans(conn, user_id, deck_kind, offset, num_items, tag_id)
     })
     .await?;
 
@@ -376,9 +487,10 @@ pub async fn paginated_rated(
     deck_kind: DeckKind,
     offset: i32,
     num_items: i32,
+    tag_id: Option<Key>,
 ) -> crate::Result<impl Responder> {
     let slimdecks = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_rated(conn, user_id, deck_kind, offset, num_items)
+        db::paginated_rated(conn, user_id, deck_kind, offset, num_items, tag_id)
     })
     .await?;
 
@@ -18,7 +18,8 @@
 use crate::ai::{AI, openai_interface};
 use crate::db::dialogues as db;
 use crate::db::{SqlitePool, db_thread};
-use crate::handler::{AuthUser, PaginationQuery, decks};
+use crate::error::Error;
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, decks, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::decks::DeckKind;
 use crate::interop::dialogues as interop;
@@ -127,10 +128,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let dialogue = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, dialogue, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let dialogue_id = params.id;
+    let dialogue = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, dialogue, dialogue_id, version),
+        move |conn| db::get(conn, user_id, dialogue_id),
+    )
     .await?;
 
     Ok(Json(dialogue))
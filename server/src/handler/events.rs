@@ -17,8 +17,9 @@
 
 use crate::db::events as db;
 use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
 use crate::handler::decks;
-use crate::handler::{AuthUser, PaginationQuery};
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::decks::{DeckKind, ProtoDeck};
 use crate::interop::events as interop;
@@ -72,10 +73,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let event = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, event, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let event_id = params.id;
+    let event = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, event, event_id, version),
+        move |conn| db::get(conn, user_id, event_id),
+    )
     .await?;
 
     Ok(Json(event))
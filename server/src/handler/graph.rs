@@ -17,18 +17,84 @@
 
 use crate::db::graph as db;
 use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
 use crate::handler::AuthUser;
 use crate::interop::IdParam;
+use crate::interop::graph::{GraphReach, MAX_GRAPH_BUDGET, MAX_GRAPH_DEPTH};
 use actix_web::Responder;
-use actix_web::web::{Data, Json, Path};
+use actix_web::web::{Data, Json, Path, Query};
+use std::str::FromStr;
+
+// the kinds are comma separated, e.g. ?depth=2&refKinds=ref_to_parent,ref_to_child&deckKinds=idea
+//
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphQuery {
+    pub depth: Option<u32>,
+    pub ref_kinds: Option<String>,
+    pub deck_kinds: Option<String>,
+    pub budget: Option<usize>,
+}
+
+fn invalid(message: String, field: &str) -> Error {
+    Error::InvalidRequest {
+        message,
+        field: Some(field.to_string()),
+    }
+}
+
+fn kinds<T: FromStr>(list: &Option<String>, field: &str) -> crate::Result<Vec<T>> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
+        .map(|kind| {
+            kind.parse()
+                .map_err(|_| invalid(format!("'{kind}' isn't a valid kind"), field))
+        })
+        .collect()
+}
+
+impl GraphQuery {
+    fn reach(&self) -> crate::Result<GraphReach> {
+        let default = GraphReach::default();
+
+        let depth = self.depth.unwrap_or(default.depth);
+        if !(1..=MAX_GRAPH_DEPTH).contains(&depth) {
+            return Err(invalid(
+                format!("depth must be between 1 and {MAX_GRAPH_DEPTH}"),
+                "depth",
+            ));
+        }
+        let budget = self.budget.unwrap_or(default.budget);
+        if !(1..=MAX_GRAPH_BUDGET).contains(&budget) {
+            return Err(invalid(
+                format!("budget must be between 1 and {MAX_GRAPH_BUDGET}"),
+                "budget",
+            ));
+        }
+
+        Ok(GraphReach {
+            depth,
+            ref_kinds: kinds(&self.ref_kinds, "refKinds")?,
+            deck_kinds: kinds(&self.deck_kinds, "deckKinds")?,
+            budget,
+        })
+    }
+}
 
 pub async fn get(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    Query(query): Query<GraphQuery>,
 ) -> crate::Result<impl Responder> {
-    let connectivity =
-        db_thread(&sqlite_pool, move |conn| db::get(conn, user_id, params.id)).await?;
+    let reach = query.reach()?;
+    let connectivity = db_thread(&sqlite_pool, move |conn| {
+        db::get(conn, user_id, params.id, &reach)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
 
     Ok(Json(connectivity))
 }
@@ -17,10 +17,11 @@
 
 use crate::db::points as db;
 use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
 use crate::handler::AuthUser;
 use crate::interop::points as interop;
 use actix_web::Responder;
-use actix_web::web::{Data, Json, Path};
+use actix_web::web::{Data, Json, Path, Query};
 
 #[derive(serde::Deserialize)]
 pub struct YearRangeParam {
@@ -49,3 +50,123 @@ pub async fn get_points(
         points,
     }))
 }
+
+// either a bounding box (south, west, north and east) or a radius around a
+// location (latitude, longitude and radiusKm), optionally within the years from
+// lower to upper, e.g. ?latitude=43.77&longitude=11.25&radiusKm=100&lower=1400&upper=1500
+//
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AreaQuery {
+    pub south: Option<f64>,
+    pub west: Option<f64>,
+    pub north: Option<f64>,
+    pub east: Option<f64>,
+
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub radius_km: Option<f64>,
+
+    pub lower: Option<i32>,
+    pub upper: Option<i32>,
+}
+
+fn invalid(message: &str, field: &str) -> Error {
+    Error::InvalidRequest {
+        message: message.to_string(),
+        field: Some(field.to_string()),
+    }
+}
+
+fn check_latitude(latitude: f64, field: &str) -> crate::Result<()> {
+    if (-90.0..=90.0).contains(&latitude) {
+        Ok(())
+    } else {
+        Err(invalid("a latitude must be between -90 and 90", field))
+    }
+}
+
+fn check_longitude(longitude: f64, field: &str) -> crate::Result<()> {
+    if (-180.0..=180.0).contains(&longitude) {
+        Ok(())
+    } else {
+        Err(invalid("a longitude must be between -180 and 180", field))
+    }
+}
+
+impl AreaQuery {
+    fn area(&self) -> crate::Result<interop::Area> {
+        match (self.south, self.west, self.north, self.east) {
+            (Some(south), Some(west), Some(north), Some(east)) => {
+                check_latitude(south, "south")?;
+                check_latitude(north, "north")?;
+                check_longitude(west, "west")?;
+                check_longitude(east, "east")?;
+                if south > north {
+                    return Err(invalid("south must not be greater than north", "south"));
+                }
+                Ok(interop::Area::BoundingBox {
+                    south,
+                    west,
+                    north,
+                    east,
+                })
+            }
+            (None, None, None, None) => match (self.latitude, self.longitude, self.radius_km) {
+                (Some(latitude), Some(longitude), Some(radius_km)) => {
+                    check_latitude(latitude, "latitude")?;
+                    check_longitude(longitude, "longitude")?;
+                    if !radius_km.is_finite() || radius_km <= 0.0 {
+                        return Err(invalid("the radius must be a positive number", "radiusKm"));
+                    }
+                    Ok(interop::Area::Radius {
+                        latitude,
+                        longitude,
+                        radius_km,
+                    })
+                }
+                _ => Err(invalid(
+                    "an area requires either south, west, north and east or latitude, longitude and radiusKm",
+                    "radiusKm",
+                )),
+            },
+            _ => Err(invalid(
+                "a bounding box requires all of south, west, north and east",
+                "south",
+            )),
+        }
+    }
+
+    fn years(&self) -> crate::Result<Option<(i32, i32)>> {
+        match (self.lower, self.upper) {
+            (Some(lower), Some(upper)) if lower <= upper => Ok(Some((lower, upper))),
+            (Some(_), Some(_)) => Err(invalid("lower must not be greater than upper", "lower")),
+            (None, None) => Ok(None),
+            _ => Err(invalid(
+                "a year range requires both lower and upper",
+                "lower",
+            )),
+        }
+    }
+}
+
+pub async fn get_points_within_area(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<AreaQuery>,
+) -> crate::Result<impl Responder> {
+    let area = query.area()?;
+    let years = query.years()?;
+
+    let points = db_thread(&sqlite_pool, move |conn| {
+        db::all_points_within_area(conn, user_id, &area, years)
+    })
+    .await?;
+
+    Ok(Json(interop::PointsWithinArea {
+        area,
+        lower_year: years.map(|(lower, _)| lower),
+        upper_year: years.map(|(_, upper)| upper),
+        points,
+    }))
+}
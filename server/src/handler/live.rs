@@ -0,0 +1,73 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::live as db;
+use crate::handler::AuthUser;
+use crate::interop::live::LiveEvent;
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, Responder};
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::interval;
+
+#[allow(unused_imports)]
+use tracing::{error, info};
+
+// keeps idle connections from being closed by proxies
+const HEARTBEAT: Duration = Duration::from_secs(20);
+
+const HEARTBEAT_MESSAGE: &[u8] = b": heartbeat\n\n";
+const RESYNC_MESSAGE: &[u8] = b"event: resync\ndata: {}\n\n";
+
+// a server-sent event stream of changes to the user's data. Clients receive a
+// 'change' event for each change and a 'resync' event if they fell so far
+// behind that some changes were dropped and everything should be re-fetched
+//
+pub async fn stream(AuthUser(user_id): AuthUser) -> crate::Result<impl Responder> {
+    let receiver = db::subscribe();
+    let heartbeat = interval(HEARTBEAT);
+
+    let events = futures::stream::unfold(
+        (receiver, heartbeat),
+        move |(mut receiver, mut heartbeat)| async move {
+            let message = loop {
+                tokio::select! {
+                    received = receiver.recv() => match received {
+                        Ok(event) if event.user_id == user_id => break change(&event),
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(_)) => break Bytes::from_static(RESYNC_MESSAGE),
+                        Err(RecvError::Closed) => return None,
+                    },
+                    _ = heartbeat.tick() => break Bytes::from_static(HEARTBEAT_MESSAGE),
+                }
+            };
+            Some((Ok::<_, Infallible>(message), (receiver, heartbeat)))
+        },
+    );
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
+
+fn change(event: &LiveEvent) -> Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Bytes::from(format!("event: change\ndata: {data}\n\n"))
+}
@@ -43,7 +43,6 @@ pub async fn card_rated(
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
 ) -> crate::Result<impl Responder> {
-    // need to be in a session, even though we're not using the information
     // card_ratings are linked to a user via the card_id
     let rating = rating.rating;
 
@@ -53,8 +52,11 @@ pub async fn card_rated(
         })
         .await?;
 
-        card = sqlite_update_easiness_factor(card, rating)?;
-        db_thread(&sqlite_pool, move |conn| db::card_rated(conn, card, rating)).await?;
+        card = sqlite_update_easiness_factor(card, rating);
+        db_thread(&sqlite_pool, move |conn| {
+            db::card_rated(conn, user_id, card, rating)
+        })
+        .await?;
 
         Ok(Json(true))
     } else {
@@ -97,7 +99,7 @@ pub async fn delete(
 1 - incorrect response; the correct one remembered
 0 - complete blackout.
 */
-fn sqlite_update_easiness_factor(mut card: FlashCard, rating: i16) -> crate::Result<FlashCard> {
+pub(crate) fn sqlite_update_easiness_factor(mut card: FlashCard, rating: i16) -> FlashCard {
     // note: repetition starts at 1, that's the initial value given to a new card
     if rating < 3 {
         // start repetitions for the item from the beginning without changing the E-Factor (i.e. use intervals I(1), I(2) etc. as if the item was memorized anew
@@ -141,7 +143,7 @@ fn sqlite_update_easiness_factor(mut card: FlashCard, rating: i16) -> crate::Res
 
     // there is logic in the client to re-display cards in a session until they've all been rated at least 4
 
-    Ok(card)
+    card
 }
 
 pub async fn get_cards(
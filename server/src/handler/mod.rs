@@ -15,9 +15,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::{DbError, SqlitePool, db_thread};
 use crate::{error::Error, interop::Key, session};
 use actix_session::SessionExt; // for req.get_session()
-use actix_web::{FromRequest, HttpRequest, dev::Payload};
+use actix_web::{FromRequest, HttpRequest, dev::Payload, http::header};
+use rusqlite::Connection;
+use serde::Serialize;
 use std::future::{Ready, ready}; // ready() and Ready<>
 
 pub struct AuthUser(pub Key);
@@ -35,10 +38,66 @@ impl FromRequest for AuthUser {
     }
 }
 
+// the version given in an If-Match header, accepts both 3 and "3"
+//
+pub struct IfMatch(pub Option<i32>);
+
+impl FromRequest for IfMatch {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let version = req
+            .headers()
+            .get(header::IF_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value
+                    .trim()
+                    .trim_start_matches("W/")
+                    .trim_matches('"')
+                    .parse()
+                    .ok()
+            });
+        ready(Ok(IfMatch(version)))
+    }
+}
+
+// runs an edit that only applies if the item is still at the version the
+// client last read. On a conflict the current copy is returned in a 409
+//
+pub(crate) async fn versioned_edit<T, E, C>(
+    sqlite_pool: &SqlitePool,
+    edit: E,
+    current: C,
+) -> crate::Result<T>
+where
+    T: Serialize + Send + 'static,
+    E: FnOnce(&mut Connection) -> Result<T, DbError> + Send + 'static,
+    C: FnOnce(&Connection) -> Result<Option<T>, DbError> + Send + 'static,
+{
+    let edited = db_thread(sqlite_pool, move |conn| match edit(conn) {
+        Ok(edited) => Ok(Ok(edited)),
+        Err(DbError::VersionConflict) => Ok(Err(current(conn)?)),
+        Err(e) => Err(e),
+    })
+    .await?;
+
+    match edited {
+        Ok(edited) => Ok(edited),
+        Err(Some(current)) => Err(Error::VersionConflict(
+            serde_json::to_value(current).unwrap_or_default(),
+        )),
+        Err(None) => Err(Error::NotFound),
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchQuery {
     q: String,
+    tag: Option<Key>,
+    cursor: Option<String>, // the next_cursor of the previous page of results
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -46,9 +105,13 @@ pub struct SearchQuery {
 pub struct PaginationQuery {
     offset: i32,
     num_items: i32,
+    tag: Option<Key>,
 }
 
+pub mod admin;
+pub mod aliases;
 pub mod articles;
+pub mod audit;
 pub mod bookmarks;
 pub mod concepts;
 pub mod decks;
@@ -57,13 +120,60 @@ pub mod events;
 pub mod graph;
 pub mod ideas;
 pub mod interval;
+pub mod live;
 pub mod memorise;
 pub mod notes;
 pub mod people;
 pub mod predictions;
 pub mod quotes;
+pub mod saved_searches;
 pub mod search;
+pub mod share_links;
+pub mod shares;
+pub mod sync;
+pub mod tags;
 pub mod timelines;
 pub mod ubersetup;
 pub mod uploader;
 pub mod users;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::notes as db;
+    use crate::db::test_util::{OWNER, add_note, create_deck, test_db};
+    use actix_web::{ResponseError, http::StatusCode};
+
+    #[tokio::test]
+    async fn test_stale_edit_returns_the_current_copy() {
+        let mut test = test_db("versioned-edit");
+        let deck_id = create_deck(&test.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut test.conn, deck_id, "first draft");
+        let stale = db::get_note(&test.conn, OWNER, note_id).unwrap().unwrap();
+
+        let mut edit = stale.clone();
+        edit.content = "second draft".to_string();
+        db::edit_note(&test.conn, OWNER, edit, note_id, Some(stale.version)).unwrap();
+
+        let mut edit = stale.clone();
+        edit.content = "written against the first draft".to_string();
+        let version = stale.version;
+        let r = versioned_edit(
+            &test.pool(),
+            move |conn| db::edit_note(conn, OWNER, edit, note_id, Some(version)),
+            move |conn| db::get_note(conn, OWNER, note_id),
+        )
+        .await;
+
+        let Err(err) = r else {
+            panic!("a stale version should be rejected");
+        };
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+
+        let body = err.body();
+        assert_eq!(body.code, "version_conflict");
+        let current = body.current.expect("a conflict carries the current copy");
+        assert_eq!(current["content"], "second draft");
+        assert_eq!(current["version"], stale.version + 1);
+    }
+}
@@ -18,12 +18,14 @@
 use crate::db::notes as db;
 use crate::db::references as db_refs;
 use crate::db::{SqlitePool, db_thread};
-use crate::handler::AuthUser;
-use crate::interop::IdParam;
+use crate::error::Error;
+use crate::handler::{AuthUser, IfMatch, versioned_edit};
 use crate::interop::notes as interop;
 use crate::interop::references as interop_refs;
+use crate::interop::{IdParam, Key};
 use actix_web::Responder;
 use actix_web::web::{Data, Json, Path};
+use serde::Deserialize;
 
 pub async fn create_notes(
     Json(note): Json<interop::ProtoNote>,
@@ -43,10 +45,15 @@ pub async fn edit_note(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let note = db_thread(&sqlite_pool, move |conn| {
-        db::edit_note(conn, user_id, note, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let note_id = params.id;
+    let note = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit_note(conn, user_id, note, note_id, Some(version)),
+        move |conn| db::get_note(conn, user_id, note_id),
+    )
     .await?;
 
     Ok(Json(note))
@@ -65,6 +72,126 @@ pub async fn delete_note(
     Ok(Json(notes))
 }
 
+pub async fn move_note_up(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let notes = db_thread(&sqlite_pool, move |conn| {
+        db::move_note_up(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(notes))
+}
+
+pub async fn move_note_down(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let notes = db_thread(&sqlite_pool, move |conn| {
+        db::move_note_down(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(notes))
+}
+
+#[derive(Deserialize)]
+pub struct MoveToDeckParam {
+    id: Key,
+    deck_id: Key,
+}
+
+pub async fn move_note_to_deck(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<MoveToDeckParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let notes = db_thread(&sqlite_pool, move |conn| {
+        db::move_note_to_deck(conn, user_id, params.id, params.deck_id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(notes))
+}
+
+pub async fn split_note(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let notes = db_thread(&sqlite_pool, move |conn| {
+        db::split_note(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(notes))
+}
+
+pub async fn merge_note_with_next(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let notes = db_thread(&sqlite_pool, move |conn| {
+        db::merge_note_with_next(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(notes))
+}
+
+pub async fn set_note_private(
+    Json(privacy): Json<interop::Privacy>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let notes = db_thread(&sqlite_pool, move |conn| {
+        db::set_note_private(conn, user_id, params.id, privacy.private)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(notes))
+}
+
+pub async fn get_private_notes_default(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let privacy = db_thread(&sqlite_pool, move |conn| {
+        db::get_private_notes_default(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(privacy))
+}
+
+pub async fn set_private_notes_default(
+    Json(privacy): Json<interop::Privacy>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let privacy = db_thread(&sqlite_pool, move |conn| {
+        db::set_private_notes_default(conn, user_id, params.id, privacy.private)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(privacy))
+}
+
 pub async fn edit_references(
     Json(diff): Json<interop_refs::ReferencesDiff>,
     sqlite_pool: Data<SqlitePool>,
@@ -18,7 +18,8 @@
 use crate::db::people as db;
 use crate::db::points as points_db;
 use crate::db::{SqlitePool, db_thread};
-use crate::handler::{AuthUser, PaginationQuery, decks};
+use crate::error::Error;
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, decks, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::decks::{DeckKind, ProtoDeck, ProtoSlimDeck};
 use crate::interop::points as points_interop;
@@ -58,10 +59,14 @@ pub async fn pagination(
 pub async fn uncategorised(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
     let paginated = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_uncategorised(conn, user_id, offset, num_items)
+        db::paginated_uncategorised(conn, user_id, offset, num_items, tag)
     })
     .await?;
 
@@ -71,10 +76,14 @@ pub async fn uncategorised(
 pub async fn ancient(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
     let paginated = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_ancient(conn, user_id, offset, num_items)
+        db::paginated_ancient(conn, user_id, offset, num_items, tag)
     })
     .await?;
 
@@ -84,10 +93,14 @@ pub async fn ancient(
 pub async fn medieval(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
     let paginated = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_medieval(conn, user_id, offset, num_items)
+        db::paginated_medieval(conn, user_id, offset, num_items, tag)
     })
     .await?;
 
@@ -97,10 +110,14 @@ pub async fn medieval(
 pub async fn modern(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
     let paginated = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_modern(conn, user_id, offset, num_items)
+        db::paginated_modern(conn, user_id, offset, num_items, tag)
     })
     .await?;
 
@@ -110,10 +127,14 @@ pub async fn modern(
 pub async fn contemporary(
     sqlite_pool: Data<SqlitePool>,
     AuthUser(user_id): AuthUser,
-    Query(PaginationQuery { offset, num_items }): Query<PaginationQuery>,
+    Query(PaginationQuery {
+        offset,
+        num_items,
+        tag,
+    }): Query<PaginationQuery>,
 ) -> crate::Result<impl Responder> {
     let paginated = db_thread(&sqlite_pool, move |conn| {
-        db::paginated_contemporary(conn, user_id, offset, num_items)
+        db::paginated_contemporary(conn, user_id, offset, num_items, tag)
     })
     .await?;
 
@@ -137,10 +158,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let person = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, person, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let person_id = params.id;
+    let person = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, person, person_id, version),
+        move |conn| db::get(conn, user_id, person_id),
+    )
     .await?;
 
     Ok(Json(person))
@@ -161,7 +187,7 @@ pub async fn add_point(
     AuthUser(user_id): AuthUser,
 ) -> crate::Result<impl Responder> {
     let person = db_thread(&sqlite_pool, move |conn| {
-        points_db::create(conn, point, params.id)?;
+        points_db::create(conn, user_id, point, params.id)?;
         db::get(conn, user_id, params.id)
     })
     .await?
@@ -180,7 +206,7 @@ pub async fn add_multipoints(
         // nocheckin: shouldn't this be in a transaction?
         //
         for point in points {
-            points_db::create(conn, point, params.id)?;
+            points_db::create(conn, user_id, point, params.id)?;
         }
         db::get(conn, user_id, params.id)
     })
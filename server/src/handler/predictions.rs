@@ -17,8 +17,9 @@
 
 use crate::db::predictions as db;
 use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
 use crate::handler::decks;
-use crate::handler::{AuthUser, PaginationQuery};
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::decks::{DeckKind, ProtoDeck};
 use crate::interop::predictions as interop;
@@ -72,10 +73,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let prediction = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, prediction, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let prediction_id = params.id;
+    let prediction = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, prediction, prediction_id, version),
+        move |conn| db::get(conn, user_id, prediction_id),
+    )
     .await?;
 
     Ok(Json(prediction))
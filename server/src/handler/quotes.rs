@@ -17,7 +17,8 @@
 
 use crate::db::quotes as db;
 use crate::db::{SqlitePool, db_thread};
-use crate::handler::{AuthUser, PaginationQuery, decks};
+use crate::error::Error;
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, decks, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::decks::DeckKind;
 use crate::interop::quotes as interop;
@@ -97,10 +98,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let quote = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, quote, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let quote_id = params.id;
+    let quote = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, quote, quote_id, version),
+        move |conn| db::get(conn, user_id, quote_id),
+    )
     .await?;
 
     Ok(Json(quote))
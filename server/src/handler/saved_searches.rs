@@ -0,0 +1,222 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::saved_searches as db;
+use crate::db::search as db_search;
+use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
+use crate::handler::AuthUser;
+use crate::interop::IdParam;
+use crate::interop::saved_searches::{ProtoSavedSearch, SavedSearchResults};
+use crate::interop::search::SearchTerms;
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Path};
+
+// a saved search needs a name and a query that would find something
+//
+fn validated(proto: ProtoSavedSearch) -> crate::Result<ProtoSavedSearch> {
+    let name = proto.name.trim().to_string();
+    if name.is_empty() {
+        return Err(Error::InvalidRequest {
+            message: "a saved search requires a name".to_string(),
+            field: Some("name".to_string()),
+        });
+    }
+
+    let terms = parse_query(&proto.query)?;
+    if terms.fts().is_empty() && !terms.has_filters() {
+        return Err(Error::InvalidRequest {
+            message: "a saved search requires a query".to_string(),
+            field: Some("query".to_string()),
+        });
+    }
+
+    Ok(ProtoSavedSearch {
+        name,
+        query: proto.query.trim().to_string(),
+        pinned: proto.pinned,
+    })
+}
+
+fn parse_query(query: &str) -> crate::Result<SearchTerms> {
+    query.parse().map_err(|e| match e {
+        Error::InvalidRequest { message, .. } => Error::InvalidRequest {
+            message,
+            field: Some("query".to_string()),
+        },
+        e => e,
+    })
+}
+
+pub async fn get_saved_searches(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let saved_searches = db_thread(&sqlite_pool, move |conn| {
+        db::get_saved_searches(conn, user_id)
+    })
+    .await?;
+
+    Ok(Json(saved_searches))
+}
+
+pub async fn create_saved_search(
+    Json(proto): Json<ProtoSavedSearch>,
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let proto = validated(proto)?;
+    let saved_search = db_thread(&sqlite_pool, move |conn| {
+        db::create_saved_search(conn, user_id, &proto)
+    })
+    .await?;
+
+    Ok(Json(saved_search))
+}
+
+// the search is run again every time that it's opened
+//
+pub async fn run_saved_search(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let saved_search = db_thread(&sqlite_pool, move |conn| {
+        db::get_saved_search(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    let terms = parse_query(&saved_search.query)?;
+    let query = saved_search.query.clone();
+    let results = db_thread(&sqlite_pool, move |conn| {
+        db_search::search_at_all_levels(conn, user_id, query, &terms, None)
+    })
+    .await?;
+
+    Ok(Json(SavedSearchResults {
+        saved_search,
+        results,
+    }))
+}
+
+pub async fn edit_saved_search(
+    Json(proto): Json<ProtoSavedSearch>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let proto = validated(proto)?;
+    let saved_search = db_thread(&sqlite_pool, move |conn| {
+        db::edit_saved_search(conn, user_id, params.id, &proto)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(saved_search))
+}
+
+pub async fn delete_saved_search(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let saved_searches = db_thread(&sqlite_pool, move |conn| {
+        db::delete_saved_search(conn, user_id, params.id)?;
+        db::get_saved_searches(conn, user_id)
+    })
+    .await?;
+
+    Ok(Json(saved_searches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{OWNER, create_deck, test_db};
+
+    fn proto(name: &str, query: &str) -> ProtoSavedSearch {
+        ProtoSavedSearch {
+            name: name.to_string(),
+            query: query.to_string(),
+            pinned: false,
+        }
+    }
+
+    fn invalid_field(proto: ProtoSavedSearch) -> Option<String> {
+        match validated(proto) {
+            Err(Error::InvalidRequest { field, .. }) => field,
+            _ => panic!("expected an invalid request"),
+        }
+    }
+
+    #[test]
+    fn test_validated() {
+        let valid = validated(proto("  Stoics ", " kind:person stoic ")).unwrap();
+        assert_eq!(valid.name, "Stoics");
+        assert_eq!(valid.query, "kind:person stoic");
+
+        // filters alone are enough of a query
+        assert!(validated(proto("People", "kind:person")).is_ok());
+
+        assert_eq!(invalid_field(proto("", "stoic")), Some("name".to_string()));
+        assert_eq!(
+            invalid_field(proto("   ", "stoic")),
+            Some("name".to_string())
+        );
+        assert_eq!(
+            invalid_field(proto("Stoics", "")),
+            Some("query".to_string())
+        );
+        assert_eq!(
+            invalid_field(proto("Stoics", "  ")),
+            Some("query".to_string())
+        );
+        assert_eq!(
+            invalid_field(proto("Stoics", "kind:planet")),
+            Some("query".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rerun_filtered_search() {
+        let test = test_db("saved-search-rerun");
+        let seneca = create_deck(&test.conn, "person", "Seneca the Stoic");
+        create_deck(&test.conn, "idea", "Stoic Virtue");
+
+        let saved = validated(proto("Stoic people", "kind:person stoic")).unwrap();
+        let saved = db::create_saved_search(&test.conn, OWNER, &saved).unwrap();
+
+        // as run_saved_search does, the stored query is parsed and searched for each time
+        let run = || {
+            let saved = db::get_saved_search(&test.conn, OWNER, saved.id)
+                .unwrap()
+                .unwrap();
+            let terms = parse_query(&saved.query).unwrap();
+            let results =
+                db_search::search_at_all_levels(&test.conn, OWNER, saved.query, &terms, None)
+                    .unwrap();
+            let mut ids: Vec<i64> = results.deck_level.iter().map(|sd| sd.deck.id.0).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(run(), [seneca.0]);
+
+        let epictetus = create_deck(&test.conn, "person", "Epictetus the Stoic");
+        assert_eq!(run(), [seneca.0, epictetus.0]);
+    }
+}
@@ -15,14 +15,52 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::db::ranking as db_ranking;
 use crate::db::search as db;
-use crate::db::{SqlitePool, db_thread};
+use crate::db::shares as db_shares;
+use crate::db::users as db_users;
+use crate::db::{DbError, SqlitePool, db_thread};
 use crate::handler::{AuthUser, SearchQuery};
-use crate::interop::IdParam;
-use crate::interop::search::SearchResults;
+use crate::interop::search::{
+    Cursor, Ranking, SearchDeck, SearchLanguage, SearchResults, SearchTerms,
+};
+use crate::interop::{IdParam, Key};
 use actix_web::Responder;
 use actix_web::web::{Data, Json, Path, Query};
 
+// decks that other users have shared are appended after the user's own results.
+// Tags belong to a single user and the field filters and excluded terms are only
+// applied to the user's own decks, so shared decks are left out of any search that
+// uses them rather than being returned unfiltered
+//
+fn with_shared_decks(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    q: String,
+    mut results: Vec<SearchDeck>,
+) -> Result<Vec<SearchDeck>, DbError> {
+    if q.trim().is_empty() {
+        return Ok(results);
+    }
+    results.append(&mut db_shares::search_shared_decks(conn, user_id, q)?);
+    Ok(results)
+}
+
+// the tag is filtered on in sql along with the rest of the query so that it's
+// applied before the results are limited and paged
+//
+fn terms(query: &SearchQuery) -> crate::Result<SearchTerms> {
+    let terms: SearchTerms = query.q.parse()?;
+    Ok(SearchTerms {
+        tag: query.tag,
+        ..terms
+    })
+}
+
+fn cursor(query: &SearchQuery) -> crate::Result<Option<Cursor>> {
+    query.cursor.as_deref().map(str::parse).transpose()
+}
+
 // called by the realtime search at the top of the page
 //
 pub async fn search_at_deck_level(
@@ -30,10 +68,25 @@ pub async fn search_at_deck_level(
     AuthUser(user_id): AuthUser,
     Query(query): Query<SearchQuery>,
 ) -> crate::Result<impl Responder> {
-    let q: String = query.q;
-    let q2 = q.clone();
-    let deck_level_results = db_thread(&sqlite_pool, move |conn| {
-        db::search_at_deck_level(conn, user_id, q)
+    let terms = terms(&query)?;
+    let after = cursor(&query)?;
+    let q2 = query.q.clone();
+    let (deck_level_results, did_you_mean, next_cursor) = db_thread(&sqlite_pool, move |conn| {
+        let text = terms.text.join(" ");
+        let (results, next_cursor) =
+            db::search_at_deck_level(conn, user_id, &terms, after.as_ref())?;
+        let unfiltered = !terms.has_filters() && terms.excluded.is_empty();
+        let results = if unfiltered && after.is_none() {
+            with_shared_decks(conn, user_id, text.clone(), results)?
+        } else {
+            results
+        };
+        let did_you_mean = if results.is_empty() && after.is_none() {
+            db::did_you_mean(conn, user_id, &text)?
+        } else {
+            None
+        };
+        Ok((results, did_you_mean, next_cursor))
     })
     .await?;
 
@@ -41,6 +94,8 @@ pub async fn search_at_deck_level(
         search_text: q2,
         deck_level: deck_level_results,
         note_level: vec![],
+        did_you_mean,
+        next_cursor: next_cursor.map(|cursor| cursor.to_string()),
     };
 
     Ok(Json(res))
@@ -55,8 +110,24 @@ pub async fn search_names_at_deck_level(
 ) -> crate::Result<impl Responder> {
     let q: String = query.q;
     let q2 = q.clone();
-    let results = db_thread(&sqlite_pool, move |conn| {
-        db::search_names_at_deck_level(conn, user_id, q)
+    let (results, did_you_mean) = db_thread(&sqlite_pool, move |conn| {
+        let mut results = db::search_names_at_deck_level(conn, user_id, q.clone(), query.tag)?;
+
+        // fall back to similarly spelt names so that a typo in a picker doesn't
+        // lead to a duplicate deck being created
+        let mut did_you_mean = None;
+        if results.is_empty() {
+            results = db::fuzzy_search_names_at_deck_level(conn, user_id, &q, query.tag)?;
+            did_you_mean = results
+                .first()
+                .map(|search_deck| search_deck.deck.title.clone());
+        }
+
+        let results = match query.tag {
+            Some(_) => results,
+            None => with_shared_decks(conn, user_id, q, results)?,
+        };
+        Ok((results, did_you_mean))
     })
     .await?;
 
@@ -64,6 +135,8 @@ pub async fn search_names_at_deck_level(
         search_text: q2,
         deck_level: results,
         note_level: vec![],
+        did_you_mean,
+        next_cursor: None,
     };
 
     Ok(Json(res))
@@ -92,8 +165,15 @@ pub async fn search_at_all_levels(
     AuthUser(user_id): AuthUser,
     Query(query): Query<SearchQuery>,
 ) -> crate::Result<impl Responder> {
+    let terms = terms(&query)?;
+    let after = cursor(&query)?;
     let res = db_thread(&sqlite_pool, move |conn| {
-        db::search_at_all_levels(conn, user_id, query.q)
+        let mut res = db::search_at_all_levels(conn, user_id, query.q, &terms, after.as_ref())?;
+        if query.tag.is_none() && after.is_none() {
+            res.deck_level =
+                with_shared_decks(conn, user_id, terms.text.join(" "), res.deck_level)?;
+        }
+        Ok(res)
     })
     .await?;
 
@@ -105,10 +185,62 @@ pub async fn search_quotes(
     AuthUser(user_id): AuthUser,
     Query(query): Query<SearchQuery>,
 ) -> crate::Result<impl Responder> {
+    let terms = terms(&query)?;
     let res = db_thread(&sqlite_pool, move |conn| {
-        db::search_quotes(conn, user_id, query.q)
+        db::search_quotes(conn, user_id, query.q, &terms)
     })
     .await?;
 
     Ok(Json(res))
 }
+
+pub async fn get_ranking(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let ranking = db_thread(&sqlite_pool, move |conn| {
+        db_ranking::get_ranking(conn, user_id)
+    })
+    .await?;
+
+    Ok(Json(ranking))
+}
+
+pub async fn edit_ranking(
+    Json(ranking): Json<Ranking>,
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    ranking.validate()?;
+    let ranking = db_thread(&sqlite_pool, move |conn| {
+        db_ranking::edit_ranking(conn, user_id, &ranking)
+    })
+    .await?;
+
+    Ok(Json(ranking))
+}
+
+pub async fn get_language(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let language = db_thread(&sqlite_pool, move |conn| {
+        db_users::get_search_language(conn, user_id)
+    })
+    .await?;
+
+    Ok(Json(SearchLanguage { language }))
+}
+
+pub async fn edit_language(
+    Json(search_language): Json<SearchLanguage>,
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let language = db_thread(&sqlite_pool, move |conn| {
+        db_users::edit_search_language(conn, user_id, search_language.language)
+    })
+    .await?;
+
+    Ok(Json(SearchLanguage { language }))
+}
@@ -0,0 +1,101 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::share_links as db;
+use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
+use crate::handler::AuthUser;
+use crate::interop::share_links::ProtoShareLink;
+use crate::interop::{IdParam, Key};
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Path};
+use rand::{RngCore, rng};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct ShareLinkParam {
+    id: Key,
+    link_id: Key,
+}
+
+#[derive(Deserialize)]
+pub struct TokenParam {
+    token: String,
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0; 16];
+    rng().fill_bytes(&mut bytes);
+
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn get_share_links(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let links = db_thread(&sqlite_pool, move |conn| {
+        db::get_share_links(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(links))
+}
+
+pub async fn create_share_link(
+    Json(proto_share_link): Json<ProtoShareLink>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let token = generate_token();
+    let links = db_thread(&sqlite_pool, move |conn| {
+        db::create_share_link(conn, user_id, params.id, proto_share_link, token)
+    })
+    .await?;
+
+    Ok(Json(links))
+}
+
+pub async fn revoke_share_link(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<ShareLinkParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let links = db_thread(&sqlite_pool, move |conn| {
+        db::revoke_share_link(conn, user_id, params.id, params.link_id)
+    })
+    .await?;
+
+    Ok(Json(links))
+}
+
+// the only handler that doesn't require a logged in user
+//
+pub async fn view_share_link(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<TokenParam>,
+) -> crate::Result<impl Responder> {
+    let public_deck = db_thread(&sqlite_pool, move |conn| {
+        db::view_share_link(conn, &params.token)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(public_deck))
+}
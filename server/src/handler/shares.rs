@@ -0,0 +1,115 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::shares as db;
+use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
+use crate::handler::AuthUser;
+use crate::interop::IdParam;
+use crate::interop::shares::ProtoShare;
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Path};
+
+// shares that the user has given to others
+//
+pub async fn get_shares(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let shares = db_thread(&sqlite_pool, move |conn| db::get_shares(conn, user_id)).await?;
+
+    Ok(Json(shares))
+}
+
+pub async fn delete_share(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let shares = db_thread(&sqlite_pool, move |conn| {
+        db::delete_share(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(shares))
+}
+
+// decks that other users have shared with the user
+//
+pub async fn get_received(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let decks = db_thread(&sqlite_pool, move |conn| db::get_received(conn, user_id)).await?;
+
+    Ok(Json(decks))
+}
+
+pub async fn get_shared_deck(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let deck = db_thread(&sqlite_pool, move |conn| {
+        db::get_shared_deck(conn, user_id, params.id)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(deck))
+}
+
+pub async fn get_deck_shares(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let shares = db_thread(&sqlite_pool, move |conn| {
+        db::get_deck_shares(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(shares))
+}
+
+pub async fn create_deck_share(
+    Json(proto_share): Json<ProtoShare>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let ProtoShare {
+        email,
+        include_subtree,
+    } = proto_share;
+
+    let recipient_id = db_thread(&sqlite_pool, move |conn| {
+        db::get_user_id_by_email(conn, &email)
+    })
+    .await?;
+
+    if recipient_id == Some(user_id) {
+        return Err(Error::ShareWithSelf);
+    }
+
+    let shares = db_thread(&sqlite_pool, move |conn| {
+        db::create_share(conn, user_id, params.id, recipient_id, include_subtree)
+    })
+    .await?;
+
+    Ok(Json(shares))
+}
@@ -0,0 +1,361 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::live;
+use crate::db::memorise as memorise_db;
+use crate::db::notes as notes_db;
+use crate::db::references as refs_db;
+use crate::db::sync as db;
+use crate::db::{DbError, SqlitePool, db_thread};
+use crate::handler::AuthUser;
+use crate::handler::memorise::sqlite_update_easiness_factor;
+use crate::interop::Key;
+use crate::interop::sync::{
+    SyncChange, SyncEntity, SyncOp, SyncOutcome, SyncStatus, SyncUpload, SyncUploadResult,
+};
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Query};
+use rusqlite::TransactionBehavior;
+use serde::Deserialize;
+
+#[allow(unused_imports)]
+use tracing::error;
+
+#[derive(Deserialize)]
+pub struct SyncQuery {
+    since: i64,
+}
+
+pub async fn get_delta(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+    Query(query): Query<SyncQuery>,
+) -> crate::Result<impl Responder> {
+    let delta = db_thread(&sqlite_pool, move |conn| {
+        db::get_delta(conn, user_id, query.since)
+    })
+    .await?;
+
+    Ok(Json(delta))
+}
+
+pub async fn upload(
+    Json(upload): Json<SyncUpload>,
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let result = db_thread(&sqlite_pool, move |conn| {
+        apply_upload(conn, user_id, upload)
+    })
+    .await?;
+
+    Ok(Json(result))
+}
+
+// applies the ops in order, each op either applies as a whole or not at all
+//
+fn apply_upload(
+    conn: &mut rusqlite::Connection,
+    user_id: Key,
+    upload: SyncUpload,
+) -> Result<SyncUploadResult, DbError> {
+    // holding the write lock for the whole upload means that nothing else can
+    // change an entity between its conflict check and the op being applied
+    let mut tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    // anything changed after this point was changed by the upload itself,
+    // so later ops can build upon earlier ones without conflicting
+    let baseline = db::last_seq(&tx, user_id)?;
+
+    let mut outcomes = Vec::with_capacity(upload.ops.len());
+    for op in upload.ops {
+        // an op that fails is rolled back on its own, the rest of the upload still applies.
+        // Clients aren't told about the changes that it had queued
+        let queued = live::mark();
+        let sp = tx.savepoint()?;
+        match apply(&sp, user_id, upload.since, baseline, op) {
+            Ok(applied) => {
+                sp.commit()?;
+                outcomes.push(applied);
+            }
+            Err(e) => {
+                error!("sync op failed: {:?}", e);
+                live::rollback_to(queued);
+                outcomes.push(outcome(SyncStatus::Failed, None));
+            }
+        }
+    }
+
+    let seq = db::last_seq(&tx, user_id)?;
+    tx.commit()?;
+
+    Ok(SyncUploadResult { seq, outcomes })
+}
+
+fn outcome(status: SyncStatus, current: Option<SyncChange>) -> SyncOutcome {
+    SyncOutcome {
+        status,
+        created: vec![],
+        current,
+    }
+}
+
+// the entity that an op checks for conflicts against
+//
+fn target(op: &SyncOp) -> (SyncEntity, Key) {
+    match op {
+        SyncOp::CreateNote { note } => (SyncEntity::Deck, note.deck_id),
+        SyncOp::EditNote { id, .. } | SyncOp::DeleteNote { id } => (SyncEntity::Note, *id),
+        SyncOp::EditRefs { note_id, .. } => (SyncEntity::Ref, *note_id),
+        SyncOp::EditCard { id, .. } | SyncOp::RateCard { id, .. } | SyncOp::DeleteCard { id } => {
+            (SyncEntity::Card, *id)
+        }
+    }
+}
+
+fn apply(
+    conn: &rusqlite::Connection,
+    user_id: Key,
+    since: i64,
+    baseline: i64,
+    op: SyncOp,
+) -> Result<SyncOutcome, DbError> {
+    if let SyncOp::RateCard { rating, .. } = op
+        && !(0..=5).contains(&rating)
+    {
+        return Ok(outcome(SyncStatus::Invalid, None));
+    }
+
+    let (entity, id) = target(&op);
+    let current = db::get_change(conn, user_id, entity, id)?;
+
+    // a note only has a ref change once it's had refs, so check that the note itself exists
+    let exists = match &op {
+        SyncOp::EditRefs { note_id, .. } => {
+            db::get_change(conn, user_id, SyncEntity::Note, *note_id)?
+                .is_some_and(|note| note.data.is_some())
+        }
+        _ => current.as_ref().is_some_and(|c| c.data.is_some()),
+    };
+    if !exists {
+        return Ok(outcome(SyncStatus::Missing, current));
+    }
+
+    // creating a note only needs the deck to still be around
+    let checks_conflict = !matches!(op, SyncOp::CreateNote { .. });
+    if let Some(c) = &current
+        && checks_conflict
+        && c.seq > since
+        && c.seq <= baseline
+    {
+        return Ok(outcome(SyncStatus::Conflict, current));
+    }
+
+    let mut created = vec![];
+    match op {
+        SyncOp::CreateNote { note } => {
+            let existing = db::note_ids_in_deck(conn, note.deck_id)?;
+            created = notes_db::create_notes_in_tx(conn, user_id, note)?
+                .into_iter()
+                .map(|n| n.id)
+                .filter(|id| !existing.contains(id))
+                .collect();
+        }
+        SyncOp::EditNote { id, note } => {
+            notes_db::edit_note(conn, user_id, note, id, None)?;
+        }
+        SyncOp::DeleteNote { id } => {
+            notes_db::delete_note_properly_in_tx(conn, user_id, id)?;
+        }
+        SyncOp::EditRefs { note_id, diff } => {
+            refs_db::update_references_in_tx(conn, diff, user_id, note_id)?;
+        }
+        SyncOp::EditCard { id, card } => {
+            memorise_db::edit_flashcard(conn, user_id, card, id)?;
+        }
+        SyncOp::RateCard { id, rating } => {
+            let card = memorise_db::get_card_full_fat(conn, user_id, id)?;
+            let card = sqlite_update_easiness_factor(card, rating);
+            memorise_db::card_rated_in_tx(conn, user_id, card, rating)?;
+        }
+        SyncOp::DeleteCard { id } => {
+            memorise_db::delete_flashcard_in_tx(conn, user_id, id)?;
+        }
+    }
+
+    Ok(SyncOutcome {
+        status: SyncStatus::Applied,
+        created,
+        current: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::decks as decks_db;
+    use crate::db::test_util::{OWNER, add_note, create_deck, test_db};
+    use crate::interop::font::Font;
+    use crate::interop::notes::{NoteKind, ProtoNote};
+    use crate::interop::sync::SyncData;
+
+    fn edit(conn: &rusqlite::Connection, id: Key, content: &str) -> SyncOp {
+        let mut note = notes_db::get_note(conn, OWNER, id).unwrap().unwrap();
+        note.content = content.to_string();
+        SyncOp::EditNote { id, note }
+    }
+
+    fn statuses(result: &SyncUploadResult) -> Vec<SyncStatus> {
+        result.outcomes.iter().map(|o| o.status).collect()
+    }
+
+    fn content(conn: &rusqlite::Connection, id: Key) -> String {
+        notes_db::get_note(conn, OWNER, id)
+            .unwrap()
+            .unwrap()
+            .content
+    }
+
+    #[test]
+    fn test_stale_edit_is_a_conflict() {
+        let mut db = test_db("sync-conflict");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "original");
+        let since = db::last_seq(&db.conn, OWNER).unwrap();
+
+        // edited on the server after the client last synced
+        let op = edit(&db.conn, note_id, "edited on the server");
+        apply_upload(
+            &mut db.conn,
+            OWNER,
+            SyncUpload {
+                since,
+                ops: vec![op],
+            },
+        )
+        .unwrap();
+
+        let op = edit(&db.conn, note_id, "edited offline");
+        let result = apply_upload(
+            &mut db.conn,
+            OWNER,
+            SyncUpload {
+                since,
+                ops: vec![op],
+            },
+        )
+        .unwrap();
+        assert_eq!(statuses(&result), vec![SyncStatus::Conflict]);
+        let current = result.outcomes[0].current.as_ref().unwrap();
+        assert!(matches!(
+            &current.data,
+            Some(SyncData::Note(note)) if note.content == "edited on the server"
+        ));
+        assert_eq!(content(&db.conn, note_id), "edited on the server");
+    }
+
+    #[test]
+    fn test_ops_build_on_earlier_ops() {
+        let mut db = test_db("sync-build-on");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "original");
+        let since = db::last_seq(&db.conn, OWNER).unwrap();
+
+        let ops = vec![
+            edit(&db.conn, note_id, "first draft"),
+            edit(&db.conn, note_id, "second draft"),
+        ];
+        let result = apply_upload(&mut db.conn, OWNER, SyncUpload { since, ops }).unwrap();
+        assert_eq!(
+            statuses(&result),
+            vec![SyncStatus::Applied, SyncStatus::Applied]
+        );
+        assert_eq!(content(&db.conn, note_id), "second draft");
+        assert_eq!(result.seq, db::last_seq(&db.conn, OWNER).unwrap());
+        assert!(result.seq > since);
+    }
+
+    #[test]
+    fn test_deleted_deck_reports_its_notes_and_points() {
+        let mut db = test_db("sync-deleted-deck");
+        let deck_id = create_deck(&db.conn, "event", "Battle of Actium");
+        let note_id = add_note(&mut db.conn, deck_id, "the fleets met");
+        let point_id: Key = db
+            .conn
+            .query_row(
+                "INSERT INTO points(deck_id, title, kind) VALUES (?1, 'Actium', 'point')
+                 RETURNING id",
+                [deck_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let since = db::last_seq(&db.conn, OWNER).unwrap();
+
+        decks_db::delete(&db.conn, OWNER, deck_id).unwrap();
+
+        let delta = db::get_delta(&db.conn, OWNER, since).unwrap();
+        for (entity, id) in [
+            (SyncEntity::Deck, deck_id),
+            (SyncEntity::Note, note_id),
+            (SyncEntity::Point, point_id),
+        ] {
+            let change = delta
+                .changes
+                .iter()
+                .find(|c| c.entity == entity && c.id == id)
+                .unwrap_or_else(|| panic!("no change for {entity:?} {id:?}"));
+            assert!(change.data.is_none());
+        }
+    }
+
+    #[test]
+    fn test_failed_op_is_rolled_back_alone() {
+        let mut db = test_db("sync-failed-op");
+        let deck_id = create_deck(&db.conn, "idea", "Stoicism");
+        let note_id = add_note(&mut db.conn, deck_id, "original");
+        let since = db::last_seq(&db.conn, OWNER).unwrap();
+
+        let proto = |point_id| ProtoNote {
+            kind: NoteKind::Note,
+            font: Font::Serif,
+            content: vec!["written offline".to_string()],
+            deck_id,
+            point_id,
+            prev_note_id: Some(note_id),
+            next_note_id: None,
+        };
+        let ops = vec![
+            edit(&db.conn, note_id, "edited offline"),
+            // there's no such point, so inserting the note fails
+            SyncOp::CreateNote {
+                note: proto(Some(Key(9999))),
+            },
+            SyncOp::CreateNote { note: proto(None) },
+        ];
+        let result = apply_upload(&mut db.conn, OWNER, SyncUpload { since, ops }).unwrap();
+        assert_eq!(
+            statuses(&result),
+            vec![SyncStatus::Applied, SyncStatus::Failed, SyncStatus::Applied]
+        );
+        assert_eq!(result.outcomes[2].created.len(), 1);
+
+        assert_eq!(content(&db.conn, note_id), "edited offline");
+        let notes = notes_db::notes_for_deck(&db.conn, deck_id).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert!(notes.iter().all(|n| n.point_id.is_none()));
+    }
+}
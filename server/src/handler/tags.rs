@@ -0,0 +1,180 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::db::tags as db;
+use crate::db::{SqlitePool, db_thread};
+use crate::error::Error;
+use crate::handler::AuthUser;
+use crate::interop::tags::ProtoTag;
+use crate::interop::{IdParam, Key};
+use actix_web::Responder;
+use actix_web::web::{Data, Json, Path};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct TagParam {
+    id: Key,
+    tag_id: Key,
+}
+
+fn tag_name(proto_tag: &ProtoTag) -> crate::Result<String> {
+    let name = proto_tag.name.trim();
+    if name.is_empty() {
+        Err(Error::EmptyTagName)
+    } else {
+        Ok(name.to_string())
+    }
+}
+
+pub async fn get_tags(
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let tags = db_thread(&sqlite_pool, move |conn| db::get_tags(conn, user_id)).await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn create_tag(
+    Json(proto_tag): Json<ProtoTag>,
+    sqlite_pool: Data<SqlitePool>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let name = tag_name(&proto_tag)?;
+    let tag = db_thread(&sqlite_pool, move |conn| {
+        db::get_or_create_tag(conn, user_id, &name)
+    })
+    .await?;
+
+    Ok(Json(tag))
+}
+
+pub async fn edit_tag(
+    Json(proto_tag): Json<ProtoTag>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let name = tag_name(&proto_tag)?;
+    let tag = db_thread(&sqlite_pool, move |conn| {
+        db::edit_tag(conn, user_id, params.id, &name)
+    })
+    .await?
+    .ok_or(Error::NotFound)?;
+
+    Ok(Json(tag))
+}
+
+pub async fn delete_tag(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::delete_tag(conn, user_id, params.id)?;
+        db::get_tags(conn, user_id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn get_deck_tags(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::get_deck_tags(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn add_deck_tag(
+    Json(proto_tag): Json<ProtoTag>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let name = tag_name(&proto_tag)?;
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::add_deck_tag(conn, user_id, params.id, &name)?;
+        db::get_deck_tags(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn remove_deck_tag(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<TagParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::remove_deck_tag(conn, user_id, params.id, params.tag_id)?;
+        db::get_deck_tags(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn get_note_tags(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::get_note_tags(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn add_note_tag(
+    Json(proto_tag): Json<ProtoTag>,
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<IdParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let name = tag_name(&proto_tag)?;
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::add_note_tag(conn, user_id, params.id, &name)?;
+        db::get_note_tags(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
+
+pub async fn remove_note_tag(
+    sqlite_pool: Data<SqlitePool>,
+    params: Path<TagParam>,
+    AuthUser(user_id): AuthUser,
+) -> crate::Result<impl Responder> {
+    let tags = db_thread(&sqlite_pool, move |conn| {
+        db::remove_note_tag(conn, user_id, params.id, params.tag_id)?;
+        db::get_note_tags(conn, user_id, params.id)
+    })
+    .await?;
+
+    Ok(Json(tags))
+}
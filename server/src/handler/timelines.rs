@@ -18,7 +18,8 @@
 use crate::db::points as points_db;
 use crate::db::timelines as db;
 use crate::db::{SqlitePool, db_thread};
-use crate::handler::{AuthUser, PaginationQuery, decks};
+use crate::error::Error;
+use crate::handler::{AuthUser, IfMatch, PaginationQuery, decks, versioned_edit};
 use crate::interop::IdParam;
 use crate::interop::decks::{DeckKind, ProtoDeck, ProtoSlimDeck};
 use crate::interop::points as points_interop;
@@ -72,10 +73,15 @@ pub async fn edit(
     sqlite_pool: Data<SqlitePool>,
     params: Path<IdParam>,
     AuthUser(user_id): AuthUser,
+    IfMatch(version): IfMatch,
 ) -> crate::Result<impl Responder> {
-    let timeline = db_thread(&sqlite_pool, move |conn| {
-        db::edit(conn, user_id, timeline, params.id)
-    })
+    let version = version.ok_or(Error::PreconditionRequired)?;
+    let timeline_id = params.id;
+    let timeline = versioned_edit(
+        &sqlite_pool,
+        move |conn| db::edit(conn, user_id, timeline, timeline_id, version),
+        move |conn| db::get(conn, user_id, timeline_id),
+    )
     .await?;
 
     Ok(Json(timeline))
@@ -96,7 +102,7 @@ pub async fn add_point(
     AuthUser(user_id): AuthUser,
 ) -> crate::Result<impl Responder> {
     let timeline = db_thread(&sqlite_pool, move |conn| {
-        points_db::create(conn, point, params.id)?;
+        points_db::create(conn, user_id, point, params.id)?;
         db::get(conn, user_id, params.id)
     })
     .await?
@@ -115,7 +121,7 @@ pub async fn add_multipoints(
         // nocheckin: shouldn't this be in a transaction?
         //
         for point in points {
-            points_db::create(conn, point, params.id)?;
+            points_db::create(conn, user_id, point, params.id)?;
         }
         db::get(conn, user_id, params.id)
     })
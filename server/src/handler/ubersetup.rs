@@ -18,12 +18,14 @@
 use crate::db::bookmarks as db_bookmarks;
 use crate::db::memorise as db_memorise;
 use crate::db::references as db_references;
+use crate::db::saved_searches as db_saved_searches;
 use crate::db::uploader as db_uploader;
 use crate::db::{SqlitePool, db_thread};
 use crate::handler::AuthUser;
 use crate::interop::Key;
 use crate::interop::bookmarks as interop_bookmarks;
 use crate::interop::decks::SlimDeck;
+use crate::interop::saved_searches as interop_saved_searches;
 use crate::interop::uploader as interop_uploader;
 use actix_web::Responder;
 use actix_web::web::{Data, Json};
@@ -41,6 +43,7 @@ struct UberStruct {
     pub memorise_review_count: i32,
     pub memorise_earliest_review_date: Option<chrono::NaiveDateTime>,
     pub bookmarks: Vec<interop_bookmarks::Bookmark>,
+    pub saved_searches: Vec<interop_saved_searches::SavedSearch>, // only the pinned ones
 }
 
 pub async fn setup(
@@ -69,6 +72,11 @@ pub async fn setup(
     })
     .await?;
 
+    let saved_searches = db_thread(&sqlite_pool, move |conn| {
+        db_saved_searches::get_pinned_saved_searches(conn, user_id)
+    })
+    .await?;
+
     let uber = UberStruct {
         directory,
         recently_used_decks,
@@ -76,6 +84,7 @@ pub async fn setup(
         memorise_review_count: upcoming_review.review_count,
         memorise_earliest_review_date: upcoming_review.earliest_review_date,
         bookmarks,
+        saved_searches,
     };
 
     Ok(Json(uber))
@@ -16,11 +16,13 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::ServerConfig;
+use crate::db::audit::{self as audit_db, Payload};
 use crate::db::uploader as db;
 use crate::db::{SqlitePool, db_thread};
 use crate::error::Error;
 use crate::handler::AuthUser;
-use crate::interop::AtLeastParam;
+use crate::interop::audit::{AuditAction, AuditEntity};
+use crate::interop::{AtLeastParam, Key};
 use actix_multipart::Multipart;
 use actix_web::Responder;
 use actix_web::web::{Data, Json, Path};
@@ -77,7 +79,16 @@ pub async fn create(
         }
 
         db_thread(&sqlite_pool, move |conn| {
-            db::add_image_entry(conn, user_id, derived)
+            let payload = Payload::after(&serde_json::json!({ "filename": derived }));
+            db::add_image_entry(conn, user_id, derived)?;
+            audit_db::record(
+                conn,
+                user_id,
+                AuditAction::Create,
+                AuditEntity::Image,
+                Some(Key(conn.last_insert_rowid())),
+                payload,
+            )
         })
         .await?;
     }
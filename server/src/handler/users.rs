@@ -20,6 +20,7 @@ use crate::db::users as db;
 use crate::db::{SqlitePool, db_thread};
 use crate::error::Error;
 use crate::interop::Key;
+use crate::interop::audit::AuditAction;
 use crate::interop::users as interop;
 use crate::session;
 use actix_web::web::{Data, Json};
@@ -52,6 +53,11 @@ pub async fn login(
         // save id to the session
         session::save_user_id(&session, id)?;
 
+        db_thread(&sqlite_pool, move |conn| {
+            db::record_session_event(conn, id, AuditAction::Login)
+        })
+        .await?;
+
         if id == Key(1) {
             user.admin = Some(interop::Admin {
                 db_name: env::var("SQLITE_DB")?,
@@ -69,9 +75,16 @@ pub async fn login(
 }
 
 pub async fn logout(
-    _sqlite_pool: Data<SqlitePool>,
+    sqlite_pool: Data<SqlitePool>,
     session: actix_session::Session,
 ) -> crate::Result<impl Responder> {
+    if let Ok(user_id) = session::user_id(&session) {
+        db_thread(&sqlite_pool, move |conn| {
+            db::record_session_event(conn, user_id, AuditAction::Logout)
+        })
+        .await?;
+    }
+
     session.purge();
     // todo: what to return when logging out???
     Ok(Json(true))
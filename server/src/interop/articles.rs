@@ -31,6 +31,7 @@ pub struct Article {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     pub source: Option<String>,
     pub author: Option<String>,
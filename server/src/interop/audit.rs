@@ -0,0 +1,125 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interop::Key;
+use rusqlite::types::{ToSql, ToSqlOutput};
+
+// the kind of thing that an audit event is about
+//
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuditEntity {
+    Alias,
+    Bookmark,
+    Card,
+    Database,
+    Deck,
+    DeckTag,
+    Image,
+    Note,
+    NoteTag,
+    Point,
+    Ref,
+    SavedSearch,
+    Share,
+    ShareLink,
+    Tag,
+    User,
+}
+
+impl AuditEntity {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            AuditEntity::Alias => "alias",
+            AuditEntity::Bookmark => "bookmark",
+            AuditEntity::Card => "card",
+            AuditEntity::Database => "database",
+            AuditEntity::Deck => "deck",
+            AuditEntity::DeckTag => "deck-tag",
+            AuditEntity::Image => "image",
+            AuditEntity::Note => "note",
+            AuditEntity::NoteTag => "note-tag",
+            AuditEntity::Point => "point",
+            AuditEntity::Ref => "ref",
+            AuditEntity::SavedSearch => "saved-search",
+            AuditEntity::Share => "share",
+            AuditEntity::ShareLink => "share-link",
+            AuditEntity::Tag => "tag",
+            AuditEntity::User => "user",
+        }
+    }
+}
+
+impl ToSql for AuditEntity {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Edit,
+    Delete,
+    Convert,
+    Merge,
+    Move,
+    Split,
+    Summarize,
+    Rate,
+    Login,
+    Logout,
+    Migrate,
+}
+
+impl AuditAction {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Edit => "edit",
+            AuditAction::Delete => "delete",
+            AuditAction::Convert => "convert",
+            AuditAction::Merge => "merge",
+            AuditAction::Move => "move",
+            AuditAction::Split => "split",
+            AuditAction::Summarize => "summarize",
+            AuditAction::Rate => "rate",
+            AuditAction::Login => "login",
+            AuditAction::Logout => "logout",
+            AuditAction::Migrate => "migrate",
+        }
+    }
+}
+
+impl ToSql for AuditAction {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+// user_id is None for events that weren't caused by a user, e.g. migrations
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub id: Key,
+    pub user_id: Option<Key>,
+    pub created_at: chrono::NaiveDateTime,
+    pub entity: String,
+    pub entity_id: Option<Key>,
+    pub action: String,
+    pub payload: Option<serde_json::Value>,
+}
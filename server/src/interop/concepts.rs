@@ -32,6 +32,7 @@ pub struct Concept {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     pub notes: Vec<Note>,
     pub arrivals: Vec<Arrival>,
@@ -49,6 +50,7 @@ impl From<crate::db::decks::DeckBase> for Concept {
             insignia: d.insignia,
             font: d.font,
             impact: d.impact,
+            version: d.version,
 
             notes: vec![],
             arrivals: vec![],
@@ -121,20 +121,31 @@ impl fmt::Display for RefKind {
     }
 }
 
-impl FromSql for RefKind {
-    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        let input = value.as_str()?;
-        match input {
+impl FromStr for RefKind {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
             "ref" => Ok(RefKind::Ref),
             "ref_to_parent" => Ok(RefKind::RefToParent),
             "ref_to_child" => Ok(RefKind::RefToChild),
             "ref_in_contrast" => Ok(RefKind::RefInContrast),
             "ref_critical" => Ok(RefKind::RefCritical),
-            _ => Err(FromSqlError::InvalidType),
+            _ => Err(crate::Error::InvalidRequest {
+                message: format!("'{s}' isn't a kind of ref"),
+                field: None,
+            }),
         }
     }
 }
 
+impl FromSql for RefKind {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        s.parse().map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
 impl ToSql for RefKind {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
         Ok(ToSqlOutput::from(self.to_string()))
@@ -160,6 +171,36 @@ pub struct Ref {
     pub impact: i32,
 }
 
+// when two decks are merged, decides which deck's extras row survives
+// if both decks have one (e.g. both have an article_extras row)
+//
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, serde_repr::Serialize_repr, serde_repr::Deserialize_repr,
+)]
+#[repr(u8)]
+pub enum ExtrasMergeStrategy {
+    KeepTarget = 1,
+    KeepSource,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtoMerge {
+    pub extras_strategy: ExtrasMergeStrategy,
+}
+
+// the result of converting a deck to another kind. If the conversion would lose data
+// (e.g. an article's source and author) and wasn't forced then converted is false,
+// the deck is left untouched and warnings describes what would have been lost
+//
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeckConversion {
+    pub converted: bool,
+    pub warnings: Vec<String>,
+    pub deck: SlimDeck,
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProtoDeck {
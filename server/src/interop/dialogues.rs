@@ -66,6 +66,7 @@ pub struct Dialogue {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     pub notes: Vec<Note>,
     pub arrivals: Vec<Arrival>,
@@ -32,6 +32,7 @@ pub struct Event {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     pub location_textual: Option<String>,
     pub longitude: Option<f32>,
@@ -60,6 +61,7 @@ impl From<crate::db::decks::DeckBase> for Event {
             insignia: d.insignia,
             font: d.font,
             impact: d.impact,
+            version: d.version,
 
             location_textual: None,
             longitude: None,
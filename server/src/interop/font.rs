@@ -16,6 +16,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::str::FromStr;
 
 #[derive(
     Copy, Clone, Debug, PartialEq, Eq, serde_repr::Serialize_repr, serde_repr::Deserialize_repr,
@@ -45,6 +46,23 @@ impl From<Font> for i32 {
     }
 }
 
+impl FromStr for Font {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "serif" => Ok(Font::Serif),
+            "sans" => Ok(Font::Sans),
+            "cursive" => Ok(Font::Cursive),
+            "ai" => Ok(Font::AI),
+            "dewalpergens" => Ok(Font::DeWalpergens),
+            "essays1743" => Ok(Font::Essays1743),
+            "hyperlegible" => Ok(Font::Hyperlegible),
+            _ => Err(crate::Error::StringConversionToEnum),
+        }
+    }
+}
+
 impl FromSql for Font {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
         let i = value.as_i64()?;
@@ -0,0 +1,105 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fmt, str::FromStr};
+
+// a problem found when checking the database for consistency
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub check: &'static str,
+    pub description: String,
+    pub repaired: bool,
+}
+
+// the fts5 maintenance commands that can be run on the full text search tables
+//
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FtsCommand {
+    Rebuild,
+    Optimize,
+    IntegrityCheck,
+}
+
+impl FtsCommand {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            FtsCommand::Rebuild => "rebuild",
+            FtsCommand::Optimize => "optimize",
+            FtsCommand::IntegrityCheck => "integrity-check",
+        }
+    }
+}
+
+impl fmt::Display for FtsCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FtsCommand {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rebuild" => Ok(FtsCommand::Rebuild),
+            "optimize" => Ok(FtsCommand::Optimize),
+            "integrity-check" => Ok(FtsCommand::IntegrityCheck),
+            _ => Err(crate::Error::InvalidFtsCommand),
+        }
+    }
+}
+
+// the outcome of running an FtsCommand on a single full text search table
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FtsReport {
+    pub table: &'static str,
+    pub command: FtsCommand,
+    pub ok: bool,
+    pub message: Option<String>,
+    pub duration_ms: u64,
+    pub index_bytes_before: i64,
+    pub index_bytes_after: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fts_command_round_trip() {
+        for command in [
+            FtsCommand::Rebuild,
+            FtsCommand::Optimize,
+            FtsCommand::IntegrityCheck,
+        ] {
+            assert_eq!(command.to_string().parse::<FtsCommand>().unwrap(), command);
+            assert_eq!(
+                serde_json::to_value(command).unwrap(),
+                serde_json::Value::from(command.as_str())
+            );
+        }
+
+        assert!("integrity_check".parse::<FtsCommand>().is_err());
+        assert!("Rebuild".parse::<FtsCommand>().is_err());
+        assert!("".parse::<FtsCommand>().is_err());
+    }
+}
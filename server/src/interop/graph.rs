@@ -16,7 +16,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::interop::Key;
-use crate::interop::decks::{RefKind, SlimDeck};
+use crate::interop::decks::{DeckKind, RefKind, SlimDeck};
+use crate::interop::shares::Foreign;
 
 #[derive(
     Hash,
@@ -49,4 +50,34 @@ pub struct ConnectivityData {
     pub source_deck: SlimDeck,
     pub edges: Vec<Edge>,
     pub decks: Vec<SlimDeck>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreign: Option<Foreign>,
+    // set when there were more decks within reach than the budget allowed
+    pub truncated: bool,
+}
+
+pub const MAX_GRAPH_DEPTH: u32 = 4;
+pub const MAX_GRAPH_BUDGET: usize = 500;
+
+// how far out from a deck its graph reaches. Decks that are graph terminators
+// are shown but never expanded and the budget is the most decks that are
+// returned, empty kinds allow every kind
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphReach {
+    pub depth: u32,
+    pub ref_kinds: Vec<RefKind>,
+    pub deck_kinds: Vec<DeckKind>,
+    pub budget: usize,
+}
+
+impl Default for GraphReach {
+    fn default() -> Self {
+        GraphReach {
+            depth: 1,
+            ref_kinds: vec![],
+            deck_kinds: vec![],
+            budget: 200,
+        }
+    }
 }
@@ -0,0 +1,32 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interop::Key;
+
+// a notification sent to a user's open clients whenever one of their
+// decks, notes, refs, points, bookmarks or cards changes
+//
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveEvent {
+    #[serde(skip)]
+    pub user_id: Key,
+    pub entity: &'static str,
+    pub action: &'static str,
+    pub id: Option<Key>,
+    pub deck_id: Option<Key>, // the deck that should be re-fetched, if known
+}
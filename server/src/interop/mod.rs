@@ -15,16 +15,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod aliases;
 pub mod articles;
+pub mod audit;
 pub mod bookmarks;
 pub mod concepts;
 pub mod decks;
 pub mod dialogues;
 pub mod events;
 pub mod font;
+pub mod fsck;
 pub mod graph;
 pub mod ideas;
 pub mod key;
+pub mod live;
 pub mod memorise;
 pub mod notes;
 pub mod people;
@@ -32,8 +36,14 @@ pub mod points;
 pub mod predictions;
 pub mod quotes;
 pub mod references;
+pub mod related;
+pub mod saved_searches;
 pub mod search;
+pub mod share_links;
+pub mod shares;
 pub mod stats;
+pub mod sync;
+pub mod tags;
 pub mod timelines;
 pub mod uploader;
 pub mod users;
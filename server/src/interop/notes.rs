@@ -71,9 +71,18 @@ pub struct Note {
     pub content: String,
     pub point_id: Option<Key>,
     pub font: Font,
+    #[serde(default)]
+    pub private: bool,
+    // bumped on every edit, 0 when a client doesn't send it
+    #[serde(default)]
+    pub version: i32,
 
     pub refs: Vec<Ref>,
     pub flashcards: Vec<FlashCard>,
+
+    // search results only: the text around the matches of a long note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -88,6 +97,14 @@ pub struct ProtoNote {
     pub next_note_id: Option<Key>,
 }
 
+// used for both a note's private flag and a deck's default for its new notes
+//
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Privacy {
+    pub private: bool,
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PreviewNotes {
@@ -33,6 +33,7 @@ pub struct Person {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     pub sort_date: Option<chrono::NaiveDate>,
 
@@ -58,6 +59,7 @@ impl From<crate::db::decks::DeckBase> for Person {
             insignia: d.insignia,
             font: d.font,
             impact: d.impact,
+            version: d.version,
 
             sort_date: None,
 
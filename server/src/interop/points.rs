@@ -98,3 +98,33 @@ pub struct PointsWithinYears {
     pub upper_year: i32,
     pub points: Vec<Point>,
 }
+
+// a region of the earth's surface, in degrees. A bounding box whose west is
+// greater than its east crosses the antimeridian
+//
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum Area {
+    #[serde(rename_all = "camelCase")]
+    BoundingBox {
+        south: f64,
+        west: f64,
+        north: f64,
+        east: f64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Radius {
+        latitude: f64,
+        longitude: f64,
+        radius_km: f64,
+    },
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointsWithinArea {
+    pub area: Area,
+    pub lower_year: Option<i32>,
+    pub upper_year: Option<i32>,
+    pub points: Vec<Point>,
+}
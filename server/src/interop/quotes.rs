@@ -32,6 +32,7 @@ pub struct Quote {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     // will always be a single note
     pub notes: Vec<Note>,
@@ -0,0 +1,47 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interop::Key;
+use crate::interop::search::SearchResults;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub id: Key,
+    pub name: String,
+    pub query: String, // in the search query language, e.g. kind:person ref:"Stoicism"
+    pub pinned: bool,  // pinned searches are listed alongside the bookmarks as smart decks
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtoSavedSearch {
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+// a saved search along with the result of running it again
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearchResults {
+    pub saved_search: SavedSearch,
+    pub results: SearchResults,
+}
@@ -15,8 +15,14 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::interop::decks::SlimDeck;
+use crate::error::Error;
+use crate::interop::Key;
+use crate::interop::decks::{DeckKind, SlimDeck};
+use crate::interop::font::Font;
 use crate::interop::notes::Note;
+use crate::interop::shares::Foreign;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::str::FromStr;
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -24,6 +30,24 @@ pub struct SearchDeck {
     pub rank: f32,
     pub deck: SlimDeck,
     pub notes: Vec<Note>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreign: Option<Foreign>,
+    // a fragment of the point (or for a result with a source, the annotation or
+    // dialogue message) that matched, with the match marked up as :searched()
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    // set when the notes were found through something other than their content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SearchSource>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSource {
+    // the annotation of one of the note's refs
+    Annotation,
+    // the original text of the dialogue message that the note was made from
+    Dialogue,
 }
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -32,4 +56,428 @@ pub struct SearchResults {
     pub search_text: String,
     pub deck_level: Vec<SearchDeck>,
     pub note_level: Vec<SearchDeck>,
+    // the name of a deck that was probably meant when nothing matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did_you_mean: Option<String>,
+    // given when there are more deck level results, pass it back to get the next page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+// how a user's search results are ordered. The bm25 weights are given to fts5
+// for each column, the remaining weights decide how much each of a deck's
+// signals (all scaled to between 0 and 1) adds to its text relevance
+//
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ranking {
+    pub title: f64,
+    pub aliases: f64,
+    pub body: f64,
+    pub impact: f64,
+    pub insignia: f64,
+    pub visits: f64,
+    pub recency: f64,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Ranking {
+            title: 10.0,
+            aliases: 5.0,
+            body: 1.0,
+            impact: 0.4,
+            insignia: 0.1,
+            visits: 0.2,
+            recency: 0.1,
+        }
+    }
+}
+
+impl Ranking {
+    pub fn validate(&self) -> crate::Result<()> {
+        let weights = [
+            ("title", self.title),
+            ("aliases", self.aliases),
+            ("body", self.body),
+            ("impact", self.impact),
+            ("insignia", self.insignia),
+            ("visits", self.visits),
+            ("recency", self.recency),
+        ];
+        for (field, weight) in weights {
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(Error::InvalidRequest {
+                    message: format!("the {field} weight must be a positive number"),
+                    field: Some(field.to_string()),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+// the language that a user's notes are mostly written in. Only English is
+// stemmed, the others are searched by substring using the trigram indexes
+//
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "de")]
+    German,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "ja")]
+    Japanese,
+    #[serde(rename = "zh")]
+    Chinese,
+    #[serde(rename = "ko")]
+    Korean,
+}
+
+impl Language {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::French => "fr",
+            Language::Spanish => "es",
+            Language::Italian => "it",
+            Language::Japanese => "ja",
+            Language::Chinese => "zh",
+            Language::Korean => "ko",
+        }
+    }
+
+    pub fn is_stemmed(self) -> bool {
+        self == Language::English
+    }
+}
+
+impl FromSql for Language {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "en" => Ok(Language::English),
+            "de" => Ok(Language::German),
+            "fr" => Ok(Language::French),
+            "es" => Ok(Language::Spanish),
+            "it" => Ok(Language::Italian),
+            "ja" => Ok(Language::Japanese),
+            "zh" => Ok(Language::Chinese),
+            "ko" => Ok(Language::Korean),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for Language {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchLanguage {
+    pub language: Language,
+}
+
+// a position within ranked deck level results. Recency is measured from when the
+// first page was searched for so that every page of a search is scored the same
+//
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cursor {
+    pub score: f32,
+    pub deck_id: Key,
+    pub searched_at: i64,
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}_{}", self.score, self.deck_id, self.searched_at)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidRequest {
+            message: format!("'{s}' isn't a valid cursor"),
+            field: Some("cursor".to_string()),
+        };
+
+        let mut parts = s.split('_');
+        let (Some(score), Some(deck_id), Some(searched_at), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+
+        Ok(Cursor {
+            score: score.parse().map_err(|_| invalid())?,
+            deck_id: deck_id.parse().map_err(|_| invalid())?,
+            searched_at: searched_at.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+// a search query split into its full text and its filters, e.g.
+//
+//   kind:person before:1800 insignia:2 ref:"Stoicism" font:serif "exact phrase" -excluded
+//
+//   kind:K        decks of kind K, can be given more than once to allow several kinds
+//   before:Y      decks with a point dated before the year Y
+//   after:Y       decks with a point dated in or after the year Y
+//   insignia:B    decks with all of the insignia bits in B
+//   font:F        decks (or for note level results, notes) using font F
+//   ref:"NAME"    decks connected to the NAME deck by a ref in either direction,
+//                 at the note level: notes that are in or refer to the NAME deck
+//   -TERM         text that must not match
+//
+// everything else is full text and is given to fts5 as it was written. The tag
+// isn't part of the query language, it's set from a search's tag parameter
+//
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchTerms {
+    pub text: Vec<String>,
+    pub excluded: Vec<String>,
+    pub kinds: Vec<DeckKind>,
+    pub before: Option<i32>,
+    pub after: Option<i32>,
+    pub insignia: Option<i32>,
+    pub font: Option<Font>,
+    pub refs: Vec<String>,
+    pub tag: Option<Key>,
+}
+
+impl SearchTerms {
+    pub fn has_filters(&self) -> bool {
+        self.tag.is_some()
+            || !self.kinds.is_empty()
+            || self.before.is_some()
+            || self.after.is_some()
+            || self.insignia.is_some()
+            || self.font.is_some()
+            || !self.refs.is_empty()
+    }
+
+    // the text as an fts5 MATCH expression, empty if there's nothing to match
+    pub fn fts(&self) -> String {
+        let text = self.text.join(" ");
+        if text.is_empty() || self.excluded.is_empty() {
+            text
+        } else {
+            format!("({}) NOT ({})", text, self.excluded.join(" OR "))
+        }
+    }
+
+    // the text that would match anything excluded, used when there's no text to match
+    pub fn excluded_fts(&self) -> String {
+        self.excluded.join(" OR ")
+    }
+
+    // Chinese, Japanese and Korean aren't written with spaces between words so
+    // the stemmed indexes treat each run of characters as a single token
+    pub fn has_cjk(&self) -> bool {
+        self.text
+            .iter()
+            .chain(&self.excluded)
+            .flat_map(|t| t.chars())
+            .any(is_cjk)
+    }
+
+    // the trigram indexes can't match anything shorter than 3 characters
+    pub fn suits_trigrams(&self) -> bool {
+        self.text
+            .iter()
+            .chain(&self.excluded)
+            .filter(|t| !matches!(t.as_str(), "AND" | "OR" | "NOT"))
+            .all(|t| {
+                let t = t.rsplit(':').next().unwrap_or(t);
+                t.trim_matches(|c| c == '"' || c == '*').chars().count() >= 3
+            })
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // hiragana and katakana
+        | '\u{3400}'..='\u{4dbf}' // ideographs, extension a
+        | '\u{4e00}'..='\u{9fff}' // ideographs
+        | '\u{ac00}'..='\u{d7af}' // hangul syllables
+        | '\u{f900}'..='\u{faff}' // compatibility ideographs
+        | '\u{ff66}'..='\u{ff9f}' // halfwidth katakana
+    )
+}
+
+impl FromStr for SearchTerms {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut terms = SearchTerms::default();
+
+        for token in tokenize(s) {
+            if let Some(excluded) = token.strip_prefix('-').filter(|t| !t.is_empty()) {
+                terms.excluded.push(excluded.to_string());
+                continue;
+            }
+
+            let Some((key, value)) = token.split_once(':') else {
+                terms.text.push(token);
+                continue;
+            };
+            let value = value.trim_matches('"');
+
+            match key {
+                "kind" => terms
+                    .kinds
+                    .push(value.parse().map_err(|_| invalid(key, value))?),
+                "before" => terms.before = Some(value.parse().map_err(|_| invalid(key, value))?),
+                "after" => terms.after = Some(value.parse().map_err(|_| invalid(key, value))?),
+                "insignia" => {
+                    terms.insignia = Some(value.parse().map_err(|_| invalid(key, value))?)
+                }
+                "font" => terms.font = Some(value.parse().map_err(|_| invalid(key, value))?),
+                "ref" if !value.is_empty() => terms.refs.push(value.to_string()),
+                // e.g. an fts5 column filter
+                _ => terms.text.push(token),
+            }
+        }
+
+        Ok(terms)
+    }
+}
+
+fn invalid(key: &str, value: &str) -> Error {
+    Error::InvalidRequest {
+        message: format!("'{value}' isn't a valid value for {key}:"),
+        field: Some("q".to_string()),
+    }
+}
+
+// splits on whitespace that isn't within double quotes, the quotes are kept
+fn tokenize(s: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in s.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        if c.is_whitespace() && !quoted {
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(c);
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(q: &str) -> SearchTerms {
+        q.parse().unwrap()
+    }
+
+    #[test]
+    fn test_quoted_ref() {
+        let terms = parse(r#"ref:"Marcus Aurelius" virtue"#);
+        assert_eq!(terms.refs, vec!["Marcus Aurelius"]);
+        assert_eq!(terms.text, vec!["virtue"]);
+        assert!(terms.has_filters());
+    }
+
+    #[test]
+    fn test_repeated_kind() {
+        let terms = parse("kind:person kind:idea stoic");
+        assert_eq!(terms.kinds, vec![DeckKind::Person, DeckKind::Idea]);
+        assert_eq!(terms.text, vec!["stoic"]);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        for q in [
+            "kind:planet",
+            "before:soon",
+            "after:1800s",
+            "insignia:gold",
+            "font:comic",
+        ] {
+            let result: Result<SearchTerms, Error> = q.parse();
+            assert!(
+                matches!(result, Err(Error::InvalidRequest { .. })),
+                "{q} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_excluded_terms() {
+        let terms = parse("stoic -fate -luck");
+        assert_eq!(terms.text, vec!["stoic"]);
+        assert_eq!(terms.excluded, vec!["fate", "luck"]);
+        assert!(!terms.has_filters());
+
+        // a lone dash isn't excluding anything
+        assert_eq!(parse("-").text, vec!["-"]);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_text() {
+        let terms = parse("title:stoic before:1800");
+        assert_eq!(terms.text, vec!["title:stoic"]);
+        assert_eq!(terms.before, Some(1800));
+    }
+
+    #[test]
+    fn test_fts() {
+        assert_eq!(parse("stoic virtue").fts(), "stoic virtue");
+        assert_eq!(
+            parse("stoic virtue -fate").fts(),
+            "(stoic virtue) NOT (fate)"
+        );
+        assert_eq!(parse("-fate").fts(), "");
+
+        let terms = parse("-fate -luck");
+        assert_eq!(terms.excluded_fts(), "fate OR luck");
+        assert_eq!(parse("stoic").excluded_fts(), "");
+    }
+
+    #[test]
+    fn test_has_cjk() {
+        assert!(parse("東京").has_cjk());
+        assert!(parse("ひらがな").has_cjk());
+        assert!(parse("서울").has_cjk());
+        assert!(parse("tokyo -東京").has_cjk());
+        assert!(!parse("Zürich café").has_cjk());
+        assert!(!parse("kind:person").has_cjk());
+    }
+
+    #[test]
+    fn test_suits_trigrams() {
+        assert!(parse("東京都").suits_trigrams());
+        assert!(parse("philosophy -art").suits_trigrams());
+        assert!(parse(r#""東京都" OR 大阪府*"#).suits_trigrams());
+        assert!(!parse("東京").suits_trigrams());
+        assert!(!parse("哲学 philosophy").suits_trigrams());
+        assert!(!parse("philosophy -日本").suits_trigrams());
+        // the operators aren't terms
+        assert!(parse("東京都 AND 大阪府").suits_trigrams());
+    }
 }
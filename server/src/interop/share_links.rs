@@ -0,0 +1,53 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interop::Key;
+use crate::interop::decks::{Arrival, SlimDeck};
+use crate::interop::notes::Note;
+use crate::interop::points::Point;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub id: Key,
+    pub deck_id: Key,
+    pub token: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub revoked: bool,
+    pub include_arrivals: bool,
+    pub view_count: i32,
+    pub last_viewed_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtoShareLink {
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub include_arrivals: bool,
+}
+
+// what's served to anyone with the link's token
+//
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicDeck {
+    pub deck: SlimDeck,
+    pub notes: Vec<Note>,
+    pub points: Vec<Point>,
+    pub arrivals: Vec<Arrival>,
+}
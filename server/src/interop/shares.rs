@@ -0,0 +1,69 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interop::Key;
+use crate::interop::decks::{Arrival, SlimDeck};
+use crate::interop::notes::Note;
+
+// marks a deck as belonging to another user who has shared it
+//
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Foreign {
+    pub owner: String,
+    pub read_only: bool,
+}
+
+// a grant of read-only access to a deck. If include_subtree is set then the recipient can also see
+// the decks that it refers to, and the decks that they refer to, stopping at graph terminators
+//
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Share {
+    pub id: Key,
+    pub deck_id: Key,
+    pub deck_name: String,
+    pub recipient_email: String,
+    pub include_subtree: bool,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtoShare {
+    pub email: String,
+    pub include_subtree: bool,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignDeck {
+    pub deck: SlimDeck,
+    pub foreign: Foreign,
+}
+
+// a deck that's been shared with the user, only the notes and arrivals that
+// the user is allowed to see are included
+//
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedDeck {
+    pub deck: SlimDeck,
+    pub foreign: Foreign,
+    pub notes: Vec<Note>,
+    pub arrivals: Vec<Arrival>,
+}
@@ -0,0 +1,199 @@
+// Copyright (C) 2026 Inderjit Gill <email@indy.io>
+
+// This file is part of Civil
+
+// Civil is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Civil is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::interop::Key;
+use crate::interop::decks::{RefKind, SlimDeck};
+use crate::interop::font::Font;
+use crate::interop::memorise::FlashCard;
+use crate::interop::notes::{Note, NoteKind, ProtoNote};
+use crate::interop::points::PointKind;
+use crate::interop::references::ReferencesDiff;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+
+// the kinds of thing that a client can keep an offline copy of
+//
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncEntity {
+    Deck,
+    Note,
+    Ref,
+    Point,
+    Card,
+}
+
+impl SyncEntity {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            SyncEntity::Deck => "deck",
+            SyncEntity::Note => "note",
+            SyncEntity::Ref => "ref",
+            SyncEntity::Point => "point",
+            SyncEntity::Card => "card",
+        }
+    }
+}
+
+impl FromSql for SyncEntity {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "deck" => Ok(SyncEntity::Deck),
+            "note" => Ok(SyncEntity::Note),
+            "ref" => Ok(SyncEntity::Ref),
+            "point" => Ok(SyncEntity::Point),
+            "card" => Ok(SyncEntity::Card),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for SyncEntity {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncNote {
+    pub id: Key,
+    pub deck_id: Key,
+    pub prev_note_id: Option<Key>,
+    pub point_id: Option<Key>,
+    pub kind: NoteKind,
+    pub content: String,
+    pub font: Font,
+    pub private: bool,
+}
+
+// refs are synced per note: a change to any of a note's refs sends all of them
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncRef {
+    pub deck_id: Key,
+    pub ref_kind: RefKind,
+    pub annotation: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPoint {
+    pub id: Key,
+    pub deck_id: Key,
+    pub kind: PointKind,
+    pub title: String,
+    pub font: Font,
+
+    pub location_textual: Option<String>,
+    pub longitude: Option<f32>,
+    pub latitude: Option<f32>,
+    pub location_fuzz: f32,
+
+    pub date_textual: Option<String>,
+    pub exact_date: Option<chrono::NaiveDate>,
+    pub lower_date: Option<chrono::NaiveDate>,
+    pub upper_date: Option<chrono::NaiveDate>,
+    pub date_fuzz: f32,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+pub enum SyncData {
+    Deck(SlimDeck),
+    Note(SyncNote),
+    Refs(Vec<SyncRef>),
+    Point(SyncPoint),
+    Card(FlashCard),
+}
+
+// the current state of something that has changed. data is None when it has
+// been deleted. Deleting a deck also deletes its notes and points, and deleting
+// a note also deletes its refs and cards, so clients should drop those as well
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncChange {
+    pub seq: i64,
+    pub entity: SyncEntity,
+    pub id: Key,
+    pub data: Option<SyncData>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncDelta {
+    // pass this as the next request's 'since'
+    pub seq: i64,
+    pub changes: Vec<SyncChange>,
+    pub has_more: bool,
+}
+
+// an edit that was made while offline. Decks and points are synced down
+// but are still edited through their own endpoints
+//
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SyncOp {
+    CreateNote { note: ProtoNote },
+    EditNote { id: Key, note: Note },
+    DeleteNote { id: Key },
+    EditRefs { note_id: Key, diff: ReferencesDiff },
+    EditCard { id: Key, card: FlashCard },
+    RateCard { id: Key, rating: i16 },
+    DeleteCard { id: Key },
+}
+
+// since is the seq of the last delta that the client applied before making these edits
+//
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncUpload {
+    pub since: i64,
+    pub ops: Vec<SyncOp>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncStatus {
+    Applied,
+    // changed on the server after 'since', the op wasn't applied
+    Conflict,
+    // doesn't exist or belongs to another user
+    Missing,
+    Invalid,
+    // the op hit an error and was rolled back, the other ops in the upload still applied
+    Failed,
+}
+
+// one outcome per op, in the same order as the upload. On a conflict 'current'
+// is the server's copy so that the client can merge its edit into it
+//
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOutcome {
+    pub status: SyncStatus,
+    pub created: Vec<Key>,
+    pub current: Option<SyncChange>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncUploadResult {
+    pub seq: i64,
+    pub outcomes: Vec<SyncOutcome>,
+}
@@ -33,6 +33,7 @@ pub struct Timeline {
     pub insignia: i32,
     pub font: Font,
     pub impact: i32,
+    pub version: i32,
 
     pub points: Vec<Point>,
 
@@ -53,6 +54,7 @@ impl From<crate::db::decks::DeckBase> for Timeline {
             insignia: d.insignia,
             font: d.font,
             impact: d.impact,
+            version: d.version,
 
             points: vec![],
 
@@ -18,6 +18,7 @@
 pub mod ai;
 pub mod db;
 mod error;
+pub mod fsck_api;
 mod handler;
 pub mod interop;
 pub mod note_parser_api;
@@ -15,7 +15,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::error::{Error, ErrorBody};
+use crate::handler::admin;
+use crate::handler::aliases;
 use crate::handler::articles;
+use crate::handler::audit;
 use crate::handler::bookmarks;
 use crate::handler::concepts;
 use crate::handler::decks;
@@ -24,20 +28,28 @@ use crate::handler::events;
 use crate::handler::graph;
 use crate::handler::ideas;
 use crate::handler::interval;
+use crate::handler::live;
 use crate::handler::memorise;
 use crate::handler::notes;
 use crate::handler::people;
 use crate::handler::predictions;
 use crate::handler::quotes;
+use crate::handler::saved_searches;
 use crate::handler::search;
+use crate::handler::share_links;
+use crate::handler::shares;
+use crate::handler::sync;
+use crate::handler::tags;
 use crate::handler::timelines;
 use crate::handler::ubersetup;
 use crate::handler::uploader;
 use crate::handler::users;
 use actix_files::NamedFile;
+use actix_web::error::{JsonPayloadError, QueryPayloadError};
+use actix_web::http::header::CONTENT_TYPE;
 use actix_web::middleware::ErrorHandlerResponse;
 use actix_web::web::{delete, get, post, put, scope};
-use actix_web::{Responder, dev};
+use actix_web::{HttpRequest, HttpResponse, Responder, dev};
 use std::env;
 use tracing::warn;
 
@@ -53,7 +65,19 @@ pub fn public_api(mount_point: &str) -> actix_web::Scope {
             scope("/search")
                 .route("/decks", get().to(search::search_at_deck_level))
                 .route("/names", get().to(search::search_names_at_deck_level))
-                .route("/full", get().to(search::search_at_all_levels)),
+                .route("/full", get().to(search::search_at_all_levels))
+                .route("/ranking", get().to(search::get_ranking))
+                .route("/ranking", put().to(search::edit_ranking))
+                .route("/language", get().to(search::get_language))
+                .route("/language", put().to(search::edit_language))
+                .route("/saved", get().to(saved_searches::get_saved_searches))
+                .route("/saved", post().to(saved_searches::create_saved_search))
+                .route("/saved/{id}", get().to(saved_searches::run_saved_search))
+                .route("/saved/{id}", put().to(saved_searches::edit_saved_search))
+                .route(
+                    "/saved/{id}",
+                    delete().to(saved_searches::delete_saved_search),
+                ),
         )
         .service(
             scope("/decks")
@@ -64,14 +88,50 @@ pub fn public_api(mount_point: &str) -> actix_web::Scope {
                     "/{id}/additional_search",
                     get().to(search::additional_search_for_decks),
                 )
+                .route("/{id}/merge-into/{target}", post().to(decks::merge_into))
+                .route("/{id}/convert/{kind}", put().to(decks::convert))
+                .route("/{id}/aliases", get().to(aliases::get_aliases))
+                .route("/{id}/aliases", post().to(aliases::create_alias))
+                .route("/{id}/aliases/{alias_id}", put().to(aliases::edit_alias))
+                .route(
+                    "/{id}/aliases/{alias_id}",
+                    delete().to(aliases::delete_alias),
+                )
+                .route("/{id}/tags", get().to(tags::get_deck_tags))
+                .route("/{id}/tags", post().to(tags::add_deck_tag))
+                .route("/{id}/tags/{tag_id}", delete().to(tags::remove_deck_tag))
+                .route(
+                    "/{id}/private-notes",
+                    get().to(notes::get_private_notes_default),
+                )
+                .route(
+                    "/{id}/private-notes",
+                    put().to(notes::set_private_notes_default),
+                )
+                .route("/{id}/shares", get().to(shares::get_deck_shares))
+                .route("/{id}/shares", post().to(shares::create_deck_share))
+                .route("/{id}/links", get().to(share_links::get_share_links))
+                .route("/{id}/links", post().to(share_links::create_share_link))
+                .route(
+                    "/{id}/links/{link_id}",
+                    delete().to(share_links::revoke_share_link),
+                )
+                .route("/{id}/related", get().to(decks::related))
                 .route("/hits/{id}", get().to(decks::hits))
                 .route("/summarize/{id}", post().to(decks::summarize))
                 .route("/preview/{id}", get().to(decks::preview)),
         )
-        .service(scope("/interval").route(
-            "points-within-years/{lower}/{upper}",
-            get().to(interval::get_points),
-        ))
+        .service(
+            scope("/interval")
+                .route(
+                    "points-within-years/{lower}/{upper}",
+                    get().to(interval::get_points),
+                )
+                .route(
+                    "points-within-area",
+                    get().to(interval::get_points_within_area),
+                ),
+        )
         .service(
             scope("/users")
                 .route("/ui_config", put().to(users::edit_ui_config))
@@ -189,7 +249,36 @@ pub fn public_api(mount_point: &str) -> actix_web::Scope {
                 .route("", post().to(notes::create_notes))
                 .route("/{id}", put().to(notes::edit_note))
                 .route("/{id}", delete().to(notes::delete_note))
-                .route("/{id}/references", put().to(notes::edit_references)),
+                .route("/{id}/references", put().to(notes::edit_references))
+                .route("/{id}/move-up", post().to(notes::move_note_up))
+                .route("/{id}/move-down", post().to(notes::move_note_down))
+                .route(
+                    "/{id}/move-to-deck/{deck_id}",
+                    post().to(notes::move_note_to_deck),
+                )
+                .route("/{id}/private", put().to(notes::set_note_private))
+                .route("/{id}/split", post().to(notes::split_note))
+                .route(
+                    "/{id}/merge-with-next",
+                    post().to(notes::merge_note_with_next),
+                )
+                .route("/{id}/tags", get().to(tags::get_note_tags))
+                .route("/{id}/tags", post().to(tags::add_note_tag))
+                .route("/{id}/tags/{tag_id}", delete().to(tags::remove_note_tag)),
+        )
+        .service(
+            scope("/tags")
+                .route("", get().to(tags::get_tags))
+                .route("", post().to(tags::create_tag))
+                .route("/{id}", put().to(tags::edit_tag))
+                .route("/{id}", delete().to(tags::delete_tag)),
+        )
+        .service(
+            scope("/shares")
+                .route("", get().to(shares::get_shares))
+                .route("/received", get().to(shares::get_received))
+                .route("/decks/{id}", get().to(shares::get_shared_deck))
+                .route("/{id}", delete().to(shares::delete_share)),
         )
         .service(
             scope("/bookmarks")
@@ -207,6 +296,14 @@ pub fn public_api(mount_point: &str) -> actix_web::Scope {
                 .route("/{id}", put().to(memorise::edit))
                 .route("/{id}", delete().to(memorise::delete)),
         )
+        .service(scope("/admin").route("/fts/{command}", post().to(admin::fts)))
+        .service(scope("/audit").route("", get().to(audit::get_events)))
+        .service(
+            scope("/sync")
+                .route("", get().to(sync::get_delta))
+                .route("", post().to(sync::upload)),
+        )
+        .service(scope("/live").route("", get().to(live::stream)))
         .service(scope("/ubersetup").route("", get().to(ubersetup::setup)))
         .service(scope("/graph").route("/{id}", get().to(graph::get)))
         .service(
@@ -217,7 +314,63 @@ pub fn public_api(mount_point: &str) -> actix_web::Scope {
         )
 }
 
+// unauthenticated, read-only access to a single deck through a share link
+//
+pub fn share_links_api(mount_point: &str) -> actix_web::Scope {
+    scope(mount_point).route("/{token}", get().to(share_links::view_share_link))
+}
+
+// a json body that doesn't deserialize, the field at fault is given if serde names it
+//
+pub fn json_error(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match err {
+        JsonPayloadError::Deserialize(e) => invalid_request(e.to_string()).into(),
+        e => e.into(),
+    }
+}
+
+pub fn query_error(err: QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    invalid_request(err.to_string()).into()
+}
+
+fn invalid_request(message: String) -> Error {
+    let field = message.find("field `").and_then(|start| {
+        let name = &message[start + "field `".len()..];
+        name.find('`').map(|end| name[..end].to_string())
+    });
+
+    Error::InvalidRequest { message, field }
+}
+
+fn is_api_request(req: &HttpRequest) -> bool {
+    req.path() == "/api" || req.path().starts_with("/api/")
+}
+
+// errors from /api are always json, even the ones that didn't come from a
+// handler (e.g. a route that doesn't exist or a body that's too large)
+//
+pub fn api_error<B>(res: dev::ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let is_json = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|ct| ct.as_bytes().starts_with(b"application/json"));
+
+    if !is_api_request(res.request()) || is_json {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    let status = res.status();
+    warn!("api error: {:?} {:?}", &status, &res.request());
+    let new_resp = HttpResponse::build(status)
+        .json(ErrorBody::for_status(status))
+        .map_into_right_body();
+    Ok(ErrorHandlerResponse::Response(res.into_response(new_resp)))
+}
+
 pub fn bad_request<B>(res: dev::ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    if is_api_request(res.request()) {
+        return api_error(res);
+    }
     warn!("bad request: {:?} {:?}", &res.status(), &res.request());
     let www = env::var("WWW_PATH").expect("unable to resolve WWW_PATH");
     let new_resp = NamedFile::open(format!("{}/errors/400.html", www))?
@@ -230,6 +383,9 @@ pub fn bad_request<B>(res: dev::ServiceResponse<B>) -> actix_web::Result<ErrorHa
 }
 
 pub fn not_found<B>(res: dev::ServiceResponse<B>) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    if is_api_request(res.request()) {
+        return api_error(res);
+    }
     warn!("not found: {:?} {:?}", &res.status(), &res.request());
     let www = env::var("WWW_PATH").expect("unable to resolve WWW_PATH");
     let new_resp = NamedFile::open(format!("{}/errors/404.html", www))?
@@ -244,6 +400,9 @@ pub fn not_found<B>(res: dev::ServiceResponse<B>) -> actix_web::Result<ErrorHand
 pub fn internal_server_error<B>(
     res: dev::ServiceResponse<B>,
 ) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    if is_api_request(res.request()) {
+        return api_error(res);
+    }
     warn!(
         "internal server error: {:?} {:?}",
         &res.status(),
@@ -259,3 +418,127 @@ pub fn internal_server_error<B>(
         .map_into_right_body();
     Ok(ErrorHandlerResponse::Response(res.into_response(new_resp)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::ErrorHandlers;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use actix_web::{App, web};
+    use serde_json::{Value, json};
+
+    #[derive(serde::Deserialize)]
+    struct Titled {
+        title: String,
+    }
+
+    async fn titled(titled: web::Json<Titled>) -> crate::Result<HttpResponse> {
+        Ok(HttpResponse::Ok().body(titled.into_inner().title))
+    }
+
+    async fn conflict() -> crate::Result<HttpResponse> {
+        Err(Error::VersionConflict(json!({"version": 2})))
+    }
+
+    async fn failure() -> crate::Result<HttpResponse> {
+        Err(Error::SqliteStringConversion)
+    }
+
+    async fn plain_failure() -> HttpResponse {
+        HttpResponse::InternalServerError().body("oops")
+    }
+
+    async fn call(req: TestRequest) -> (StatusCode, Value) {
+        let error_handlers = ErrorHandlers::new()
+            .handler(StatusCode::INTERNAL_SERVER_ERROR, internal_server_error)
+            .handler(StatusCode::BAD_REQUEST, bad_request)
+            .handler(StatusCode::NOT_FOUND, not_found)
+            .default_handler(api_error);
+        let app = init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().error_handler(json_error))
+                .wrap(error_handlers)
+                .service(
+                    scope("/api")
+                        .route("/titled", post().to(titled))
+                        .route("/conflict", get().to(conflict))
+                        .route("/failure", get().to(failure))
+                        .route("/plain-failure", get().to(plain_failure)),
+                ),
+        )
+        .await;
+
+        let res = call_service(&app, req.to_request()).await;
+        let status = res.status();
+        let is_json = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .is_some_and(|ct| ct.as_bytes().starts_with(b"application/json"));
+        assert!(is_json, "{} isn't json", status);
+
+        (status, read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_json_is_a_400_naming_the_field() {
+        let req = TestRequest::post()
+            .uri("/api/titled")
+            .set_json(json!({"name": "Stoicism"}));
+        let (status, body) = call(req).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_request");
+        assert_eq!(body["fields"][0]["field"], "title");
+    }
+
+    #[actix_web::test]
+    async fn test_unknown_api_route_is_a_json_404() {
+        let (status, body) = call(TestRequest::get().uri("/api/missing")).await;
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "not found");
+    }
+
+    #[actix_web::test]
+    async fn test_version_conflict_is_a_409_with_the_current_copy() {
+        let (status, body) = call(TestRequest::get().uri("/api/conflict")).await;
+
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["code"], "version_conflict");
+        assert_eq!(body["current"]["version"], 2);
+    }
+
+    #[actix_web::test]
+    async fn test_internal_errors_are_a_json_500_without_details() {
+        let (status, body) = call(TestRequest::get().uri("/api/failure")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "internal");
+        assert_eq!(body["message"], "internal server error");
+
+        // a 500 that didn't come from an Error is replaced with json as well
+        let (status, body) = call(TestRequest::get().uri("/api/plain-failure")).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "internal");
+    }
+
+    #[test]
+    fn test_invalid_request_field() {
+        let field = |message: &str| match invalid_request(message.to_string()) {
+            Error::InvalidRequest { field, .. } => field,
+            _ => panic!("expected an invalid request"),
+        };
+
+        assert_eq!(
+            field("missing field `title` at line 1 column 2"),
+            Some("title".to_string())
+        );
+        assert_eq!(
+            field("unknown field `nmae`, expected `name` or `kind`"),
+            Some("nmae".to_string())
+        );
+        assert_eq!(field("expected value at line 1 column 1"), None);
+        assert_eq!(field("missing field `unterminated"), None);
+    }
+}
@@ -26,11 +26,12 @@ mod parser;
 
 use compiler::compile_to_struct;
 use lexer::tokenize;
-use parser::{parse, Node};
+use parser::{get_node_pos, parse};
 
 pub use colour::{Hsluv, Rgb};
 pub use element::Element;
 pub use error::Error;
+pub use parser::Node;
 
 pub type Result<T> = ::std::result::Result<T, error::Error>;
 
@@ -44,6 +45,27 @@ pub fn markup_as_ast(markup: &str) -> crate::Result<Vec<Node>> {
     Ok(nodes)
 }
 
+// split the given markup into the text of each of its top level sections,
+// mirrors the splitter that the client uses when creating notes
+//
+pub fn split_markup(markup: &str) -> crate::Result<Vec<String>> {
+    let nodes = markup_as_ast(markup)?;
+
+    // node positions are in chars rather than bytes
+    let byte_offset = |char_pos: usize| markup.char_indices().nth(char_pos).map_or(markup.len(), |(i, _)| i);
+
+    let mut split_points: Vec<usize> = nodes.iter().map(|n| byte_offset(get_node_pos(n))).collect();
+    split_points.push(markup.len());
+
+    let sections = split_points
+        .windows(2)
+        .map(|w| markup[w[0]..w[1]].trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    Ok(sections)
+}
+
 // return an AST that's easily convertible into
 // HTML/Plain Text/Preact components
 //
@@ -54,3 +76,58 @@ pub fn markup_as_struct(markup: &str, note_id: usize) -> crate::Result<Vec<Eleme
 
     Ok(html)
 }
+
+// a plain text version of the markup without any of its margin content, used as the input to
+// AI systems. Mirrors buildSimplifiedText in the client
+//
+pub fn markup_as_simplified_text(markup: &str) -> crate::Result<String> {
+    fn is_on_right_margin(element: &Element) -> bool {
+        element.class_name.as_ref().is_some_and(|c| c.contains("right-margin"))
+    }
+
+    fn collect_text(element: &Element, text: &mut Vec<String>) {
+        if is_on_right_margin(element) {
+            return;
+        }
+        if element.name == "li" {
+            text.push("\n".to_string());
+        }
+        if element.name == "text"
+            && let Some(t) = &element.text
+        {
+            text.push(t.to_string());
+        }
+        for child in &element.children {
+            collect_text(child, text);
+        }
+    }
+
+    let mut text = vec![];
+    for element in markup_as_struct(markup, 0)? {
+        collect_text(&element, &mut text);
+    }
+
+    Ok(text.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_markup() {
+        assert_eq!(
+            split_markup("first paragraph\n\nPutin’s second\n\n# a header\nthird").unwrap(),
+            vec!["first paragraph", "Putin’s second", "# a header", "third"]
+        );
+        assert_eq!(split_markup("just the one").unwrap(), vec!["just the one"]);
+    }
+
+    #[test]
+    fn test_markup_as_simplified_text() {
+        assert_eq!(markup_as_simplified_text("hello world").unwrap(), "hello world");
+        let text = markup_as_simplified_text("body text :side(margin text)").unwrap();
+        assert!(text.contains("body text"));
+        assert!(!text.contains("margin text"));
+    }
+}
@@ -109,7 +109,7 @@ pub enum Node {
     YouTube(usize, String, String),
 }
 
-fn get_node_pos(node: &Node) -> usize {
+pub(crate) fn get_node_pos(node: &Node) -> usize {
     match node {
         Node::BlockQuote(pos, _) => *pos,
         Node::Codeblock(pos, _) => *pos,